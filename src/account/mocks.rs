@@ -1,5 +1,7 @@
 use crate::account::Account;
+use crate::address_book::AddressBook;
 use crate::permissions::BitcoinPermissions;
+use crate::spending_limits::SpendingLimits;
 
 /// Creates an `Account` struct with send permissions.
 pub fn get_child_with_permissions_to_spend() -> Account{
@@ -10,6 +12,16 @@ pub fn get_child_with_permissions_to_spend() -> Account{
         addresses:  vec![],
         pending_transactions: vec![],
         bitcoin_transfered_from_master: 0,
+        spending_limits: SpendingLimits::default(),
+        spend_records: vec![],
+        approval_threshold: None,
+        address_book: AddressBook::default(),
+        change_addresses: vec![],
+        max_fee_rate: None,
+        account_index: None,
+        pending_cancellations: vec![],
+        pending_on_chain_transfers: vec![],
+        on_chain_transfers: vec![],
     };
     account_with_permissions
 }
@@ -23,6 +35,16 @@ pub fn get_child_without_permissions_to_spend() -> Account{
         addresses:  vec![],
         pending_transactions: vec![],
         bitcoin_transfered_from_master: 0,
+        spending_limits: SpendingLimits::default(),
+        spend_records: vec![],
+        approval_threshold: None,
+        address_book: AddressBook::default(),
+        change_addresses: vec![],
+        max_fee_rate: None,
+        account_index: None,
+        pending_cancellations: vec![],
+        pending_on_chain_transfers: vec![],
+        on_chain_transfers: vec![],
     };
     account_without_permissions
 }
\ No newline at end of file
@@ -1,14 +1,19 @@
 pub mod mocks;
-use bdk::{bitcoin::{Address, Script}, TransactionDetails};
+use bdk::{bitcoin::{Address, OutPoint, Script, Txid}, TransactionDetails};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
 
+use crate::address_book::AddressBook;
 use crate::permissions::BitcoinPermissions;
+use crate::spending_limits::{SpendRecord, SpendTally, SpendingLimits, SpendingPeriod};
 
 /// An `Account` struct is used to determine the details of a users wallet state.
 /// 
 /// An `Account` contains information around the users permissions, bitcoin addresses, transactions that are still pending,
 /// total amount of bitcoin recieved as well as bitcoin transfered from the master account. Essentially all bitcoin related 
 /// information that user needs to spend, receive, and hold bitcoin.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
 pub struct Account {
     pub bitcoin_amount: u64,
     pub account_id: i32,
@@ -16,13 +21,70 @@ pub struct Account {
     pub addresses: Vec<Address>,
     pub pending_transactions:Vec<TransactionDetails>,
     pub bitcoin_transfered_from_master: u64,
+    #[serde(default)]
+    pub spending_limits: SpendingLimits,
+    /// Recent spends, used to enforce the daily, weekly and monthly `spending_limits`.
+    #[serde(default)]
+    pub spend_records: Vec<SpendRecord>,
+    /// Spends above this amount must be approved by the head of the house before they are broadcast.
+    #[serde(default)]
+    pub approval_threshold: Option<u64>,
+    /// Labelled destinations, the only addresses the `Account` may send to if it has the `SendToWhitelistOnly` permission.
+    #[serde(default)]
+    pub address_book: AddressBook,
+    /// Addresses the change of the `Account`'s spends is sent to.
+    #[serde(default)]
+    pub change_addresses: Vec<Address>,
+    /// The highest fee rate, in sat/vB, the `Account`'s spends may use.
+    #[serde(default)]
+    pub max_fee_rate: Option<f32>,
+    /// The BIP84 account index of the `Account`'s sub-account wallet, `None` if its addresses come from the `MasterAccount`'s own wallet.
+    #[serde(default)]
+    pub account_index: Option<u32>,
+    /// Cancellations of the `Account`'s spends whose replacement has not been confirmed yet.
+    #[serde(default)]
+    pub pending_cancellations: Vec<PendingCancellation>,
+    /// Transfers of the `MasterAccount`'s coins to the `Account`'s addresses which have not been confirmed yet.
+    #[serde(default)]
+    pub pending_on_chain_transfers: Vec<PendingOnChainTransfer>,
+    /// The coins the `MasterAccount` sent to the `Account`'s addresses whose transfer has been confirmed, counted in
+    /// `bitcoin_transfered_from_master` rather than in the `Account`'s utxo balance.
+    #[serde(default)]
+    pub on_chain_transfers: Vec<OutPoint>,
+}
+
+/// A spend which has been cancelled by a replacement paying its coins back to the household.
+/// 
+/// Until the replacement is confirmed the original spend could still be confirmed instead, so the bitcoin transfered from the
+/// `MasterAccount` which the original spend used is only given back to the `Account` once the replacement is confirmed.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct PendingCancellation {
+    /// The txid of the replacement.
+    pub txid: Txid,
+    /// The bitcoin transfered from the `MasterAccount` which the cancelled spend used.
+    pub transfered_bitcoin_to_restore: u64,
+}
+
+/// A transfer which sent the `MasterAccount`'s coins to one of the `Account`'s addresses.
+/// 
+/// Until the transfer is confirmed it could be dropped or replaced, so the coins are only counted as transfered to the
+/// `Account` once it is confirmed.
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct PendingOnChainTransfer {
+    /// The output of the transfer paying the `Account`.
+    pub outpoint: OutPoint,
+    /// The amount sent to the `Account`.
+    pub amount: u64,
 }
 
 impl Account {
     /// Generates a new `Account` struct.
     /// 
-    /// Since it is a new account the `addresses`, `pending_transactions` and `bitcoin_transfered_from_master`
-    /// will all be empty.
+    /// Since it is a new account the `addresses`, `change_addresses`, `pending_transactions`, `pending_cancellations`, `pending_on_chain_transfers`,
+    /// `on_chain_transfers`, `bitcoin_transfered_from_master` and `spend_records` will all be empty, and it will have no `spending_limits`
+    /// or `approval_threshold`.
     pub fn new(bitcoin_amount: u64, account_id: i32, permissions: Vec<BitcoinPermissions>)-> Account {
         let new_account = Account {
             bitcoin_amount: bitcoin_amount,
@@ -31,6 +93,16 @@ impl Account {
             addresses:Vec::new(),
             pending_transactions: Vec::new(),
             bitcoin_transfered_from_master: 0,
+            spending_limits: SpendingLimits::default(),
+            spend_records: Vec::new(),
+            approval_threshold: None,
+            address_book: AddressBook::default(),
+            change_addresses: Vec::new(),
+            max_fee_rate: None,
+            account_index: None,
+            pending_cancellations: Vec::new(),
+            pending_on_chain_transfers: Vec::new(),
+            on_chain_transfers: Vec::new(),
         };
         new_account
     }
@@ -45,15 +117,38 @@ impl Account {
         }
      }
      
-     /// Determine if an `Account` has the Spent permission enabled.
+     /// Determine if an `Account` has the Spent permission enabled, either to any address or only to whitelisted addresses.
      pub fn has_permission_to_spend(&self)-> bool{
-         let mut has_permission_to_spend = false;
-         for permission in &self.permissions {
-             if *permission == BitcoinPermissions::Send{
-                 has_permission_to_spend = true
-             }
+         self.has_permission(BitcoinPermissions::Send) || self.has_permission(BitcoinPermissions::SendToWhitelistOnly)
+     }
+
+     /// Determine if an `Account` has a permission.
+     pub fn has_permission(&self, permission: BitcoinPermissions)-> bool{
+         self.permissions.contains(&permission)
+     }
+
+     /// Determine if an `Account` may send to an address.
+     /// 
+     /// An `Account` with the `SendToWhitelistOnly` permission may only send to addresses in its address book,
+     /// even if it also has the `Send` permission.
+     pub fn can_send_to_address(&self, address: &Address)-> bool{
+         if self.has_permission(BitcoinPermissions::SendToWhitelistOnly) {
+             self.address_book.contains(address)
+         } else {
+             self.has_permission(BitcoinPermissions::Send)
          }
-         has_permission_to_spend 
+     }
+
+     /// Give the `Account` a permission, if it does not already have it.
+     pub fn grant_permission(&mut self, permission: BitcoinPermissions){
+         if !self.has_permission(permission) {
+             self.permissions.push(permission);
+         }
+     }
+
+     /// Take a permission away from the `Account`.
+     pub fn revoke_permission(&mut self, permission: BitcoinPermissions){
+         self.permissions.retain(|account_permission| *account_permission != permission);
      }
 
      /// Reduce the `Account`'s current bitcoin amount.
@@ -69,16 +164,51 @@ impl Account {
         self.addresses.push(new_address);
      }
 
+     /// Add an address the change of a spend by the `Account` is sent to.
+     pub fn add_change_address(&mut self, change_address:Address){
+        self.change_addresses.push(change_address);
+     }
+
      /// Add a transaction to the `Account`'s list of pending_transactions.
      pub fn add_pending_transaction(&mut self, pending_transaction: TransactionDetails){
-        println!("we are adding a pending transaction {:?}", pending_transaction);
+        debug!(account_id = self.account_id, txid = %pending_transaction.txid, "adding pending transaction");
         self.pending_transactions.push(pending_transaction);
      }
 
+     /// Swap the pending transaction `replaced_txid` for its `replacement`, moving any cancellation waiting for it to the replacement.
+     pub fn replace_pending_transaction(&mut self, replaced_txid: Txid, replacement: TransactionDetails){
+        self.pending_transactions.retain(|transaction_details| transaction_details.txid != replaced_txid);
+        for pending_cancellation in self.pending_cancellations.iter_mut().filter(|pending_cancellation| pending_cancellation.txid == replaced_txid) {
+            pending_cancellation.txid = replacement.txid;
+        }
+        self.add_pending_transaction(replacement);
+     }
+
+     /// Add the cancellation of one of the `Account`'s spends, waiting for its replacement to be confirmed.
+     pub fn add_pending_cancellation(&mut self, pending_cancellation: PendingCancellation){
+        debug!(account_id = self.account_id, txid = %pending_cancellation.txid, "adding pending cancellation");
+        self.pending_cancellations.push(pending_cancellation);
+     }
+
+     /// Add a transfer of the `MasterAccount`'s coins to the `Account`'s addresses, waiting to be confirmed.
+     pub fn add_pending_on_chain_transfer(&mut self, pending_on_chain_transfer: PendingOnChainTransfer){
+        debug!(account_id = self.account_id, outpoint = %pending_on_chain_transfer.outpoint, "adding pending on chain transfer");
+        self.pending_on_chain_transfers.push(pending_on_chain_transfer);
+     }
+
+     /// Get the coins sent to the `Account` by on chain transfers from the `MasterAccount`, confirmed or not, which are not
+     /// counted in its utxo balance.
+     pub fn get_on_chain_transfer_outpoints(&self)-> Vec<OutPoint> {
+        self.pending_on_chain_transfers.iter()
+            .map(|pending_on_chain_transfer| pending_on_chain_transfer.outpoint)
+            .chain(self.on_chain_transfers.iter().copied())
+            .collect()
+     }
+
      /// Get an array of bitcoin scripts associated with each `Account`'s address.
      /// 
      /// Each bitcoin address is derived into a bitcoin locking script.
-     /// This function will look at the Account's bitcoin addresses and generate the associated script.
+     /// This function will look at the Account's bitcoin addresses, including its change addresses, and generate the associated script.
      /// 
      /// # Examples 
      /// This will convert a bitcoin address like
@@ -91,7 +221,7 @@ impl Account {
      /// ```
      /// 
      pub fn get_addresses_as_script_pub_keys(&self)-> Vec<Script>{
-        let addresses_as_script_pub_keys: Vec<Script> = self.addresses.iter().map(|address| address.script_pubkey()).collect();
+        let addresses_as_script_pub_keys: Vec<Script> = self.addresses.iter().chain(self.change_addresses.iter()).map(|address| address.script_pubkey()).collect();
         addresses_as_script_pub_keys
      }
 
@@ -106,6 +236,30 @@ impl Account {
         self.bitcoin_transfered_from_master -= amount;
      }
 
+     /// Get the total the `Account` has spent in each rolling spending period ending at `now`.
+     pub fn get_spend_tally(&self, now: u64)-> SpendTally{
+        SpendTally::from_spend_records(&self.spend_records, now)
+     }
+
+     /// Find the spending limit, if any, that spending `amount` at `now` would exceed.
+     pub fn get_exceeded_spending_period(&self, amount: u64, now: u64)-> Option<SpendingPeriod>{
+        self.spending_limits.exceeded_period(amount, &self.get_spend_tally(now))
+     }
+
+     /// Determine if spending `amount` must first be approved by the head of the house.
+     pub fn does_spend_require_approval(&self, amount: u64)-> bool{
+        match self.approval_threshold {
+            Some(approval_threshold) => amount > approval_threshold,
+            None => false,
+        }
+     }
+
+     /// Record a spend made at `now`, discarding records too old to count towards any spending limit.
+     pub fn record_spend(&mut self, amount: u64, now: u64){
+        self.spend_records.retain(|spend_record| !SpendingLimits::is_spend_record_expired(spend_record, now));
+        self.spend_records.push(SpendRecord { amount, timestamp: now });
+     }
+
  
 }
 
@@ -114,6 +268,7 @@ mod tests {
     use super::*;
     use mocks::{get_child_with_permissions_to_spend, get_child_without_permissions_to_spend};
     use crate::{testing_helpers::{set_up}, helpers::convert_float_to_satoshis};
+    use std::str::FromStr;
 
     #[test]
     fn has_permission_to_spend_returns_true_when_child_has_such_permission() {
@@ -129,6 +284,47 @@ mod tests {
 
         assert_eq!(child_with_permissions.has_permission_to_spend(), false)  
     }
+    #[test]
+    fn send_to_whitelist_only_restricts_an_account_with_send(){
+        set_up();
+        let mut child_with_permissions_to_spend = get_child_with_permissions_to_spend();
+        let whitelisted_address = Address::from_str("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20").unwrap();
+        let other_address = Address::from_str("bcrt1q8wmlcmsw6756u87swmx8ux0d8m2ukjjn9q9g4k").unwrap();
+        assert!(child_with_permissions_to_spend.can_send_to_address(&other_address));
+
+        child_with_permissions_to_spend.grant_permission(BitcoinPermissions::SendToWhitelistOnly);
+        child_with_permissions_to_spend.address_book.add(String::from("grandma"), whitelisted_address.clone()).unwrap();
+
+        assert!(child_with_permissions_to_spend.can_send_to_address(&whitelisted_address));
+        assert!(!child_with_permissions_to_spend.can_send_to_address(&other_address));
+
+        child_with_permissions_to_spend.revoke_permission(BitcoinPermissions::SendToWhitelistOnly);
+        assert!(child_with_permissions_to_spend.can_send_to_address(&other_address));
+    }
+
+    #[test]
+    fn script_pub_keys_include_change_addresses(){
+        set_up();
+        let mut child_with_permissions_to_spend = get_child_with_permissions_to_spend();
+        let receive_address = Address::from_str("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20").unwrap();
+        let change_address = Address::from_str("bcrt1q8wmlcmsw6756u87swmx8ux0d8m2ukjjn9q9g4k").unwrap();
+        child_with_permissions_to_spend.add_address(receive_address.clone());
+        child_with_permissions_to_spend.add_change_address(change_address.clone());
+
+        assert_eq!(child_with_permissions_to_spend.get_addresses_as_script_pub_keys(), vec![receive_address.script_pubkey(), change_address.script_pubkey()]);
+        assert_eq!(child_with_permissions_to_spend.addresses, vec![receive_address]);
+    }
+
+    #[test]
+    fn grant_permission_does_not_duplicate_permissions(){
+        set_up();
+        let mut child_with_permissions_to_spend = get_child_with_permissions_to_spend();
+        child_with_permissions_to_spend.grant_permission(BitcoinPermissions::Send);
+        child_with_permissions_to_spend.grant_permission(BitcoinPermissions::Receive);
+
+        assert_eq!(child_with_permissions_to_spend.permissions, vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+    }
+
     #[test]
     fn child_with_permission_sends_bitcoin_successfully(){
         set_up();
@@ -153,4 +349,56 @@ mod tests {
 
         assert_eq!(child_with_permissions_to_spend.bitcoin_amount, 100000000)
     }
+
+    #[test]
+    fn record_spend_counts_towards_spending_limits(){
+        set_up();
+        let mut child_with_permissions_to_spend = get_child_with_permissions_to_spend();
+        child_with_permissions_to_spend.spending_limits.daily = Some(10000);
+        child_with_permissions_to_spend.record_spend(6000, 1_700_000_000);
+
+        assert_eq!(child_with_permissions_to_spend.get_exceeded_spending_period(4000, 1_700_000_060), None);
+        assert_eq!(child_with_permissions_to_spend.get_exceeded_spending_period(4001, 1_700_000_060), Some(SpendingPeriod::Daily));
+        // a day later the spend no longer counts towards the daily limit
+        assert_eq!(child_with_permissions_to_spend.get_exceeded_spending_period(10000, 1_700_086_400), None);
+    }
+
+    #[test]
+    fn spends_above_approval_threshold_require_approval(){
+        set_up();
+        let mut child_with_permissions_to_spend = get_child_with_permissions_to_spend();
+        assert!(!child_with_permissions_to_spend.does_spend_require_approval(u64::MAX));
+
+        child_with_permissions_to_spend.approval_threshold = Some(10000);
+        assert!(!child_with_permissions_to_spend.does_spend_require_approval(10000));
+        assert!(child_with_permissions_to_spend.does_spend_require_approval(10001));
+    }
+
+    #[test]
+    fn record_spend_discards_spends_older_than_a_month(){
+        set_up();
+        let mut child_with_permissions_to_spend = get_child_with_permissions_to_spend();
+        child_with_permissions_to_spend.record_spend(6000, 1_700_000_000);
+        child_with_permissions_to_spend.record_spend(1000, 1_700_000_000 + 31 * 24 * 60 * 60);
+
+        assert_eq!(child_with_permissions_to_spend.spend_records.len(), 1);
+    }
+
+    #[test]
+    fn replacing_a_pending_transaction_moves_its_cancellation_to_the_replacement() {
+        set_up();
+        let mut account = get_child_with_permissions_to_spend();
+        let original_txid = Txid::from_str("6d7f7b1f9ab6a8ec2a3b67b2a1f0c7d5e7a6b9c8d1e2f3a4b5c6d7e8f9a0b1c2").unwrap();
+        let replacement_txid = Txid::from_str("0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b1c").unwrap();
+        let original = TransactionDetails { transaction: None, txid: original_txid, received: 0, sent: 10_000, fee: Some(200), confirmation_time: None };
+        account.add_pending_transaction(original.clone());
+        account.add_pending_cancellation(PendingCancellation { txid: original_txid, transfered_bitcoin_to_restore: 5_000 });
+
+        account.replace_pending_transaction(original_txid, TransactionDetails { txid: replacement_txid, fee: Some(1_000), ..original });
+
+        assert_eq!(account.pending_transactions.len(), 1);
+        assert_eq!(account.pending_transactions[0].txid, replacement_txid);
+        assert_eq!(account.pending_cancellations, vec![PendingCancellation { txid: replacement_txid, transfered_bitcoin_to_restore: 5_000 }]);
+    }
+
 }
@@ -0,0 +1,80 @@
+use bdk::bitcoin::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::AccountError;
+
+/// A labelled address an `Account` may pay, for example "grandma's wallet".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressBookEntry {
+    pub label: String,
+    pub address: Address,
+}
+
+/// An `Account`'s approved destinations.
+///
+/// If the `Account` has the `SendToWhitelistOnly` permission these are the only addresses it may send to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AddressBook {
+    pub entries: Vec<AddressBookEntry>,
+}
+
+impl AddressBook {
+    /// Add a labelled address to the book.
+    ///
+    /// # Errors
+    /// If the address, or another address with the same label, is already in the book return an `AccountError`.
+    pub fn add(&mut self, label: String, address: Address) -> Result<(), AccountError> {
+        let is_duplicate = self.entries.iter().any(|entry| entry.address == address || entry.label == label);
+        if is_duplicate {
+            return Err(AccountError::AddressBookEntryAlreadyExists)
+        }
+        self.entries.push(AddressBookEntry { label, address });
+        Ok(())
+    }
+
+    /// Remove an address from the book, returning its entry.
+    ///
+    /// # Errors
+    /// If the address is not in the book return an `AccountError`.
+    pub fn remove(&mut self, address: &Address) -> Result<AddressBookEntry, AccountError> {
+        let index = self.entries.iter().position(|entry| &entry.address == address).ok_or(AccountError::AddressBookEntryDoesNotExist)?;
+        Ok(self.entries.remove(index))
+    }
+
+    /// Return true if the address is in the book, else return false.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.entries.iter().any(|entry| &entry.address == address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn duplicate_addresses_and_labels_are_rejected(){
+        let mut address_book = AddressBook::default();
+        let grandma_address = Address::from_str("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20").unwrap();
+        let shop_address = Address::from_str("bcrt1q8wmlcmsw6756u87swmx8ux0d8m2ukjjn9q9g4k").unwrap();
+        address_book.add(String::from("grandma"), grandma_address.clone()).unwrap();
+
+        assert!(matches!(address_book.add(String::from("grandpa"), grandma_address.clone()), Err(AccountError::AddressBookEntryAlreadyExists)));
+        assert!(matches!(address_book.add(String::from("grandma"), shop_address.clone()), Err(AccountError::AddressBookEntryAlreadyExists)));
+        assert!(address_book.contains(&grandma_address));
+        assert!(!address_book.contains(&shop_address));
+    }
+
+    #[test]
+    fn removed_address_is_no_longer_in_the_book(){
+        let mut address_book = AddressBook::default();
+        let grandma_address = Address::from_str("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20").unwrap();
+        address_book.add(String::from("grandma"), grandma_address.clone()).unwrap();
+
+        let removed_entry = address_book.remove(&grandma_address).unwrap();
+
+        assert_eq!(removed_entry.label, String::from("grandma"));
+        assert!(!address_book.contains(&grandma_address));
+        assert!(matches!(address_book.remove(&grandma_address), Err(AccountError::AddressBookEntryDoesNotExist)));
+    }
+}
@@ -0,0 +1,318 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::AccountError;
+
+const SECONDS_IN_A_DAY: u64 = 24 * 60 * 60;
+
+/// A day of the week, used to schedule weekly allowances.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Days since monday, so monday is 0 and sunday is 6.
+    fn days_from_monday(&self) -> u64 {
+        match self {
+            Weekday::Monday => 0,
+            Weekday::Tuesday => 1,
+            Weekday::Wednesday => 2,
+            Weekday::Thursday => 3,
+            Weekday::Friday => 4,
+            Weekday::Saturday => 5,
+            Weekday::Sunday => 6,
+        }
+    }
+
+    /// Get the unix timestamp of the next midnight (UTC) falling on this weekday, after `now`.
+    pub fn next_occurrence(&self, now: u64) -> u64 {
+        let days_since_epoch = now / SECONDS_IN_A_DAY;
+        // the unix epoch, 1970-01-01, was a thursday
+        let today_days_from_monday = (days_since_epoch + 3) % 7;
+        let days_until_weekday = (7 + self.days_from_monday() - today_days_from_monday) % 7;
+        let days_until_next_occurrence = if days_until_weekday == 0 { 7 } else { days_until_weekday };
+        (days_since_epoch + days_until_next_occurrence) * SECONDS_IN_A_DAY
+    }
+}
+
+impl FromStr for Weekday {
+    type Err = String;
+
+    /// Parse a weekday from its lowercase name, for example "sunday".
+    fn from_str(weekday: &str) -> Result<Self, Self::Err> {
+        match weekday {
+            "monday" => Ok(Weekday::Monday),
+            "tuesday" => Ok(Weekday::Tuesday),
+            "wednesday" => Ok(Weekday::Wednesday),
+            "thursday" => Ok(Weekday::Thursday),
+            "friday" => Ok(Weekday::Friday),
+            "saturday" => Ok(Weekday::Saturday),
+            "sunday" => Ok(Weekday::Sunday),
+            _ => Err(format!("unknown weekday {}", weekday)),
+        }
+    }
+}
+
+/// How often an allowance is transfered.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AllowanceInterval {
+    Daily,
+    Weekly,
+    EveryDays(u64),
+}
+
+impl AllowanceInterval {
+    /// The length of the interval in seconds.
+    pub fn as_seconds(&self) -> u64 {
+        match self {
+            AllowanceInterval::Daily => SECONDS_IN_A_DAY,
+            AllowanceInterval::Weekly => 7 * SECONDS_IN_A_DAY,
+            AllowanceInterval::EveryDays(days) => days.max(&1) * SECONDS_IN_A_DAY,
+        }
+    }
+}
+
+impl FromStr for AllowanceInterval {
+    type Err = String;
+
+    /// Parse an interval from "daily", "weekly" or a number of days.
+    fn from_str(interval: &str) -> Result<Self, Self::Err> {
+        match interval {
+            "daily" => Ok(AllowanceInterval::Daily),
+            "weekly" => Ok(AllowanceInterval::Weekly),
+            days => match days.parse::<u64>() {
+                Ok(days) if days > 0 => Ok(AllowanceInterval::EveryDays(days)),
+                _ => Err(format!("unknown interval {}, expected daily, weekly or a number of days", interval)),
+            },
+        }
+    }
+}
+
+/// What to do with an allowance which is due when the `MasterAccount` can not cover it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InsufficientFundsPolicy {
+    /// Do not transfer this allowance, wait for the next one.
+    Skip,
+    /// Keep the allowance owed and retry it every tick until the `MasterAccount` can cover it.
+    Queue,
+}
+
+/// A recurring transfer from the `MasterAccount` to a child's `Account`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceRule {
+    pub rule_id: u32,
+    pub child_id: i32,
+    pub amount: u64,
+    pub interval: AllowanceInterval,
+    /// Unix timestamp, in seconds, of when the next allowance is due.
+    pub next_due: u64,
+    pub insufficient_funds_policy: InsufficientFundsPolicy,
+}
+
+/// An allowance which was due but could not be covered, waiting to be retried.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedAllowance {
+    pub rule_id: u32,
+    pub child_id: i32,
+    pub amount: u64,
+    pub due: u64,
+}
+
+/// What happened to a due allowance.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AllowanceOutcome {
+    Applied,
+    Skipped,
+    Queued,
+}
+
+/// An entry in the allowance history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowanceRecord {
+    pub rule_id: u32,
+    pub child_id: i32,
+    pub amount: u64,
+    /// When the allowance was due.
+    pub due: u64,
+    /// When the scheduler handled the allowance.
+    pub handled_at: u64,
+    pub outcome: AllowanceOutcome,
+}
+
+/// Stores recurring allowance rules and applies them when ticked.
+///
+/// The scheduler does not keep time itself, it is ticked with the current time and applies every
+/// allowance that has come due since the last tick, including any that were missed while it was not ticked.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AllowanceScheduler {
+    pub rules: Vec<AllowanceRule>,
+    pub queued: Vec<QueuedAllowance>,
+    pub history: Vec<AllowanceRecord>,
+    next_rule_id: u32,
+}
+
+impl AllowanceScheduler {
+    /// Add a rule transfering `amount` to a child every `interval`, starting at `first_due`, and return its id.
+    pub fn add_rule(&mut self, child_id: i32, amount: u64, interval: AllowanceInterval, first_due: u64, insufficient_funds_policy: InsufficientFundsPolicy) -> u32 {
+        self.next_rule_id += 1;
+        self.rules.push(AllowanceRule {
+            rule_id: self.next_rule_id,
+            child_id,
+            amount,
+            interval,
+            next_due: first_due,
+            insufficient_funds_policy,
+        });
+        self.next_rule_id
+    }
+
+    /// Remove a rule and any of its queued allowances.
+    ///
+    /// # Errors
+    /// If no rule has the `rule_id` return an `AccountError`.
+    pub fn remove_rule(&mut self, rule_id: u32) -> Result<AllowanceRule, AccountError> {
+        let index = self.rules.iter().position(|rule| rule.rule_id == rule_id).ok_or(AccountError::Default("Allowance rule does not exist"))?;
+        self.queued.retain(|queued_allowance| queued_allowance.rule_id != rule_id);
+        Ok(self.rules.remove(index))
+    }
+
+    /// Apply every allowance due at or before `now` using `transfer`, returning what happened to each.
+    ///
+    /// Queued allowances are retried first, oldest first. Then every rule's due allowances are transfered,
+    /// and those `transfer` fails for are skipped or queued according to the rule's `InsufficientFundsPolicy`.
+    /// Everything handled is also added to the `history`.
+    pub fn tick<F>(&mut self, now: u64, mut transfer: F) -> Vec<AllowanceRecord>
+    where
+        F: FnMut(i32, u64) -> Result<(), AccountError>,
+    {
+        let mut handled = vec![];
+
+        let mut still_queued = vec![];
+        for queued_allowance in self.queued.drain(..) {
+            if transfer(queued_allowance.child_id, queued_allowance.amount).is_ok() {
+                handled.push(AllowanceRecord {
+                    rule_id: queued_allowance.rule_id,
+                    child_id: queued_allowance.child_id,
+                    amount: queued_allowance.amount,
+                    due: queued_allowance.due,
+                    handled_at: now,
+                    outcome: AllowanceOutcome::Applied,
+                });
+            } else {
+                still_queued.push(queued_allowance);
+            }
+        }
+        self.queued = still_queued;
+
+        for rule in &mut self.rules {
+            while rule.next_due <= now {
+                let outcome = match transfer(rule.child_id, rule.amount) {
+                    Ok(()) => AllowanceOutcome::Applied,
+                    Err(_error) => match rule.insufficient_funds_policy {
+                        InsufficientFundsPolicy::Skip => AllowanceOutcome::Skipped,
+                        InsufficientFundsPolicy::Queue => {
+                            self.queued.push(QueuedAllowance { rule_id: rule.rule_id, child_id: rule.child_id, amount: rule.amount, due: rule.next_due });
+                            AllowanceOutcome::Queued
+                        },
+                    },
+                };
+                handled.push(AllowanceRecord {
+                    rule_id: rule.rule_id,
+                    child_id: rule.child_id,
+                    amount: rule.amount,
+                    due: rule.next_due,
+                    handled_at: now,
+                    outcome,
+                });
+                rule.next_due += rule.interval.as_seconds();
+            }
+        }
+
+        self.history.extend(handled.iter().cloned());
+        handled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sunday 2023-11-12 00:00:00 UTC
+    const SUNDAY: u64 = 1_699_747_200;
+
+    #[test]
+    fn next_occurrence_finds_the_next_matching_weekday(){
+        assert_eq!(Weekday::Sunday.next_occurrence(SUNDAY - 1), SUNDAY);
+        assert_eq!(Weekday::Sunday.next_occurrence(SUNDAY), SUNDAY + 7 * SECONDS_IN_A_DAY);
+        assert_eq!(Weekday::Wednesday.next_occurrence(SUNDAY + 60), SUNDAY + 3 * SECONDS_IN_A_DAY);
+    }
+
+    #[test]
+    fn tick_applies_every_due_allowance_once(){
+        let mut scheduler = AllowanceScheduler::default();
+        scheduler.add_rule(2, 50000, AllowanceInterval::Weekly, SUNDAY, InsufficientFundsPolicy::Skip);
+        let mut transfered = vec![];
+
+        let handled = scheduler.tick(SUNDAY + 8 * SECONDS_IN_A_DAY, |child_id, amount| {
+            transfered.push((child_id, amount));
+            Ok(())
+        });
+
+        // two sundays have passed
+        assert_eq!(transfered, vec![(2, 50000), (2, 50000)]);
+        assert_eq!(handled.len(), 2);
+        assert_eq!(scheduler.history.len(), 2);
+        assert_eq!(scheduler.rules[0].next_due, SUNDAY + 14 * SECONDS_IN_A_DAY);
+
+        let handled_again = scheduler.tick(SUNDAY + 8 * SECONDS_IN_A_DAY, |_child_id, _amount| Ok(()));
+        assert!(handled_again.is_empty());
+    }
+
+    #[test]
+    fn tick_skips_allowances_the_master_can_not_cover(){
+        let mut scheduler = AllowanceScheduler::default();
+        scheduler.add_rule(2, 50000, AllowanceInterval::Daily, SUNDAY, InsufficientFundsPolicy::Skip);
+
+        let handled = scheduler.tick(SUNDAY, |_child_id, _amount| Err(AccountError::InsufficientAccount));
+
+        assert_eq!(handled[0].outcome, AllowanceOutcome::Skipped);
+        assert!(scheduler.queued.is_empty());
+    }
+
+    #[test]
+    fn tick_retries_queued_allowances_once_the_master_can_cover_them(){
+        let mut scheduler = AllowanceScheduler::default();
+        scheduler.add_rule(2, 50000, AllowanceInterval::Daily, SUNDAY, InsufficientFundsPolicy::Queue);
+
+        let handled = scheduler.tick(SUNDAY, |_child_id, _amount| Err(AccountError::InsufficientAccount));
+        assert_eq!(handled[0].outcome, AllowanceOutcome::Queued);
+        assert_eq!(scheduler.queued.len(), 1);
+
+        let handled = scheduler.tick(SUNDAY + SECONDS_IN_A_DAY, |_child_id, _amount| Ok(()));
+        assert_eq!(handled.len(), 2);
+        assert_eq!(handled[0].due, SUNDAY);
+        assert_eq!(handled[0].outcome, AllowanceOutcome::Applied);
+        assert!(scheduler.queued.is_empty());
+    }
+
+    #[test]
+    fn remove_rule_drops_its_queued_allowances(){
+        let mut scheduler = AllowanceScheduler::default();
+        let rule_id = scheduler.add_rule(2, 50000, AllowanceInterval::Daily, SUNDAY, InsufficientFundsPolicy::Queue);
+        scheduler.tick(SUNDAY, |_child_id, _amount| Err(AccountError::InsufficientAccount));
+
+        scheduler.remove_rule(rule_id).unwrap();
+
+        assert!(scheduler.rules.is_empty());
+        assert!(scheduler.queued.is_empty());
+        assert!(matches!(scheduler.remove_rule(rule_id), Err(AccountError::Default(_))));
+    }
+}
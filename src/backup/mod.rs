@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::Path;
+
+use bdk::bitcoin::Network;
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::BackupError;
+use crate::encryption::{self, KeyDerivation, Sealed, CIPHER};
+use crate::logging::Redacted;
+use crate::storage::HouseholdLedger;
+
+/// The version of the backup format written by `EncryptedBackup::encrypt`.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Everything needed to rebuild a household: its mnemonic words, its BIP39 passphrase if it has one, and its `HouseholdLedger`,
+/// which holds the children, the account permissions, address ownership and the transfer ledger.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HouseholdBackup {
+    pub mnemonic_words: Redacted<String>,
+    #[serde(default)]
+    pub bip39_passphrase: Option<Redacted<String>>,
+    pub ledger: HouseholdLedger,
+}
+
+/// The unencrypted part of a backup file.
+///
+/// The header is authenticated along with the ciphertext, so changing the version, network or key derivation
+/// makes the backup fail its integrity check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupHeader {
+    pub version: u32,
+    pub network: Network,
+    pub key_derivation: KeyDerivation,
+    pub cipher: String,
+}
+
+/// A `HouseholdBackup` encrypted with a passphrase, as it is written to a backup file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncryptedBackup {
+    pub header: BackupHeader,
+    /// Base64 encoded nonce the backup was encrypted with.
+    pub nonce: String,
+    /// Base64 encoded `HouseholdBackup`, encrypted with AES-256-GCM and followed by its authentication tag.
+    pub ciphertext: String,
+}
+
+impl EncryptedBackup {
+    /// Encrypt a `HouseholdBackup` with a key derived from `passphrase`.
+    ///
+    /// # Errors
+    /// If the backup can not be serialized or encrypted return a `BackupError`.
+    pub fn encrypt(backup: &HouseholdBackup, passphrase: &str) -> Result<EncryptedBackup, BackupError> {
+        let header = BackupHeader {
+            version: BACKUP_VERSION,
+            network: backup.ledger.network,
+            key_derivation: KeyDerivation::generate()?,
+            cipher: String::from(CIPHER),
+        };
+        let associated_data = serde_json::to_vec(&header)?;
+        let sealed = encryption::seal(&header.key_derivation, passphrase, &associated_data, serde_json::to_vec(backup)?)?;
+
+        Ok(EncryptedBackup {
+            header,
+            nonce: sealed.nonce,
+            ciphertext: sealed.ciphertext,
+        })
+    }
+
+    /// Decrypt the backup with `passphrase`, checking it belongs to the bitcoin `network`.
+    ///
+    /// # Errors
+    /// If the backup format version is not supported return a `BackupError`.
+    /// If the backup was made on a different `network` return a `BackupError`.
+    /// If the passphrase is wrong, or the backup has been altered, return a `BackupError`.
+    pub fn decrypt(&self, passphrase: &str, network: Network) -> Result<HouseholdBackup, BackupError> {
+        let header = &self.header;
+        if header.version != BACKUP_VERSION || !header.key_derivation.is_supported() || header.cipher != CIPHER {
+            return Err(BackupError::UnsupportedVersion(header.version))
+        }
+        if header.network != network {
+            return Err(BackupError::NetworkMismatch)
+        }
+
+        let associated_data = serde_json::to_vec(header)?;
+        let sealed = Sealed { nonce: self.nonce.clone(), ciphertext: self.ciphertext.clone() };
+        let plaintext = encryption::open(&header.key_derivation, passphrase, &associated_data, &sealed)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Write the encrypted backup to the file at `path`, replacing it if it exists.
+    ///
+    /// # Errors
+    /// If the file can not be written return a `BackupError`.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), BackupError> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Read an encrypted backup from the file at `path`.
+    ///
+    /// # Errors
+    /// If the file can not be read, or is not a backup, return a `BackupError`.
+    pub fn read_from<P: AsRef<Path>>(path: P) -> Result<EncryptedBackup, BackupError> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::children::Children;
+    use crate::head_of_the_house::HeadOfTheHouse;
+    use crate::testing_helpers::{get_default_mnenomic_words, get_default_mnenomic_words_2, set_up};
+
+    fn mock_backup() -> HouseholdBackup {
+        let mut children = Children::new();
+        let head_of_house = HeadOfTheHouse::new(&mut children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+        HouseholdBackup {
+            mnemonic_words: Redacted::new(get_default_mnenomic_words().unwrap()),
+            bip39_passphrase: None,
+            ledger: head_of_house.to_ledger(&children),
+        }
+    }
+
+    #[test]
+    fn backup_decrypts_only_with_its_passphrase_and_network(){
+        set_up();
+        let encrypted_backup = EncryptedBackup::encrypt(&mock_backup(), "correct horse").unwrap();
+
+        let decrypted_backup = encrypted_backup.decrypt("correct horse", Network::Regtest).unwrap();
+        assert_eq!(decrypted_backup.mnemonic_words.expose(), &get_default_mnenomic_words().unwrap());
+        assert!(!format!("{:?}", decrypted_backup).contains(&get_default_mnenomic_words().unwrap()));
+        assert_eq!(decrypted_backup.ledger.accounts.len(), 1);
+        assert!(matches!(encrypted_backup.decrypt("battery staple", Network::Regtest), Err(BackupError::IntegrityCheckFailed)));
+        assert!(matches!(encrypted_backup.decrypt("correct horse", Network::Testnet), Err(BackupError::NetworkMismatch)));
+    }
+
+    #[test]
+    fn export_refuses_mnemonic_words_of_another_wallet(){
+        set_up();
+        let mut children = Children::new();
+        let head_of_house = HeadOfTheHouse::new(&mut children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+
+        let backup_result = head_of_house.export_backup(&children, &get_default_mnenomic_words_2().unwrap(), None, "correct horse");
+
+        assert!(matches!(backup_result, Err(BackupError::MnemonicMismatch)));
+    }
+
+    #[test]
+    fn altered_backup_fails_its_integrity_check(){
+        set_up();
+        let encrypted_backup = EncryptedBackup::encrypt(&mock_backup(), "correct horse").unwrap();
+
+        let mut altered_header = encrypted_backup.clone();
+        altered_header.header.key_derivation.iterations += 1;
+        assert!(matches!(altered_header.decrypt("correct horse", Network::Regtest), Err(BackupError::IntegrityCheckFailed)));
+
+        let mut altered_ciphertext = encrypted_backup.clone();
+        let mut ciphertext = base64::decode(&altered_ciphertext.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        altered_ciphertext.ciphertext = base64::encode(ciphertext);
+        assert!(matches!(altered_ciphertext.decrypt("correct horse", Network::Regtest), Err(BackupError::IntegrityCheckFailed)));
+
+        let mut future_version = encrypted_backup;
+        future_version.header.version = BACKUP_VERSION + 1;
+        assert!(matches!(future_version.decrypt("correct horse", Network::Regtest), Err(BackupError::UnsupportedVersion(_))));
+    }
+}
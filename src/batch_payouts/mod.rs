@@ -0,0 +1,161 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// One output of a batched spend, `amount` paid to `address` by the `Account` with `user_id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchPayout {
+    pub user_id: i32,
+    pub address: String,
+    pub amount: u64,
+}
+
+impl FromStr for BatchPayout {
+    type Err = String;
+
+    /// Parse a payout written as <id>:<amount>:<address>.
+    fn from_str(payout: &str) -> Result<Self, Self::Err> {
+        let mut parts = payout.splitn(3, ':');
+        let user_id = parts.next().unwrap_or_default().parse::<i32>().map_err(|_e| format!("invalid payout {}, expected <id>:<amount>:<address>", payout))?;
+        let amount = parts.next().unwrap_or_default().parse::<u64>().map_err(|_e| format!("invalid payout {}, expected <id>:<amount>:<address>", payout))?;
+        let address = match parts.next() {
+            Some(address) if !address.is_empty() => String::from(address),
+            _ => return Err(format!("invalid payout {}, expected <id>:<amount>:<address>", payout)),
+        };
+        Ok(BatchPayout { user_id, address, amount })
+    }
+}
+
+/// How the fee of a batched spend is shared between the `Account`s paying in it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchFeePolicy {
+    /// Each `Account` pays a share of the fee in proportion to the amount it pays out.
+    #[default]
+    Proportional,
+    /// Each `Account` pays the same share of the fee.
+    Even,
+    /// The head of the house pays the whole fee.
+    HeadOfTheHouse,
+}
+
+impl fmt::Display for BatchFeePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchFeePolicy::Proportional => write!(f, "proportional"),
+            BatchFeePolicy::Even => write!(f, "even"),
+            BatchFeePolicy::HeadOfTheHouse => write!(f, "head-of-the-house"),
+        }
+    }
+}
+
+impl FromStr for BatchFeePolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "proportional" => Ok(BatchFeePolicy::Proportional),
+            "even" => Ok(BatchFeePolicy::Even),
+            "head-of-the-house" => Ok(BatchFeePolicy::HeadOfTheHouse),
+            _ => Err(format!("unknown fee policy {}, expected proportional, even or head-of-the-house", policy)),
+        }
+    }
+}
+
+/// What one `Account` pays in a batched spend, the total of its payouts and its share of the fee.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchShare {
+    pub user_id: i32,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+impl BatchShare {
+    /// The total the share takes from the `Account`'s balance, the amount plus the fee.
+    pub fn total(&self) -> u64 {
+        self.amount + self.fee
+    }
+}
+
+/// Total the `payouts` of each `Account`, in the order the `Account`s first appear, without any share of the fee.
+pub fn account_totals(payouts: &[BatchPayout]) -> Vec<BatchShare> {
+    let mut shares: Vec<BatchShare> = vec![];
+    for payout in payouts {
+        match shares.iter_mut().find(|share| share.user_id == payout.user_id) {
+            Some(share) => share.amount += payout.amount,
+            None => shares.push(BatchShare { user_id: payout.user_id, amount: payout.amount, fee: 0 }),
+        }
+    }
+    shares
+}
+
+impl BatchFeePolicy {
+    /// Split a batched spend paying `fee` into what each `Account` paying out in it pays, in the order the `Account`s first appear in `payouts`.
+    ///
+    /// Whatever can not be split exactly, the odd satoshis, is paid by the first `Account`. With `HeadOfTheHouse` the head of
+    /// the house's `Account`, 1, pays the whole fee, and has a share even if it pays nothing out.
+    pub fn split(&self, payouts: &[BatchPayout], fee: u64) -> Vec<BatchShare> {
+        let mut shares = account_totals(payouts);
+        if shares.is_empty() {
+            return shares
+        }
+
+        match self {
+            BatchFeePolicy::Proportional => {
+                let total_amount: u64 = shares.iter().map(|share| share.amount).sum();
+                for share in shares.iter_mut() {
+                    share.fee = match total_amount {
+                        0 => 0,
+                        _ => (u128::from(fee) * u128::from(share.amount) / u128::from(total_amount)) as u64,
+                    };
+                }
+            },
+            BatchFeePolicy::Even => {
+                let share_count = shares.len() as u64;
+                for share in shares.iter_mut() {
+                    share.fee = fee / share_count;
+                }
+            },
+            BatchFeePolicy::HeadOfTheHouse => {
+                if !shares.iter().any(|share| share.user_id == 1) {
+                    shares.push(BatchShare { user_id: 1, amount: 0, fee: 0 });
+                }
+                for share in shares.iter_mut().filter(|share| share.user_id == 1) {
+                    share.fee = fee;
+                }
+            },
+        }
+        let odd_satoshis = fee - shares.iter().map(|share| share.fee).sum::<u64>();
+        shares[0].fee += odd_satoshis;
+        shares
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payout(user_id: i32, amount: u64) -> BatchPayout {
+        BatchPayout { user_id, address: String::from("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"), amount }
+    }
+
+    #[test]
+    fn fee_is_split_by_policy_between_the_paying_accounts(){
+        let payouts = vec![payout(2, 30_000), payout(3, 10_000), payout(2, 20_000)];
+
+        let proportional = BatchFeePolicy::Proportional.split(&payouts, 1_001);
+        assert_eq!(proportional, vec![BatchShare { user_id: 2, amount: 50_000, fee: 835 }, BatchShare { user_id: 3, amount: 10_000, fee: 166 }]);
+        let even = BatchFeePolicy::Even.split(&payouts, 1_001);
+        assert_eq!(even.iter().map(|share| share.fee).collect::<Vec<u64>>(), vec![501, 500]);
+        let head_of_the_house = BatchFeePolicy::HeadOfTheHouse.split(&payouts, 1_001);
+        assert_eq!(head_of_the_house[2], BatchShare { user_id: 1, amount: 0, fee: 1_001 });
+        assert_eq!(head_of_the_house.iter().map(|share| share.total()).sum::<u64>(), 61_001);
+
+        assert!(BatchFeePolicy::Even.split(&[], 1_001).is_empty());
+        assert_eq!("head-of-the-house".parse::<BatchFeePolicy>().unwrap(), BatchFeePolicy::HeadOfTheHouse);
+        assert_eq!("2:30000:bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20".parse::<BatchPayout>().unwrap(), payout(2, 30_000));
+        assert!("2:bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20".parse::<BatchPayout>().is_err());
+        assert_eq!(serde_json::to_string(&BatchFeePolicy::HeadOfTheHouse).unwrap(), r#""head_of_the_house""#);
+    }
+}
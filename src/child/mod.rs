@@ -1,15 +1,21 @@
 pub mod mocks;
 
 use crate::HeadOfTheHouse;
+use crate::children::Children;
 use crate::custom_errors::{AccountError, WalletError};
+use crate::fees::FeeSelection;
+use crate::permissions::BitcoinPermissions;
 use bdk::bitcoin::Address;
+use bdk::TransactionDetails;
+use serde::{Deserialize, Serialize};
 
 /// A Struct representating a Child, which is a user of a wallet.
 /// 
 /// Each Child has a `user_id` which is associated with the `user_id` of an `Account`
 /// The head_of_the_house will coordinate the relationship between a `Child` it's `Account` and the `MasterAccount`.
 /// This is why all Child actions are propigated to the `head_of_the_house`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
 pub struct Child {
     pub user_id: i32,
     pub account_name: String,
@@ -20,8 +26,152 @@ impl Child {
     pub fn spend_bitcoin(&self, head_of_the_house: &mut HeadOfTheHouse, amount:u64, address: &str) -> Result<&'static str, AccountError>{
         head_of_the_house.spend_bitcoin(self.user_id, amount, address)
     }
+
+    /// Spend bitcoin associated with a child's `Account`, with the fee rate chosen by `fee_selection`.
+    pub fn spend_bitcoin_with_fee(&self, head_of_the_house: &mut HeadOfTheHouse, amount:u64, address: &str, fee_selection: FeeSelection) -> Result<&'static str, AccountError>{
+        head_of_the_house.spend_bitcoin_with_fee(self.user_id, amount, address, fee_selection)
+    }
     pub fn get_new_address(&self, head_of_the_house: &mut HeadOfTheHouse) -> Result<Address, WalletError>{
         let new_address = head_of_the_house.get_new_address(self.user_id)?;
         Ok(new_address)
     }      
+
+    /// Get the child's pending transactions, if the child has the `ViewTransactionHistory` permission.
+    pub fn get_pending_transactions(&self, head_of_the_house: &mut HeadOfTheHouse) -> Result<Vec<TransactionDetails>, WalletError>{
+        self.check_can_view_transactions(head_of_the_house)?;
+        Ok(head_of_the_house.get_account_pending_transactions(self.user_id)?.clone())
+    }
+
+    /// Get the child's transaction history, if the child has the `ViewTransactionHistory` permission.
+    pub fn get_transaction_history(&self, head_of_the_house: &mut HeadOfTheHouse) -> Result<Vec<TransactionDetails>, WalletError>{
+        self.check_can_view_transactions(head_of_the_house)?;
+        head_of_the_house.get_account_transaction_history(self.user_id)
+    }
+
+    fn check_can_view_transactions(&self, head_of_the_house: &HeadOfTheHouse) -> Result<(), WalletError>{
+        head_of_the_house.check_permission(self.user_id, BitcoinPermissions::ViewTransactionHistory)
+            .map_err(|_e| WalletError::MissingPermission(BitcoinPermissions::ViewTransactionHistory))
+    }
+
+    /// Ask the head of the house for a transfer from the `MasterAccount`, if the child has the `RequestTransfers` permission.
+    pub fn request_transfer(&self, head_of_the_house: &mut HeadOfTheHouse, amount: u64) -> Result<u32, AccountError>{
+        head_of_the_house.request_transfer(self.user_id, amount)
+    }
+
+    /// Add a new child, if this child has the `ManageChildren` permission.
+    /// 
+    /// The new child can not be given the `ManageChildren` permission.
+    pub fn add_child(&self, head_of_the_house: &mut HeadOfTheHouse, children: &mut Children, account_id: i32, account_name: String, permissions: Vec<BitcoinPermissions>) -> Result<(), AccountError>{
+        head_of_the_house.check_permission(self.user_id, BitcoinPermissions::ManageChildren)?;
+        if permissions.contains(&BitcoinPermissions::ManageChildren) {
+            return Err(AccountError::MissingPermission(BitcoinPermissions::ManageChildren))
+        }
+        if head_of_the_house.get_account_by_id(account_id).is_some() {
+            return Err(AccountError::Default("Account already exists"))
+        }
+        head_of_the_house.create_new_user(children, account_id, account_name, permissions);
+        Ok(())
+    }
+
+    /// Give another child a permission, if this child has the `ManageChildren` permission.
+    pub fn grant_permission(&self, head_of_the_house: &mut HeadOfTheHouse, child_id: i32, permission: BitcoinPermissions) -> Result<(), AccountError>{
+        head_of_the_house.check_can_manage_child(self.user_id, child_id, permission)?;
+        head_of_the_house.grant_permission(child_id, permission)
+    }
+
+    /// Take a permission away from another child, if this child has the `ManageChildren` permission.
+    pub fn revoke_permission(&self, head_of_the_house: &mut HeadOfTheHouse, child_id: i32, permission: BitcoinPermissions) -> Result<(), AccountError>{
+        head_of_the_house.check_can_manage_child(self.user_id, child_id, permission)?;
+        head_of_the_house.revoke_permission(child_id, permission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Network;
+    use crate::testing_helpers::set_up;
+
+    fn set_up_household_with_manager() -> (HeadOfTheHouse, Children) {
+        let mut children = Children::new();
+        let mut head_of_the_house = HeadOfTheHouse::new(&mut children, None, Network::Regtest).unwrap();
+        head_of_the_house.create_new_user(&mut children, 2, String::from("manager"), vec![BitcoinPermissions::Receive, BitcoinPermissions::ManageChildren]);
+        head_of_the_house.create_new_user(&mut children, 3, String::from("sibling"), vec![]);
+        (head_of_the_house, children)
+    }
+
+    #[test]
+    fn child_without_receive_permission_can_not_get_new_address(){
+        set_up();
+        let (mut head_of_the_house, children) = set_up_household_with_manager();
+        let sibling = children.get_child_by_id(3).unwrap().clone();
+
+        assert!(matches!(sibling.get_new_address(&mut head_of_the_house), Err(WalletError::MissingPermission(BitcoinPermissions::Receive))));
+        assert!(head_of_the_house.get_account_by_id(3).unwrap().addresses.is_empty());
+    }
+
+    #[test]
+    fn child_without_send_permission_can_not_spend(){
+        set_up();
+        let (mut head_of_the_house, children) = set_up_household_with_manager();
+        let sibling = children.get_child_by_id(3).unwrap().clone();
+
+        let spend_result = sibling.spend_bitcoin(&mut head_of_the_house, 1000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+
+        assert!(matches!(spend_result, Err(AccountError::MissingPermission(BitcoinPermissions::Send))));
+    }
+
+    #[test]
+    fn child_without_view_permission_can_not_see_transactions(){
+        set_up();
+        let (mut head_of_the_house, children) = set_up_household_with_manager();
+        let sibling = children.get_child_by_id(3).unwrap().clone();
+
+        assert!(matches!(sibling.get_transaction_history(&mut head_of_the_house), Err(WalletError::MissingPermission(BitcoinPermissions::ViewTransactionHistory))));
+        assert!(matches!(sibling.get_pending_transactions(&mut head_of_the_house), Err(WalletError::MissingPermission(BitcoinPermissions::ViewTransactionHistory))));
+    }
+
+    #[test]
+    fn child_requests_transfer_only_with_permission(){
+        set_up();
+        let (mut head_of_the_house, children) = set_up_household_with_manager();
+        let sibling = children.get_child_by_id(3).unwrap().clone();
+        assert!(matches!(sibling.request_transfer(&mut head_of_the_house, 5000), Err(AccountError::MissingPermission(BitcoinPermissions::RequestTransfers))));
+
+        head_of_the_house.grant_permission(3, BitcoinPermissions::RequestTransfers).unwrap();
+        let request_id = sibling.request_transfer(&mut head_of_the_house, 5000).unwrap();
+
+        assert_eq!(head_of_the_house.get_transfer_requests()[0].request_id, request_id);
+        head_of_the_house.reject_transfer_request(request_id).unwrap();
+        assert!(head_of_the_house.get_transfer_requests().is_empty());
+    }
+
+    #[test]
+    fn manager_can_add_children_and_change_their_permissions(){
+        set_up();
+        let (mut head_of_the_house, mut children) = set_up_household_with_manager();
+        let manager = children.get_child_by_id(2).unwrap().clone();
+
+        manager.add_child(&mut head_of_the_house, &mut children, 4, String::from("baby"), vec![BitcoinPermissions::Receive]).unwrap();
+        manager.grant_permission(&mut head_of_the_house, 3, BitcoinPermissions::Send).unwrap();
+        manager.revoke_permission(&mut head_of_the_house, 4, BitcoinPermissions::Receive).unwrap();
+
+        assert_eq!(children.get_child_by_id(4).unwrap().account_name, String::from("baby"));
+        assert_eq!(head_of_the_house.get_account_by_id(3).unwrap().permissions, vec![BitcoinPermissions::Send]);
+        assert!(head_of_the_house.get_account_by_id(4).unwrap().permissions.is_empty());
+    }
+
+    #[test]
+    fn manager_can_not_change_itself_the_head_of_the_house_or_hand_out_manage_children(){
+        set_up();
+        let (mut head_of_the_house, mut children) = set_up_household_with_manager();
+        let manager = children.get_child_by_id(2).unwrap().clone();
+        let sibling = children.get_child_by_id(3).unwrap().clone();
+
+        assert!(manager.grant_permission(&mut head_of_the_house, 2, BitcoinPermissions::Send).is_err());
+        assert!(manager.revoke_permission(&mut head_of_the_house, 1, BitcoinPermissions::Send).is_err());
+        assert!(manager.grant_permission(&mut head_of_the_house, 3, BitcoinPermissions::ManageChildren).is_err());
+        assert!(manager.add_child(&mut head_of_the_house, &mut children, 4, String::from("baby"), vec![BitcoinPermissions::ManageChildren]).is_err());
+        assert!(matches!(sibling.grant_permission(&mut head_of_the_house, 2, BitcoinPermissions::Send), Err(AccountError::MissingPermission(BitcoinPermissions::ManageChildren))));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,1203 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bdk::KeychainKind;
+use bdk::bitcoin::{Network, Txid};
+use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
+use clap::{ArgAction, ArgGroup, Parser, Subcommand};
+
+use crate::children::Children;
+use crate::custom_errors::{AccountError, CliError, WalletError};
+use crate::env_variables::get_network;
+use crate::head_of_the_house::HeadOfTheHouse;
+use crate::master_account::MasterAccount;
+use crate::allowances::{AllowanceInterval, InsufficientFundsPolicy, Weekday};
+use crate::fees::{FeePriority, FeeSelection};
+use crate::helpers::get_current_timestamp;
+use crate::permissions::BitcoinPermissions;
+use crate::restore::{ChildMetadata, RestoredAccount, DEFAULT_STOP_GAP};
+use crate::spending_limits::SpendingLimits;
+use crate::server::{self, Household};
+use crate::storage::HouseholdStorage;
+use crate::backup::EncryptedBackup;
+use crate::seed_store::{WatchOnlyKeys, DEFAULT_RELOCK_TIMEOUT};
+use crate::offline_signing::{decode_psbt, encode_psbt, PsbtEncoding};
+use crate::multisig::MultisigPolicy;
+use crate::batch_payouts::{BatchFeePolicy, BatchPayout};
+
+/// The family wallet command line interface.
+///
+/// Every command loads the household saved in `data_dir`, runs against it and saves it again,
+/// so state such as children, address assignments and transfers is kept between commands.
+#[derive(Parser, Debug)]
+#[command(name = "family-wallet", about = "Manage a family bitcoin wallet and its children's accounts")]
+pub struct Cli {
+    /// Directory the household database is stored in.
+    #[arg(long, default_value = "family_wallet_data")]
+    pub data_dir: PathBuf,
+    /// The household's mnemonic words, required by every command except create unless the seed has been encrypted with a password.
+    /// They are only read from the mnemonic environment variable, never from a flag, so they do not show up in the process list or the shell history.
+    #[arg(skip = std::env::var("mnemonic").ok())]
+    pub mnemonic: Option<String>,
+    /// The BIP39 passphrase extending the household's mnemonic words, if it has one.
+    #[arg(long, env = "bip39_passphrase", hide_env_values = true)]
+    pub bip39_passphrase: Option<String>,
+    /// The password the household's seed is encrypted with, used to unlock the wallet when no mnemonic words are passed.
+    #[arg(long, env = "wallet_password", hide_env_values = true)]
+    pub password: Option<String>,
+    /// The bitcoin network to use, defaults to the network environment variable.
+    #[arg(long)]
+    pub network: Option<Network>,
+    /// The electrum server to sync with, defaults to the electrum_server environment variable.
+    #[arg(long)]
+    pub electrum_url: Option<String>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// The household actions the command line interface can take.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create a new household with newly generated mnemonic words, encrypting its seed if --password is passed.
+    ///
+    /// Pass --descriptor instead to create a watch-only household whose keys are held by an external signer, such as a hardware wallet.
+    /// Pass --cosigner and --threshold to create a multisig vault of the new seed's key and the cosigners' keys.
+    Create {
+        /// A public descriptor of the external signer, `wpkh([fingerprint/84'/coin'/n']xpub/0/*)` or `[fingerprint/84'/coin'/n']xpub`,
+        /// for account 0 and for any child sub-accounts, may be passed more than once.
+        #[arg(long = "descriptor", conflicts_with = "cosigners")]
+        descriptors: Vec<String>,
+        /// A cosigner's key of a multisig vault, `[fingerprint/48'/coin'/n'/2']xpub`, for account 0 and for any child sub-accounts,
+        /// may be passed more than once.
+        #[arg(long = "cosigner", requires = "threshold")]
+        cosigners: Vec<String>,
+        /// How many of the multisig vault's keys must sign a spend.
+        #[arg(long, requires = "cosigners")]
+        threshold: Option<usize>,
+    },
+    /// Restore a household from the mnemonic words in the mnemonic environment variable.
+    ///
+    /// Children are found by scanning the seed's sub-accounts, pass --child to name them or to restore children whose sub-account is unused.
+    Restore {
+        /// A child to restore as <id>:<name> or <id>:<name>:<permission>,<permission>, may be passed more than once.
+        #[arg(long = "child")]
+        children: Vec<ChildMetadata>,
+        /// How many unused addresses in a row to look past before deciding a keychain has no more.
+        #[arg(long, default_value_t = DEFAULT_STOP_GAP)]
+        stop_gap: usize,
+        /// Restore without connecting to the electrum server, run rescan later to find the children's coins.
+        #[arg(long)]
+        skip_rescan: bool,
+    },
+    /// Scan the blockchain again for the children's addresses, coins and pending transactions.
+    Rescan {
+        /// A child to add as <id>:<name> or <id>:<name>:<permission>,<permission>, may be passed more than once.
+        #[arg(long = "child")]
+        children: Vec<ChildMetadata>,
+        /// How many unused addresses in a row to look past before deciding a keychain has no more.
+        #[arg(long, default_value_t = DEFAULT_STOP_GAP)]
+        stop_gap: usize,
+    },
+    /// Add a child with its own account.
+    AddChild {
+        id: i32,
+        name: String,
+        /// Comma separated permissions, for example send,receive.
+        #[arg(long, value_delimiter = ',', default_value = "send,receive")]
+        permissions: Vec<BitcoinPermissions>,
+    },
+    /// Issue a new receive address for a child.
+    NewAddress { child_id: i32 },
+    /// Spend an amount of satoshis on a child's behalf.
+    ///
+    /// The fee rate is 1 sat/vB unless one of --fee-rate, --confirmation-target or --priority is passed.
+    Spend {
+        child_id: i32,
+        amount: u64,
+        address: String,
+        /// An explicit fee rate in sat/vB.
+        #[arg(long, group = "fee")]
+        fee_rate: Option<f32>,
+        /// Estimate the fee rate needed to confirm within this many blocks.
+        #[arg(long, group = "fee")]
+        confirmation_target: Option<usize>,
+        /// Estimate the fee rate for a priority: low, normal or high.
+        #[arg(long, group = "fee")]
+        priority: Option<FeePriority>,
+    },
+    /// Pay several children or outside addresses in one transaction, paid with the master account's coins and charged to the
+    /// accounts paying out, the children's payouts and shares of the fee coming from the bitcoin transfered to them.
+    ///
+    /// The fee rate is 1 sat/vB unless one of --fee-rate, --confirmation-target or --priority is passed.
+    SpendBatch {
+        /// A payout as <id>:<amount>:<address>, paid by the account <id>, may be passed more than once.
+        #[arg(long = "payout", required = true)]
+        payouts: Vec<BatchPayout>,
+        /// How the fee is shared between the paying accounts: proportional, even or head-of-the-house.
+        #[arg(long, default_value_t = BatchFeePolicy::Proportional)]
+        fee_policy: BatchFeePolicy,
+        /// An explicit fee rate in sat/vB.
+        #[arg(long, group = "fee")]
+        fee_rate: Option<f32>,
+        /// Estimate the fee rate needed to confirm within this many blocks.
+        #[arg(long, group = "fee")]
+        confirmation_target: Option<usize>,
+        /// Estimate the fee rate for a priority: low, normal or high.
+        #[arg(long, group = "fee")]
+        priority: Option<FeePriority>,
+    },
+    /// Transfer satoshis from the master account to a child.
+    ///
+    /// With --on-chain the master account's coins are sent to a new address of the child, at 1 sat/vB unless one of
+    /// --fee-rate, --confirmation-target or --priority is passed, and are counted as transfered once confirmed.
+    TransferToChild {
+        child_id: i32,
+        amount: u64,
+        /// Send the satoshis in a transaction instead of only recording the transfer.
+        #[arg(long)]
+        on_chain: bool,
+        /// An explicit fee rate in sat/vB.
+        #[arg(long, group = "fee", requires = "on_chain")]
+        fee_rate: Option<f32>,
+        /// Estimate the fee rate needed to confirm within this many blocks.
+        #[arg(long, group = "fee", requires = "on_chain")]
+        confirmation_target: Option<usize>,
+        /// Estimate the fee rate for a priority: low, normal or high.
+        #[arg(long, group = "fee", requires = "on_chain")]
+        priority: Option<FeePriority>,
+    },
+    /// Transfer satoshis previously transfered to a child back to the master account.
+    TransferToMaster { child_id: i32, amount: u64 },
+    /// Set a child's spending limits in satoshis, any limit which is not passed is removed.
+    SetLimits {
+        child_id: i32,
+        #[arg(long)]
+        max_per_transaction: Option<u64>,
+        #[arg(long)]
+        daily: Option<u64>,
+        #[arg(long)]
+        weekly: Option<u64>,
+        #[arg(long)]
+        monthly: Option<u64>,
+    },
+    /// Set the highest fee rate in sat/vB a child's spends may use, leave out the rate to remove the maximum.
+    SetMaxFeeRate {
+        child_id: i32,
+        max_fee_rate: Option<f32>,
+    },
+    /// Show a child's spending limits and how much they have spent in each period.
+    Limits { child_id: i32 },
+    /// Schedule a recurring allowance from the master account to a child.
+    AddAllowance {
+        child_id: i32,
+        amount: u64,
+        /// How often to transfer the allowance: daily, weekly or a number of days.
+        #[arg(long, default_value = "weekly")]
+        interval: AllowanceInterval,
+        /// The weekday the first allowance is due, by default it is due immediately.
+        #[arg(long)]
+        on: Option<Weekday>,
+        /// Keep allowances the master account can not cover owed, instead of skipping them.
+        #[arg(long)]
+        queue_when_short: bool,
+    },
+    /// Stop a recurring allowance.
+    RemoveAllowance { rule_id: u32 },
+    /// Show the scheduled allowances and the allowance history.
+    Allowances,
+    /// Apply every allowance which is due.
+    TickAllowances,
+    /// Require spends by a child above a threshold to be approved, leave out the threshold to remove the need for approval.
+    SetApprovalThreshold {
+        child_id: i32,
+        threshold: Option<u64>,
+    },
+    /// Show the spend proposals waiting for approval.
+    Proposals,
+    /// Approve a spend proposal, signing and broadcasting its transaction.
+    Approve { proposal_id: u32 },
+    /// Reject a spend proposal.
+    Reject { proposal_id: u32 },
+    /// Build the unsigned PSBT of a child's spend, to sign on another device, and hold the spend as a spend proposal.
+    ///
+    /// The fee is chosen as it is for spend.
+    BuildPsbt {
+        child_id: i32,
+        amount: u64,
+        address: String,
+        /// An explicit fee rate in sat/vB.
+        #[arg(long, group = "fee")]
+        fee_rate: Option<f32>,
+        /// Estimate the fee rate needed to confirm within this many blocks.
+        #[arg(long, group = "fee")]
+        confirmation_target: Option<usize>,
+        /// Estimate the fee rate for a priority: low, normal or high.
+        #[arg(long, group = "fee")]
+        priority: Option<FeePriority>,
+        /// Write the PSBT to this file instead of printing it.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Write the PSBT as base64 or binary, binary needs --out.
+        #[arg(long, default_value_t = PsbtEncoding::Base64, requires = "out")]
+        encoding: PsbtEncoding,
+    },
+    /// Export the PSBT of a spend proposal, with any signatures imported so far, to sign on another device.
+    ExportPsbt {
+        proposal_id: u32,
+        /// Write the PSBT to this file instead of printing it.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Write the PSBT as base64 or binary, binary needs --out.
+        #[arg(long, default_value_t = PsbtEncoding::Base64, requires = "out")]
+        encoding: PsbtEncoding,
+    },
+    /// Import a PSBT signed on another device into its spend proposal, read from a file as base64 or binary.
+    ImportPsbt { proposal_id: u32, path: PathBuf },
+    /// Broadcast a spend proposal whose PSBT has been signed.
+    Broadcast { proposal_id: u32 },
+    /// Give a child a permission, for example request-transfers.
+    Grant { child_id: i32, permission: BitcoinPermissions },
+    /// Take a permission away from a child.
+    Revoke { child_id: i32, permission: BitcoinPermissions },
+    /// Show a child's transaction history, if the child may view it.
+    History { child_id: i32 },
+    /// Ask for a transfer from the master account on a child's behalf.
+    RequestTransfer { child_id: i32, amount: u64 },
+    /// Show the transfer requests waiting for approval.
+    TransferRequests,
+    /// Approve a transfer request, transfering the amount from the master account.
+    ApproveTransfer { request_id: u32 },
+    /// Reject a transfer request.
+    RejectTransfer { request_id: u32 },
+    /// Add a labelled address to a child's address book.
+    AddAddress { child_id: i32, label: String, address: String },
+    /// Remove an address from a child's address book.
+    RemoveAddress { child_id: i32, address: String },
+    /// Show a child's address book.
+    AddressBook { child_id: i32 },
+    /// Turn whitelist only mode on (true) or off (false), in whitelist only mode a child may only spend to its address book.
+    SetWhitelistOnly {
+        child_id: i32,
+        #[arg(action = ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Write the household, including its mnemonic words, to a backup file encrypted with a passphrase.
+    ExportBackup {
+        path: PathBuf,
+        /// The passphrase to encrypt the backup with.
+        #[arg(long, env = "backup_passphrase", hide_env_values = true)]
+        passphrase: String,
+    },
+    /// Create the household saved in an encrypted backup file, the mnemonic words are read from the backup.
+    ImportBackup {
+        path: PathBuf,
+        /// The passphrase the backup was encrypted with.
+        #[arg(long, env = "backup_passphrase", hide_env_values = true)]
+        passphrase: String,
+    },
+    /// Encrypt the seed of the household with --password, so later commands can run without the mnemonic words.
+    EncryptSeed,
+    /// Show the balance of every account.
+    Balances,
+    /// Show pending transactions, for one child or for every account.
+    Pending { child_id: Option<i32> },
+    /// Speed up a child's payments from outside the household which are waiting to be confirmed, by spending them back to
+    /// the child with a fee which raises them to a higher fee rate.
+    #[command(group(ArgGroup::new("fee").required(true)))]
+    Accelerate {
+        child_id: i32,
+        /// An explicit fee rate in sat/vB.
+        #[arg(long, group = "fee")]
+        fee_rate: Option<f32>,
+        /// Estimate the fee rate needed to confirm within this many blocks.
+        #[arg(long, group = "fee")]
+        confirmation_target: Option<usize>,
+        /// Estimate the fee rate for a priority: low, normal or high.
+        #[arg(long, group = "fee")]
+        priority: Option<FeePriority>,
+    },
+    /// Replace a pending transaction with one paying a higher fee rate, the extra fee is charged to the child who made the spend.
+    #[command(group(ArgGroup::new("fee").required(true)))]
+    BumpFee {
+        txid: Txid,
+        /// An explicit fee rate in sat/vB.
+        #[arg(long, group = "fee")]
+        fee_rate: Option<f32>,
+        /// Estimate the fee rate needed to confirm within this many blocks.
+        #[arg(long, group = "fee")]
+        confirmation_target: Option<usize>,
+        /// Estimate the fee rate for a priority: low, normal or high.
+        #[arg(long, group = "fee")]
+        priority: Option<FeePriority>,
+    },
+    /// Cancel a pending transaction by replacing it with one paying its coins back to the household at a higher fee rate, the
+    /// bitcoin transfered to the child which the spend used is given back once the replacement is confirmed.
+    #[command(group(ArgGroup::new("fee").required(true)))]
+    Cancel {
+        txid: Txid,
+        /// An explicit fee rate in sat/vB.
+        #[arg(long, group = "fee")]
+        fee_rate: Option<f32>,
+        /// Estimate the fee rate needed to confirm within this many blocks.
+        #[arg(long, group = "fee")]
+        confirmation_target: Option<usize>,
+        /// Estimate the fee rate for a priority: low, normal or high.
+        #[arg(long, group = "fee")]
+        priority: Option<FeePriority>,
+    },
+    /// Serve the household's http api until the process is stopped.
+    Serve {
+        /// The address to listen on.
+        #[arg(long, default_value = "127.0.0.1:3030")]
+        listen: SocketAddr,
+        /// How many seconds the wallet stays unlocked after POST /unlock before it locks itself again.
+        #[arg(long, default_value_t = DEFAULT_RELOCK_TIMEOUT.as_secs())]
+        relock_after: u64,
+    },
+}
+
+/// Run a command line interface command and return the text to show the user.
+///
+/// # Errors
+/// If the household does not exist, or already exists when creating one, return a `CliError`.
+/// If the household action fails return a `CliError` wrapping the underlying error.
+pub async fn run(cli: Cli) -> Result<String, CliError> {
+    let network = match cli.network {
+        Some(network) => network,
+        None => get_network()?,
+    };
+    let storage = HouseholdStorage::open(&cli.data_dir)?;
+    let household_exists = storage.load_ledger()?.is_some();
+    let mut children = Children::new();
+
+    match cli.command {
+        Command::Create { descriptors, cosigners, threshold } => {
+            if household_exists {
+                return Err(CliError::HouseholdAlreadyExists)
+            }
+            if !descriptors.is_empty() {
+                let watch_only_keys = WatchOnlyKeys::from_descriptors(&descriptors, network)?;
+                let head_of_house = HeadOfTheHouse::load_from_watch_only_keys(&storage, &mut children, watch_only_keys, network)?;
+                head_of_house.save(&storage, &children)?;
+                return Ok(format!("Created a watch-only {} household, its spends are held as unsigned PSBTs for the external signer", network))
+            }
+            let mnemonic_words = MasterAccount::generate_mnemonic_words();
+            let head_of_house = match threshold {
+                Some(threshold) => {
+                    let multisig = MultisigPolicy::from_cosigner_keys(threshold, &cosigners, network)?;
+                    HeadOfTheHouse::load_multisig(&storage, &mut children, Some(mnemonic_words.clone()), cli.bip39_passphrase.clone(), multisig, network)?
+                },
+                None => HeadOfTheHouse::load_with_bip39_passphrase(&storage, &mut children, Some(mnemonic_words.clone()), cli.bip39_passphrase.clone(), network)?,
+            };
+            if let Some(password) = &cli.password {
+                storage.save_encrypted_seed(&head_of_house.encrypt_seed(&mnemonic_words, cli.bip39_passphrase.as_deref(), password)?)?;
+            }
+            head_of_house.save(&storage, &children)?;
+            let created = match head_of_house.master_account.multisig_policy() {
+                Some(multisig) => {
+                    let vault_descriptor = head_of_house.master_account.wallet.public_descriptor(KeychainKind::External).map_err(WalletError::from)?.ok_or(WalletError::KeyError)?;
+                    format!("Created a new {} of {} multisig {} household, give the cosigners its descriptor:\n{}\n", multisig.threshold, multisig.key_count(), network, vault_descriptor)
+                },
+                None => format!("Created a new {} household, ", network),
+            };
+            Ok(format!("{}write down these mnemonic words, they are needed for every other command:\n{}", created, mnemonic_words))
+        },
+        Command::Restore { children: child_metadata, stop_gap, skip_rescan } => {
+            if household_exists {
+                return Err(CliError::HouseholdAlreadyExists)
+            }
+            let mnemonic_words = cli.mnemonic.ok_or(CliError::MissingMnemonic)?;
+            let mut head_of_house = HeadOfTheHouse::load_with_bip39_passphrase(&storage, &mut children, Some(mnemonic_words), cli.bip39_passphrase, network)?;
+            if skip_rescan {
+                head_of_house.add_restored_children(&mut children, &child_metadata);
+                head_of_house.save(&storage, &children)?;
+                return Ok(format!("Restored the {} household", network))
+            }
+
+            // the household is only saved once the rescan succeeds, so a failed restore can be run again
+            let restored_accounts = head_of_house.restore_accounts(&mut children, &child_metadata, cli.electrum_url.as_deref(), stop_gap)?;
+            head_of_house.save(&storage, &children)?;
+            Ok(format!("Restored the {} household\n{}", network, format_restored_accounts(&restored_accounts)))
+        },
+        Command::ImportBackup { path, passphrase } => {
+            if household_exists {
+                return Err(CliError::HouseholdAlreadyExists)
+            }
+            let encrypted_backup = EncryptedBackup::read_from(&path)?;
+            HeadOfTheHouse::import_backup(&storage, &mut children, &encrypted_backup, &passphrase, network)?;
+            Ok(format!("Imported the {} household with {} children from {}", network, children.children.len(), path.display()))
+        },
+        command => {
+            if !household_exists {
+                return Err(CliError::HouseholdDoesNotExist)
+            }
+            let mut head_of_house = match &cli.mnemonic {
+                Some(mnemonic_words) => HeadOfTheHouse::load_with_bip39_passphrase(&storage, &mut children, Some(mnemonic_words.clone()), cli.bip39_passphrase.clone(), network)?,
+                None if storage.load_encrypted_seed()?.is_some() || signs_externally(&storage)? => {
+                    // without the mnemonic words the household runs locked, and signs only if the password unlocks it,
+                    // a household whose keys are held by an external signer always runs this way
+                    let mut head_of_house = HeadOfTheHouse::load_watch_only(&storage, &mut children, network)?;
+                    if let Some(password) = &cli.password {
+                        head_of_house.unlock(&storage, password)?;
+                    }
+                    head_of_house
+                },
+                None => return Err(CliError::MissingMnemonic),
+            };
+
+            if let Command::ExportBackup { path, passphrase } = command {
+                let mnemonic_words = cli.mnemonic.ok_or(CliError::MissingMnemonic)?;
+                head_of_house.export_backup(&children, &mnemonic_words, cli.bip39_passphrase.as_deref(), &passphrase)?.write_to(&path)?;
+                return Ok(format!("Wrote an encrypted backup of the household to {}", path.display()))
+            }
+
+            if let Command::EncryptSeed = command {
+                let mnemonic_words = cli.mnemonic.ok_or(CliError::MissingMnemonic)?;
+                let password = cli.password.ok_or(CliError::MissingPassword)?;
+                storage.save_encrypted_seed(&head_of_house.encrypt_seed(&mnemonic_words, cli.bip39_passphrase.as_deref(), &password)?)?;
+                head_of_house.save(&storage, &children)?;
+                return Ok(String::from("Encrypted the household's seed, pass --password instead of the mnemonic words from now on"))
+            }
+
+            if let Command::Serve { listen, relock_after } = command {
+                head_of_house.master_account.set_relock_timeout(Duration::from_secs(relock_after));
+                let household = Household {
+                    head_of_the_house: head_of_house,
+                    children,
+                    storage: Some(storage),
+                    electrum_url: cli.electrum_url,
+                };
+                server::serve(household, listen).await?;
+                return Ok(String::from("Server stopped"))
+            }
+
+            let output = run_household_command(command, &mut head_of_house, &mut children, cli.electrum_url.as_deref());
+            // save even if the command failed, a spend may have been broadcast before a later step errored
+            head_of_house.save(&storage, &children)?;
+            output
+        }
+    }
+}
+
+/// Return true if the household saved in `storage` was created from the descriptors of an external signer, else return false.
+///
+/// # Errors
+/// If the storage can not be read return a `CliError`.
+fn signs_externally(storage: &HouseholdStorage) -> Result<bool, CliError> {
+    let ledger = storage.load_ledger()?;
+    Ok(ledger.and_then(|ledger| ledger.watch_only_keys).is_some_and(|watch_only_keys| watch_only_keys.external_signer))
+}
+
+/// Describe what a restore or rescan found for each child, one line per child.
+fn format_restored_accounts(restored_accounts: &[RestoredAccount]) -> String {
+    if restored_accounts.is_empty() {
+        return String::from("No children were found")
+    }
+    let lines: Vec<String> = restored_accounts.iter().map(|restored_account| {
+        let found = if restored_account.discovered { "found on the blockchain" } else { "restored" };
+        format!(
+            "child {} {}: {} addresses, {} change addresses, {} pending transactions, {} sats",
+            restored_account.account_id, found, restored_account.addresses, restored_account.change_addresses,
+            restored_account.pending_transactions, restored_account.bitcoin_amount,
+        )
+    }).collect();
+    lines.join("\n")
+}
+
+/// Run a command against a loaded household.
+fn run_household_command(command: Command, head_of_house: &mut HeadOfTheHouse, children: &mut Children, electrum_url: Option<&str>) -> Result<String, CliError> {
+    match command {
+        Command::AddChild { id, name, permissions } => {
+            if head_of_house.get_account_by_id(id).is_some() {
+                return Err(CliError::Account(AccountError::Default("Account already exists")))
+            }
+            head_of_house.create_new_user(children, id, name.clone(), permissions);
+            Ok(format!("Added child {} with id {}", name, id))
+        },
+        Command::NewAddress { child_id } => {
+            let child = children.get_child_by_id(child_id).ok_or(CliError::ChildDoesNotExist)?;
+            let new_address = child.get_new_address(head_of_house)?;
+            Ok(new_address.to_string())
+        },
+        Command::Spend { child_id, amount, address, fee_rate, confirmation_target, priority } => {
+            let fee_selection = fee_selection(fee_rate, confirmation_target, priority);
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let child = children.get_child_by_id(child_id).ok_or(CliError::ChildDoesNotExist)?;
+            let spend_status = child.spend_bitcoin_with_fee(head_of_house, amount, &address, fee_selection)?;
+            if spend_status == "AWAITING_SIGNATURE" {
+                let proposal = head_of_house.get_spend_proposals().last().ok_or(CliError::Account(AccountError::SpendProposalDoesNotExist))?;
+                let signatures_needed = head_of_house.get_signatures_needed(proposal.proposal_id)?;
+                return Ok(format!("Spend of {} sats to {} is {} as proposal {}, it needs {} more signatures, sign this PSBT with the other signers:\n{}", amount, address, spend_status, proposal.proposal_id, signatures_needed, proposal.psbt))
+            }
+            Ok(format!("Spend of {} sats to {} is {}", amount, address, spend_status))
+        },
+        Command::SpendBatch { payouts, fee_policy, fee_rate, confirmation_target, priority } => {
+            let fee_selection = fee_selection(fee_rate, confirmation_target, priority);
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let batch_transaction = head_of_house.spend_bitcoin_batch(&payouts, fee_policy, fee_selection)?;
+            Ok(format!("Paid {} payouts with {}, fee {} sats shared {}", payouts.len(), batch_transaction.txid, batch_transaction.fee.unwrap_or(0), fee_policy))
+        },
+        Command::TransferToChild { child_id, amount, on_chain: false, .. } => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            head_of_house.transfer_bitcoin_from_master_to_child(amount, child_id)?;
+            Ok(format!("Transfered {} sats to child {}", amount, child_id))
+        },
+        Command::TransferToChild { child_id, amount, on_chain: true, fee_rate, confirmation_target, priority } => {
+            let fee_selection = fee_selection(fee_rate, confirmation_target, priority);
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let transfer_transaction = head_of_house.transfer_bitcoin_from_master_to_child_on_chain(amount, child_id, fee_selection)?;
+            Ok(format!("Sent {} sats to child {} with {}, fee {} sats, counted as transfered once confirmed", amount, child_id, transfer_transaction.txid, transfer_transaction.fee.unwrap_or(0)))
+        },
+        Command::TransferToMaster { child_id, amount } => {
+            head_of_house.transfer_bitcoin_from_child_to_master(amount, child_id)?;
+            Ok(format!("Transfered {} sats from child {} to the master account", amount, child_id))
+        },
+        Command::SetLimits { child_id, max_per_transaction, daily, weekly, monthly } => {
+            head_of_house.set_spending_limits(child_id, SpendingLimits { max_per_transaction, daily, weekly, monthly })?;
+            Ok(format!("Updated the spending limits of child {}", child_id))
+        },
+        Command::SetMaxFeeRate { child_id, max_fee_rate } => {
+            head_of_house.set_max_fee_rate(child_id, max_fee_rate)?;
+            match max_fee_rate {
+                Some(max_fee_rate) => Ok(format!("Spends by child {} may now use at most {} sat/vB", child_id, max_fee_rate)),
+                None => Ok(format!("Spends by child {} no longer have a maximum fee rate", child_id)),
+            }
+        },
+        Command::Limits { child_id } => {
+            let account = head_of_house.get_account_by_id(child_id).ok_or(CliError::ChildDoesNotExist)?;
+            let spending_limits = account.spending_limits.clone();
+            let spend_tally = head_of_house.get_account_spend_tally(child_id)?;
+            let format_limit = |limit: Option<u64>| limit.map(|limit| format!("{} sats", limit)).unwrap_or_else(|| String::from("none"));
+            Ok(format!(
+                "per transaction: {}\ndaily: {} ({} sats spent)\nweekly: {} ({} sats spent)\nmonthly: {} ({} sats spent)",
+                format_limit(spending_limits.max_per_transaction),
+                format_limit(spending_limits.daily), spend_tally.daily,
+                format_limit(spending_limits.weekly), spend_tally.weekly,
+                format_limit(spending_limits.monthly), spend_tally.monthly,
+            ))
+        },
+        Command::AddAllowance { child_id, amount, interval, on, queue_when_short } => {
+            let now = get_current_timestamp();
+            let first_due = on.map(|weekday| weekday.next_occurrence(now)).unwrap_or(now);
+            let insufficient_funds_policy = if queue_when_short { InsufficientFundsPolicy::Queue } else { InsufficientFundsPolicy::Skip };
+            let rule_id = head_of_house.add_allowance(child_id, amount, interval, first_due, insufficient_funds_policy)?;
+            Ok(format!("Scheduled allowance {} of {} sats for child {}", rule_id, amount, child_id))
+        },
+        Command::RemoveAllowance { rule_id } => {
+            head_of_house.remove_allowance(rule_id)?;
+            Ok(format!("Removed allowance {}", rule_id))
+        },
+        Command::Allowances => {
+            let allowance_scheduler = &head_of_house.allowance_scheduler;
+            let mut lines = vec![];
+            for rule in &allowance_scheduler.rules {
+                lines.push(format!("allowance {}: {} sats to child {} {:?}, next due at {}, {:?} when short", rule.rule_id, rule.amount, rule.child_id, rule.interval, rule.next_due, rule.insufficient_funds_policy));
+            }
+            for queued_allowance in &allowance_scheduler.queued {
+                lines.push(format!("queued: {} sats to child {} due at {}", queued_allowance.amount, queued_allowance.child_id, queued_allowance.due));
+            }
+            for record in &allowance_scheduler.history {
+                lines.push(format!("history: {} sats to child {} due at {} {:?} at {}", record.amount, record.child_id, record.due, record.outcome, record.handled_at));
+            }
+            if lines.is_empty() {
+                lines.push(String::from("No allowances"));
+            }
+            Ok(lines.join("\n"))
+        },
+        Command::TickAllowances => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let handled = head_of_house.tick_allowances(get_current_timestamp());
+            let lines: Vec<String> = handled.iter().map(|record| format!("allowance {}: {} sats to child {} {:?}", record.rule_id, record.amount, record.child_id, record.outcome)).collect();
+            if lines.is_empty() {
+                Ok(String::from("No allowances are due"))
+            } else {
+                Ok(lines.join("\n"))
+            }
+        },
+        Command::SetApprovalThreshold { child_id, threshold } => {
+            head_of_house.set_approval_threshold(child_id, threshold)?;
+            match threshold {
+                Some(threshold) => Ok(format!("Spends by child {} above {} sats now need approval", child_id, threshold)),
+                None => Ok(format!("Spends by child {} no longer need approval", child_id)),
+            }
+        },
+        Command::Proposals => {
+            let lines: Vec<String> = head_of_house.get_spend_proposals().iter().map(|proposal| format!("proposal {}: account {} spending {} sats to {}, fee {} sats, needs {} more signatures", proposal.proposal_id, proposal.user_id, proposal.amount, proposal.address, proposal.fee, head_of_house.master_account.signatures_needed(&proposal.psbt))).collect();
+            if lines.is_empty() {
+                Ok(String::from("No spend proposals"))
+            } else {
+                Ok(lines.join("\n"))
+            }
+        },
+        Command::Approve { proposal_id } => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let spend_status = head_of_house.approve_spend_proposal(proposal_id)?;
+            Ok(format!("Spend proposal {} is {}", proposal_id, spend_status))
+        },
+        Command::Reject { proposal_id } => {
+            let proposal = head_of_house.reject_spend_proposal(proposal_id)?;
+            Ok(format!("Rejected spend proposal {} of {} sats to {}", proposal_id, proposal.amount, proposal.address))
+        },
+        Command::BuildPsbt { child_id, amount, address, fee_rate, confirmation_target, priority, out, encoding } => {
+            let fee_selection = fee_selection(fee_rate, confirmation_target, priority);
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            children.get_child_by_id(child_id).ok_or(CliError::ChildDoesNotExist)?;
+            let proposal_id = head_of_house.build_unsigned_spend(child_id, amount, &address, fee_selection)?;
+            let proposal = head_of_house.get_spend_proposal(proposal_id)?;
+            let exported_psbt = export_psbt(&proposal.psbt, out, encoding)?;
+            Ok(format!("Spend of {} sats to {} is held as proposal {}, sign its PSBT on another device:\n{}", amount, address, proposal_id, exported_psbt))
+        },
+        Command::ExportPsbt { proposal_id, out, encoding } => {
+            let proposal = head_of_house.get_spend_proposal(proposal_id)?;
+            export_psbt(&proposal.psbt, out, encoding)
+        },
+        Command::ImportPsbt { proposal_id, path } => {
+            let encoded_psbt = fs::read(path).map_err(|_e| CliError::FileError)?;
+            let signed_psbt = decode_psbt(&encoded_psbt)?;
+            if head_of_house.import_signed_psbt(proposal_id, signed_psbt)? {
+                Ok(format!("Spend proposal {} is signed and ready to broadcast", proposal_id))
+            } else {
+                Ok(format!("Imported the PSBT into spend proposal {}, it still needs {} more signatures", proposal_id, head_of_house.get_signatures_needed(proposal_id)?))
+            }
+        },
+        Command::Broadcast { proposal_id } => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let spend_status = head_of_house.broadcast_spend_proposal(proposal_id)?;
+            Ok(format!("Spend proposal {} is {}", proposal_id, spend_status))
+        },
+        Command::Grant { child_id, permission } => {
+            head_of_house.grant_permission(child_id, permission)?;
+            Ok(format!("Granted {} to child {}", permission, child_id))
+        },
+        Command::Revoke { child_id, permission } => {
+            head_of_house.revoke_permission(child_id, permission)?;
+            Ok(format!("Revoked {} from child {}", permission, child_id))
+        },
+        Command::History { child_id } => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let child = children.get_child_by_id(child_id).ok_or(CliError::ChildDoesNotExist)?;
+            let lines: Vec<String> = child.get_transaction_history(head_of_house)?.iter().map(|transaction| {
+                let confirmation = transaction.confirmation_time.as_ref().map(|confirmation_time| format!("confirmed at height {}", confirmation_time.height)).unwrap_or_else(|| String::from("unconfirmed"));
+                format!("{}: received {} sats, sent {} sats, {}", transaction.txid, transaction.received, transaction.sent, confirmation)
+            }).collect();
+            if lines.is_empty() {
+                Ok(String::from("No transactions"))
+            } else {
+                Ok(lines.join("\n"))
+            }
+        },
+        Command::RequestTransfer { child_id, amount } => {
+            let child = children.get_child_by_id(child_id).ok_or(CliError::ChildDoesNotExist)?;
+            let request_id = child.request_transfer(head_of_house, amount)?;
+            Ok(format!("Requested transfer {} of {} sats for child {}", request_id, amount, child_id))
+        },
+        Command::TransferRequests => {
+            let lines: Vec<String> = head_of_house.get_transfer_requests().iter().map(|request| format!("request {}: {} sats to child {}", request.request_id, request.amount, request.child_id)).collect();
+            if lines.is_empty() {
+                Ok(String::from("No transfer requests"))
+            } else {
+                Ok(lines.join("\n"))
+            }
+        },
+        Command::ApproveTransfer { request_id } => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let request = head_of_house.approve_transfer_request(request_id)?;
+            Ok(format!("Transfered {} sats to child {}", request.amount, request.child_id))
+        },
+        Command::RejectTransfer { request_id } => {
+            let request = head_of_house.reject_transfer_request(request_id)?;
+            Ok(format!("Rejected transfer request {} of {} sats for child {}", request_id, request.amount, request.child_id))
+        },
+        Command::AddAddress { child_id, label, address } => {
+            let entry = head_of_house.add_address_book_entry(child_id, label, &address)?;
+            Ok(format!("Added {} ({}) to the address book of child {}", entry.label, entry.address, child_id))
+        },
+        Command::RemoveAddress { child_id, address } => {
+            let entry = head_of_house.remove_address_book_entry(child_id, &address)?;
+            Ok(format!("Removed {} ({}) from the address book of child {}", entry.label, entry.address, child_id))
+        },
+        Command::AddressBook { child_id } => {
+            let address_book = head_of_house.get_address_book(child_id)?;
+            let lines: Vec<String> = address_book.entries.iter().map(|entry| format!("{}: {}", entry.label, entry.address)).collect();
+            if lines.is_empty() {
+                Ok(String::from("No addresses"))
+            } else {
+                Ok(lines.join("\n"))
+            }
+        },
+        Command::SetWhitelistOnly { child_id, enabled } => {
+            head_of_house.set_whitelist_only(child_id, enabled)?;
+            if enabled {
+                Ok(format!("Child {} may now only spend to its address book", child_id))
+            } else {
+                Ok(format!("Child {} may now spend to any address", child_id))
+            }
+        },
+        Command::Balances => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            head_of_house.settle_pending_cancellations()?;
+            head_of_house.settle_pending_on_chain_transfers()?;
+            let master_balance = head_of_house.master_account.get_bitcoin_total_minus_transfers_to_children().map_err(WalletError::from)?;
+            let mut lines = vec![format!("master account: {} sats", master_balance)];
+
+            let account_ids: Vec<i32> = head_of_house.accounts.iter().map(|account| account.account_id).collect();
+            for account_id in account_ids {
+                let utxo_balance = head_of_house.get_and_update_account_balance(account_id)?;
+                let transfer_balance = head_of_house.get_account_balance_transfer_amount(account_id)?;
+                let held_amount = head_of_house.get_account_held_amount(account_id);
+                let account_name = children.get_child_by_id(account_id).map(|child| child.account_name.clone()).unwrap_or_default();
+                lines.push(format!("{} ({}): {} sats ({} received, {} transfered from master, {} held for approval)", account_name, account_id, utxo_balance + transfer_balance, utxo_balance, transfer_balance, held_amount));
+            }
+            Ok(lines.join("\n"))
+        },
+        Command::Pending { child_id } => {
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let account_ids: Vec<i32> = match child_id {
+                Some(child_id) => vec![child_id],
+                None => head_of_house.accounts.iter().map(|account| account.account_id).collect(),
+            };
+
+            let mut lines = vec![];
+            for account_id in account_ids {
+                for pending_transaction in head_of_house.get_account_pending_transactions(account_id)? {
+                    lines.push(format!("account {}: {} sent {} sats, fee {} sats", account_id, pending_transaction.txid, pending_transaction.sent, pending_transaction.fee.unwrap_or(0)));
+                }
+            }
+            if lines.is_empty() {
+                lines.push(String::from("No pending transactions"));
+            }
+            Ok(lines.join("\n"))
+        },
+        Command::Accelerate { child_id, fee_rate, confirmation_target, priority } => {
+            let fee_selection = fee_selection(fee_rate, confirmation_target, priority);
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let cpfp_transaction = head_of_house.accelerate_incoming_payments(child_id, fee_selection)?;
+            Ok(format!("Speeding up child {}'s unconfirmed payments with {}, fee {} sats", child_id, cpfp_transaction.txid, cpfp_transaction.fee.unwrap_or(0)))
+        },
+        Command::BumpFee { txid, fee_rate, confirmation_target, priority } => {
+            let fee_selection = fee_selection(fee_rate, confirmation_target, priority);
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let replacement = head_of_house.bump_fee(txid, fee_selection)?;
+            Ok(format!("Replaced {} with {}, fee {} sats", txid, replacement.txid, replacement.fee.unwrap_or(0)))
+        },
+        Command::Cancel { txid, fee_rate, confirmation_target, priority } => {
+            let fee_selection = fee_selection(fee_rate, confirmation_target, priority);
+            head_of_house.master_account.sync_wallet_with_electrum_server(electrum_url)?;
+            let replacement = head_of_house.cancel_transaction(txid, fee_selection)?;
+            Ok(format!("Cancelled {} with {}, fee {} sats", txid, replacement.txid, replacement.fee.unwrap_or(0)))
+        },
+        Command::Rescan { children: child_metadata, stop_gap } => {
+            let restored_accounts = head_of_house.restore_accounts(children, &child_metadata, electrum_url, stop_gap)?;
+            Ok(format_restored_accounts(&restored_accounts))
+        },
+        Command::Create { .. } | Command::Restore { .. } | Command::ImportBackup { .. } => Err(CliError::HouseholdAlreadyExists),
+        Command::ExportBackup { .. } | Command::EncryptSeed => Err(CliError::MissingMnemonic),
+        Command::Serve { .. } => Err(CliError::ServerError),
+    }
+}
+
+/// Choose the fee of a spend from the fee options passed, at most one of which is set.
+fn fee_selection(fee_rate: Option<f32>, confirmation_target: Option<usize>, priority: Option<FeePriority>) -> FeeSelection {
+    match (fee_rate, confirmation_target, priority) {
+        (Some(fee_rate), _, _) => FeeSelection::SatPerVb(fee_rate),
+        (_, Some(confirmation_target), _) => FeeSelection::ConfirmationTarget(confirmation_target),
+        (_, _, Some(priority)) => FeeSelection::Priority(priority),
+        _ => FeeSelection::default(),
+    }
+}
+
+/// Write a PSBT to the file `out` in the chosen encoding and say where it was written, or return it as base64 to print if there is no file.
+///
+/// # Errors
+/// If the file can not be written return a `CliError`.
+fn export_psbt(psbt: &PartiallySignedTransaction, out: Option<PathBuf>, encoding: PsbtEncoding) -> Result<String, CliError> {
+    match out {
+        Some(path) => {
+            fs::write(&path, encode_psbt(psbt, encoding)).map_err(|_e| CliError::FileError)?;
+            Ok(format!("Wrote the {} PSBT to {}", encoding, path.display()))
+        },
+        None => Ok(psbt.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use bdk::KeychainKind;
+    use bdk::bitcoin::Address;
+    use crate::custom_errors::BackupError;
+    use crate::testing_helpers::{get_default_mnenomic_words, get_default_mnenomic_words_2, get_multisig_cosigner_key, set_up};
+
+    // used to handle async await functions
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    /// Build a `Cli` for a command line, pointed at a fresh data directory for the test.
+    fn cli_for(test_name: &str, arguments: &[&str]) -> Cli {
+        let data_dir = std::env::temp_dir().join(format!("family_wallet_cli_{}", test_name));
+        let mut command_line = vec!["family-wallet", "--data-dir", data_dir.to_str().unwrap()];
+        command_line.extend_from_slice(arguments);
+        Cli::try_parse_from(command_line).unwrap()
+    }
+
+    fn cli_with_mnemonic(test_name: &str, mnemonic_words: &str, arguments: &[&str]) -> Cli {
+        let mut cli = cli_for(test_name, arguments);
+        cli.mnemonic = Some(mnemonic_words.to_string());
+        cli
+    }
+
+    fn remove_data_dir(test_name: &str) {
+        let _ = std::fs::remove_dir_all(std::env::temp_dir().join(format!("family_wallet_cli_{}", test_name)));
+    }
+
+    #[test]
+    fn add_child_parses_comma_separated_permissions(){
+        let cli = Cli::try_parse_from(["family-wallet", "add-child", "2", "alice", "--permissions", "receive"]).unwrap();
+        match cli.command {
+            Command::AddChild { id, name, permissions } => {
+                assert_eq!(id, 2);
+                assert_eq!(name, String::from("alice"));
+                assert_eq!(permissions, vec![BitcoinPermissions::Receive]);
+            },
+            _ => panic!("expected the add-child command"),
+        }
+    }
+
+    #[test]
+    fn add_allowance_parses_interval_and_weekday(){
+        let cli = Cli::try_parse_from(["family-wallet", "add-allowance", "2", "50000", "--interval", "14", "--on", "sunday", "--queue-when-short"]).unwrap();
+        match cli.command {
+            Command::AddAllowance { child_id, amount, interval, on, queue_when_short } => {
+                assert_eq!(child_id, 2);
+                assert_eq!(amount, 50000);
+                assert_eq!(interval, AllowanceInterval::EveryDays(14));
+                assert_eq!(on, Some(Weekday::Sunday));
+                assert!(queue_when_short);
+            },
+            _ => panic!("expected the add-allowance command"),
+        }
+    }
+
+    #[test]
+    fn restore_then_add_child_and_issue_address_is_saved(){
+        set_up();
+        let test_name = "restore_then_add_child";
+        remove_data_dir(test_name);
+        let mnemonic_words = get_default_mnenomic_words().unwrap();
+
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["restore", "--skip-rescan"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["add-child", "2", "alice"]))).unwrap();
+        let new_address = aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["new-address", "2"]))).unwrap();
+
+        let storage = HouseholdStorage::open(std::env::temp_dir().join(format!("family_wallet_cli_{}", test_name))).unwrap();
+        let ledger = storage.load_ledger().unwrap().unwrap();
+        let child_account = ledger.accounts.iter().find(|account| account.account_id == 2).unwrap();
+        assert_eq!(child_account.addresses.first().unwrap().to_string(), new_address);
+        assert_eq!(ledger.children.len(), 2);
+        drop(storage);
+        remove_data_dir(test_name);
+    }
+
+    #[test]
+    fn restore_without_rescan_saves_the_named_children(){
+        set_up();
+        let test_name = "restore_named_children";
+        remove_data_dir(test_name);
+        let mnemonic_words = get_default_mnenomic_words().unwrap();
+
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["restore", "--skip-rescan", "--child", "2:alice", "--child", "3:bob:receive"]))).unwrap();
+
+        let storage = HouseholdStorage::open(std::env::temp_dir().join(format!("family_wallet_cli_{}", test_name))).unwrap();
+        let ledger = storage.load_ledger().unwrap().unwrap();
+        assert_eq!(ledger.children.len(), 3);
+        let bob_account = ledger.accounts.iter().find(|account| account.account_id == 3).unwrap();
+        assert_eq!(bob_account.permissions, vec![BitcoinPermissions::Receive]);
+        assert_eq!(bob_account.account_index, Some(3));
+        drop(storage);
+        remove_data_dir(test_name);
+    }
+
+    #[test]
+    fn exported_backup_imports_the_household_into_a_new_data_dir(){
+        set_up();
+        let test_name = "export_backup";
+        let import_test_name = "import_backup";
+        remove_data_dir(test_name);
+        remove_data_dir(import_test_name);
+        let mnemonic_words = get_default_mnenomic_words().unwrap();
+        let backup_path = std::env::temp_dir().join("family_wallet_cli_test_backup.json");
+        let backup_path = backup_path.to_str().unwrap();
+
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["restore", "--skip-rescan"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["add-child", "2", "alice"]))).unwrap();
+        let child_address = aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["new-address", "2"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["export-backup", backup_path, "--passphrase", "hunter2"]))).unwrap();
+
+        let wrong_passphrase_import = aw!(run(cli_for(import_test_name, &["import-backup", backup_path, "--passphrase", "hunter3"])));
+        assert!(matches!(wrong_passphrase_import, Err(CliError::Backup(BackupError::IntegrityCheckFailed))));
+        aw!(run(cli_for(import_test_name, &["import-backup", backup_path, "--passphrase", "hunter2"]))).unwrap();
+
+        let storage = HouseholdStorage::open(std::env::temp_dir().join(format!("family_wallet_cli_{}", import_test_name))).unwrap();
+        let ledger = storage.load_ledger().unwrap().unwrap();
+        let child_account = ledger.accounts.iter().find(|account| account.account_id == 2).unwrap();
+        assert_eq!(child_account.addresses.first().unwrap().to_string(), child_address);
+        assert_eq!(ledger.children.len(), 2);
+        // a household which already exists is never overwritten, whoever imports the backup
+        let encrypted_backup = EncryptedBackup::read_from(backup_path).unwrap();
+        let second_import = HeadOfTheHouse::import_backup(&storage, &mut Children::new(), &encrypted_backup, "hunter2", Network::Regtest);
+        assert!(matches!(second_import, Err(BackupError::HouseholdAlreadyExists)));
+        drop(storage);
+        let _ = std::fs::remove_file(backup_path);
+        remove_data_dir(test_name);
+        remove_data_dir(import_test_name);
+    }
+
+    #[test]
+    fn encrypted_seed_runs_commands_locked_and_unlocks_with_the_password(){
+        set_up();
+        let test_name = "encrypt_seed";
+        remove_data_dir(test_name);
+        let mnemonic_words = get_default_mnenomic_words().unwrap();
+
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["restore", "--skip-rescan"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["--password", "hunter2", "encrypt-seed"]))).unwrap();
+        aw!(run(cli_for(test_name, &["add-child", "2", "alice"]))).unwrap();
+
+        let locked_address = aw!(run(cli_for(test_name, &["new-address", "2"])));
+        assert!(matches!(locked_address, Err(CliError::Wallet(WalletError::WalletLocked))));
+        let wrong_password_address = aw!(run(cli_for(test_name, &["--password", "hunter3", "new-address", "2"])));
+        assert!(matches!(wrong_password_address, Err(CliError::Wallet(WalletError::IncorrectPassword))));
+        let first_address = aw!(run(cli_for(test_name, &["--password", "hunter2", "new-address", "2"]))).unwrap();
+        // the sub-account's key is saved once it has been opened, so it keeps receiving while locked
+        let second_address = aw!(run(cli_for(test_name, &["new-address", "2"]))).unwrap();
+
+        let mut children = Children::new();
+        let mut head_of_house = HeadOfTheHouse::new(&mut children, Some(mnemonic_words), Network::Regtest).unwrap();
+        head_of_house.create_new_user(&mut children, 2, String::from("alice"), vec![BitcoinPermissions::Receive]);
+        assert_eq!(head_of_house.get_new_address(2).unwrap().to_string(), first_address);
+        assert_eq!(head_of_house.get_new_address(2).unwrap().to_string(), second_address);
+        remove_data_dir(test_name);
+    }
+
+    #[test]
+    fn watch_only_household_issues_addresses_from_the_external_signers_descriptors(){
+        set_up();
+        let test_name = "watch_only_household";
+        remove_data_dir(test_name);
+        let mut seed_master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+        seed_master_account.open_sub_account(3).unwrap();
+        let descriptor = seed_master_account.wallet.get_descriptor_for_keychain(KeychainKind::External).to_string();
+        let sub_account_descriptor = seed_master_account.sub_account_wallets.get(&3).unwrap().get_descriptor_for_keychain(KeychainKind::External).to_string();
+
+        aw!(run(cli_for(test_name, &["create", "--descriptor", &descriptor, "--descriptor", &sub_account_descriptor]))).unwrap();
+        aw!(run(cli_for(test_name, &["add-child", "2", "alice"]))).unwrap();
+        aw!(run(cli_for(test_name, &["add-child", "3", "bob"]))).unwrap();
+        // alice's sub-account key was not given, so she receives on the master account's addresses
+        let alice_address = aw!(run(cli_for(test_name, &["new-address", "2"]))).unwrap();
+        let bob_address = aw!(run(cli_for(test_name, &["new-address", "3"]))).unwrap();
+
+        assert_eq!(alice_address, seed_master_account.generate_new_address().unwrap().to_string());
+        assert_eq!(bob_address, seed_master_account.generate_new_sub_account_address(3).unwrap().to_string());
+        let invalid_create = aw!(run(cli_for("watch_only_invalid", &["create", "--descriptor", &sub_account_descriptor])));
+        assert!(matches!(invalid_create, Err(CliError::Wallet(WalletError::InvalidDescriptor))));
+        remove_data_dir(test_name);
+        remove_data_dir("watch_only_invalid");
+    }
+
+    #[test]
+    fn multisig_household_is_reloaded_as_a_vault_with_its_cosigners(){
+        set_up();
+        let test_name = "multisig_household";
+        remove_data_dir(test_name);
+        let cosigner_key = get_multisig_cosigner_key(&get_default_mnenomic_words_2().unwrap(), 0);
+
+        let created = aw!(run(cli_for(test_name, &["create", "--cosigner", &cosigner_key, "--threshold", "2"]))).unwrap();
+        assert!(created.starts_with("Created a new 2 of 2 multisig regtest household"));
+        let mnemonic_words = created.lines().last().unwrap().to_string();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["add-child", "2", "alice"]))).unwrap();
+        // the cosigner gave no key of account 2, so alice receives on the vault's own addresses
+        let alice_address = aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["new-address", "2"]))).unwrap();
+        assert!(Address::from_str(&alice_address).unwrap().script_pubkey().is_v0_p2wsh());
+
+        assert!(Cli::try_parse_from(["family-wallet", "create", "--cosigner", &cosigner_key]).is_err());
+        assert!(Cli::try_parse_from(["family-wallet", "create", "--threshold", "2"]).is_err());
+        remove_data_dir(test_name);
+    }
+
+    #[test]
+    fn set_limits_is_saved(){
+        set_up();
+        let test_name = "set_limits";
+        remove_data_dir(test_name);
+        let mnemonic_words = get_default_mnenomic_words().unwrap();
+
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["restore", "--skip-rescan"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["add-child", "2", "alice"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["set-limits", "2", "--daily", "10000", "--monthly", "50000"]))).unwrap();
+
+        let storage = HouseholdStorage::open(std::env::temp_dir().join(format!("family_wallet_cli_{}", test_name))).unwrap();
+        let ledger = storage.load_ledger().unwrap().unwrap();
+        let child_account = ledger.accounts.iter().find(|account| account.account_id == 2).unwrap();
+        assert_eq!(child_account.spending_limits, SpendingLimits { max_per_transaction: None, daily: Some(10000), weekly: None, monthly: Some(50000) });
+        drop(storage);
+        remove_data_dir(test_name);
+    }
+
+    #[test]
+    fn granted_permission_is_saved_and_allows_transfer_requests(){
+        set_up();
+        let test_name = "grant_permission";
+        remove_data_dir(test_name);
+        let mnemonic_words = get_default_mnenomic_words().unwrap();
+
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["restore", "--skip-rescan"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["add-child", "2", "alice"]))).unwrap();
+        let refused_request = aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["request-transfer", "2", "5000"])));
+        assert!(matches!(refused_request, Err(CliError::Account(AccountError::MissingPermission(BitcoinPermissions::RequestTransfers)))));
+
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["grant", "2", "request-transfers"]))).unwrap();
+        aw!(run(cli_with_mnemonic(test_name, &mnemonic_words, &["request-transfer", "2", "5000"]))).unwrap();
+
+        let storage = HouseholdStorage::open(std::env::temp_dir().join(format!("family_wallet_cli_{}", test_name))).unwrap();
+        let ledger = storage.load_ledger().unwrap().unwrap();
+        assert_eq!(ledger.transfer_requests.requests[0].amount, 5000);
+        drop(storage);
+        remove_data_dir(test_name);
+    }
+
+    #[test]
+    fn set_whitelist_only_parses_enabled(){
+        let cli = Cli::try_parse_from(["family-wallet", "set-whitelist-only", "2", "false"]).unwrap();
+        match cli.command {
+            Command::SetWhitelistOnly { child_id, enabled } => {
+                assert_eq!(child_id, 2);
+                assert!(!enabled);
+            },
+            _ => panic!("expected the set-whitelist-only command"),
+        }
+    }
+
+    #[test]
+    fn spend_accepts_only_one_way_of_choosing_the_fee(){
+        let cli = Cli::try_parse_from(["family-wallet", "spend", "2", "10000", "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", "--priority", "high"]).unwrap();
+        match cli.command {
+            Command::Spend { priority, fee_rate, .. } => {
+                assert_eq!(priority, Some(FeePriority::High));
+                assert_eq!(fee_rate, None);
+            },
+            _ => panic!("expected the spend command"),
+        }
+
+        assert!(Cli::try_parse_from(["family-wallet", "spend", "2", "10000", "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", "--fee-rate", "5", "--priority", "high"]).is_err());
+    }
+
+    #[test]
+    fn transfer_to_child_fee_needs_on_chain(){
+        let cli = Cli::try_parse_from(["family-wallet", "transfer-to-child", "2", "50000", "--on-chain", "--priority", "high"]).unwrap();
+        match cli.command {
+            Command::TransferToChild { child_id, amount, on_chain, priority, .. } => {
+                assert_eq!((child_id, amount, on_chain), (2, 50_000, true));
+                assert_eq!(priority, Some(FeePriority::High));
+            },
+            _ => panic!("expected the transfer-to-child command"),
+        }
+
+        assert!(Cli::try_parse_from(["family-wallet", "transfer-to-child", "2", "50000", "--fee-rate", "3"]).is_err());
+        assert!(Cli::try_parse_from(["family-wallet", "transfer-to-child", "2", "50000", "--on-chain", "--fee-rate", "3", "--priority", "high"]).is_err());
+    }
+
+    #[test]
+    fn spend_batch_takes_each_payout_and_a_fee_policy(){
+        let cli = Cli::try_parse_from(["family-wallet", "spend-batch", "--payout", "2:30000:bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", "--payout", "1:10000:bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", "--fee-policy", "even"]).unwrap();
+        match cli.command {
+            Command::SpendBatch { payouts, fee_policy, fee_rate, .. } => {
+                assert_eq!(payouts.iter().map(|payout| (payout.user_id, payout.amount)).collect::<Vec<(i32, u64)>>(), vec![(2, 30_000), (1, 10_000)]);
+                assert_eq!(fee_policy, BatchFeePolicy::Even);
+                assert_eq!(fee_rate, None);
+            },
+            _ => panic!("expected the spend-batch command"),
+        }
+
+        assert!(Cli::try_parse_from(["family-wallet", "spend-batch"]).is_err());
+        assert!(Cli::try_parse_from(["family-wallet", "spend-batch", "--payout", "2:30000"]).is_err());
+    }
+
+    #[test]
+    fn bump_fee_needs_a_new_fee_rate(){
+        let txid = "4d3c7e0a1b9f2e8d6c5b4a3928171605f4e3d2c1b0a99887766554433221100f";
+        let cli = Cli::try_parse_from(["family-wallet", "bump-fee", txid, "--confirmation-target", "2"]).unwrap();
+        match cli.command {
+            Command::BumpFee { txid: bumped_txid, confirmation_target, .. } => {
+                assert_eq!(bumped_txid, Txid::from_str(txid).unwrap());
+                assert_eq!(confirmation_target, Some(2));
+            },
+            _ => panic!("expected the bump-fee command"),
+        }
+
+        assert!(Cli::try_parse_from(["family-wallet", "bump-fee", txid]).is_err());
+        assert!(Cli::try_parse_from(["family-wallet", "bump-fee", "not-a-txid", "--fee-rate", "5"]).is_err());
+    }
+
+    #[test]
+    fn cancel_needs_a_new_fee_rate(){
+        let txid = "6d7f7b1f9ab6a8ec2a3b67b2a1f0c7d5e7a6b9c8d1e2f3a4b5c6d7e8f9a0b1c2";
+        let cli = Cli::try_parse_from(["family-wallet", "cancel", txid, "--fee-rate", "5"]).unwrap();
+        match cli.command {
+            Command::Cancel { txid: cancelled_txid, fee_rate, .. } => {
+                assert_eq!(cancelled_txid, Txid::from_str(txid).unwrap());
+                assert_eq!(fee_rate, Some(5.0));
+            },
+            _ => panic!("expected the cancel command"),
+        }
+        assert!(Cli::try_parse_from(["family-wallet", "cancel", txid]).is_err());
+    }
+
+    #[test]
+    fn binary_psbt_is_only_written_to_a_file(){
+        let cli = Cli::try_parse_from(["family-wallet", "export-psbt", "1", "--out", "spend.psbt", "--encoding", "binary"]).unwrap();
+        match cli.command {
+            Command::ExportPsbt { proposal_id, out, encoding } => {
+                assert_eq!(proposal_id, 1);
+                assert_eq!(out, Some(PathBuf::from("spend.psbt")));
+                assert_eq!(encoding, PsbtEncoding::Binary);
+            },
+            _ => panic!("expected the export-psbt command"),
+        }
+
+        let cli = Cli::try_parse_from(["family-wallet", "build-psbt", "2", "10000", "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"]).unwrap();
+        assert!(matches!(cli.command, Command::BuildPsbt { encoding: PsbtEncoding::Base64, out: None, .. }));
+        assert!(Cli::try_parse_from(["family-wallet", "export-psbt", "1", "--encoding", "binary"]).is_err());
+    }
+
+    #[test]
+    fn create_fails_when_household_already_exists(){
+        set_up();
+        let test_name = "create_twice";
+        remove_data_dir(test_name);
+
+        aw!(run(cli_for(test_name, &["create"]))).unwrap();
+        let second_create_result = aw!(run(cli_for(test_name, &["create"])));
+
+        assert!(matches!(second_create_result, Err(CliError::HouseholdAlreadyExists)));
+        remove_data_dir(test_name);
+    }
+
+    #[test]
+    fn commands_require_mnemonic_words(){
+        set_up();
+        let test_name = "missing_mnemonic";
+        remove_data_dir(test_name);
+
+        aw!(run(cli_for(test_name, &["create"]))).unwrap();
+        let add_child_result = aw!(run(cli_for(test_name, &["add-child", "2", "alice"])));
+
+        assert!(matches!(add_child_result, Err(CliError::MissingMnemonic)));
+        remove_data_dir(test_name);
+    }
+}
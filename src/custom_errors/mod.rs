@@ -3,6 +3,9 @@ use std::io;
 
 use std::{fmt, env};
 
+use crate::permissions::BitcoinPermissions;
+use crate::spending_limits::{SpendTally, SpendingPeriod};
+
 
 /// An Error with an individual `Account`
 #[derive(Debug)]
@@ -11,6 +14,44 @@ pub enum AccountError {
     AccountDoesNotExist(&'static str),
     /// If an action attempted is not permitted on this `Account`.
     InsufficientAccount,
+    /// If a bitcoin address is invalid or belongs to a different network than the wallet.
+    InvalidAddress,
+    /// If a spend would exceed one of the `Account`'s spending limits.
+    /// Includes what the `Account` has already spent in each period.
+    SpendingLimitExceeded(SpendingPeriod, SpendTally),
+    /// If searching for a spend proposal and it does not exist.
+    SpendProposalDoesNotExist,
+    /// If searching for a transfer request and it does not exist.
+    TransferRequestDoesNotExist,
+    /// If the `Account` does not have the permission an action needs.
+    MissingPermission(BitcoinPermissions),
+    /// If the `Account` may only send to whitelisted addresses and the address is not whitelisted.
+    AddressNotWhitelisted,
+    /// If an address, or its label, is already in the `Account`'s address book.
+    AddressBookEntryAlreadyExists,
+    /// If searching for an address in the `Account`'s address book and it is not there.
+    AddressBookEntryDoesNotExist,
+    /// If a spend's fee rate, in sat/vB, is above the `Account`'s maximum fee rate.
+    /// Includes the `Account`'s maximum fee rate.
+    FeeRateAboveMaximum(f32),
+    /// If a fee rate is below the minimum relay fee rate.
+    FeeRateTooLow,
+    /// If a spend needs to be signed while the `MasterAccount` is locked.
+    WalletLocked,
+    /// If a spend needs to be signed by the `MasterAccount` but its keys are held by an external signer.
+    ExternalSigner,
+    /// If an imported PSBT is not of the transaction of the spend proposal it was imported into.
+    PsbtMismatch,
+    /// If a spend proposal is broadcast before every input of its transaction has been signed.
+    UnsignedTransaction,
+    /// If a transaction to replace is not one of the pending transactions, for example because it has been confirmed.
+    TransactionNotPending,
+    /// If an `Account` has no payments from outside the household waiting to be confirmed.
+    NoUnconfirmedPayments,
+    /// If a batched spend has no payouts.
+    EmptyBatch,
+    /// If a spend which needs the head of the house's approval is made in a way which can not wait for it, for example in a batch.
+    ApprovalRequired,
     /// A general catch all `Account` error.
     Default(&'static str),
 }
@@ -22,6 +63,24 @@ impl Display for AccountError {
         AccountError::AccountDoesNotExist(_e) => write!(f, "Account does not exist"),
         AccountError::Default(_e) => write!(f, "Default account error"),
         AccountError::InsufficientAccount  => write!(f, "Account is insufficient to take desired action"),
+        AccountError::InvalidAddress => write!(f, "Address is invalid for this wallet's network"),
+        AccountError::SpendProposalDoesNotExist => write!(f, "Spend proposal does not exist"),
+        AccountError::TransferRequestDoesNotExist => write!(f, "Transfer request does not exist"),
+        AccountError::MissingPermission(permission) => write!(f, "Account does not have the {} permission", permission),
+        AccountError::AddressNotWhitelisted => write!(f, "Account may only send to addresses in its address book"),
+        AccountError::AddressBookEntryAlreadyExists => write!(f, "Address or label is already in the address book"),
+        AccountError::AddressBookEntryDoesNotExist => write!(f, "Address is not in the address book"),
+        AccountError::FeeRateAboveMaximum(max_fee_rate) => write!(f, "Fee rate is above the account's maximum of {} sat/vB", max_fee_rate),
+        AccountError::FeeRateTooLow => write!(f, "Fee rate is below the minimum relay fee rate"),
+        AccountError::WalletLocked => write!(f, "The wallet is locked, unlock it with its password to sign"),
+        AccountError::ExternalSigner => write!(f, "The wallet is watch-only, its transactions are signed by an external signer"),
+        AccountError::PsbtMismatch => write!(f, "The PSBT is not of the spend proposal's transaction"),
+        AccountError::UnsignedTransaction => write!(f, "The transaction still needs signatures before it can be broadcast"),
+        AccountError::TransactionNotPending => write!(f, "The transaction is not pending"),
+        AccountError::NoUnconfirmedPayments => write!(f, "Account has no unconfirmed payments to speed up"),
+        AccountError::EmptyBatch => write!(f, "A batched spend needs at least one payout"),
+        AccountError::ApprovalRequired => write!(f, "The spend is above the account's approval threshold, spend it on its own to have it approved"),
+        AccountError::SpendingLimitExceeded(period, tally) => write!(f, "Spend exceeds the {} spending limit, already spent {} sats today, {} sats this week and {} sats this month", period, tally.daily, tally.weekly, tally.monthly),
       }
     }
   }
@@ -47,6 +106,34 @@ pub enum WalletError {
     /// An error associated with the wallet's public or private keys.
     /// For example trying to create an xpriv from an invalid mnemonic phrase. 
     KeyError,
+    /// An error related to the bitcoin network the wallet runs on.
+    /// For example an address which belongs to a different network than the wallet, or an unknown network name.
+    NetworkError,
+    /// If the `Account` does not have the permission an action needs.
+    MissingPermission(BitcoinPermissions),
+    /// If the electrum server can not estimate a fee rate.
+    FeeEstimationError,
+    /// An error opening the database a wallet is stored in.
+    DatabaseError,
+    /// If the wallet needs its private keys, for example to sign a transaction or derive a new sub-account, while it is locked.
+    WalletLocked,
+    /// If the password given to unlock the wallet does not decrypt its seed.
+    IncorrectPassword,
+    /// If the wallet is asked to unlock but its seed has not been encrypted with a password.
+    NoEncryptedSeed,
+    /// If a public descriptor can not be used to watch a BIP84 account, for example because it has no key origin or is for another network.
+    InvalidDescriptor,
+    /// If the wallet is asked to sign, or derive a new sub-account, but its private keys are held by an external signer.
+    ExternalSigner,
+    /// If a PSBT can not be decoded from base64 or its binary format.
+    InvalidPsbt,
+    /// If a transaction is broadcast before every input has been signed.
+    UnsignedTransaction,
+    /// If a multisig vault has no cosigners, a cosigner has no key of the vault's own account, or the threshold is not
+    /// between 1 and the number of keys.
+    InvalidMultisigPolicy,
+    /// If a transaction to replace is not one of the pending transactions, for example because it has been confirmed.
+    TransactionNotPending,
 }
 
 
@@ -57,6 +144,19 @@ impl fmt::Display for WalletError {
         WalletError::AddressError => write!(f, "Bitcoin address error"),
         WalletError::BroadcastTransactionError => write!(f, "Error broadcasting transaction"),
         WalletError::KeyError => write!(f, "Key error"),
+        WalletError::NetworkError => write!(f, "Bitcoin network error"),
+        WalletError::MissingPermission(permission) => write!(f, "Account does not have the {} permission", permission),
+        WalletError::FeeEstimationError => write!(f, "Error estimating the fee rate"),
+        WalletError::DatabaseError => write!(f, "Error opening the wallet database"),
+        WalletError::WalletLocked => write!(f, "The wallet is locked, unlock it with its password to sign"),
+        WalletError::IncorrectPassword => write!(f, "Unable to unlock the wallet, the password is wrong"),
+        WalletError::NoEncryptedSeed => write!(f, "The wallet's seed has not been encrypted with a password"),
+        WalletError::InvalidDescriptor => write!(f, "The descriptor is not a BIP84 account xpub with its key origin on the wallet's network"),
+        WalletError::ExternalSigner => write!(f, "The wallet is watch-only, its transactions are signed by an external signer"),
+        WalletError::InvalidPsbt => write!(f, "Unable to decode the PSBT"),
+        WalletError::UnsignedTransaction => write!(f, "The transaction still needs signatures before it can be broadcast"),
+        WalletError::InvalidMultisigPolicy => write!(f, "The multisig threshold must be between 1 and the number of keys, and every cosigner must give its key of account 0"),
+        WalletError::TransactionNotPending => write!(f, "The transaction is not pending"),
       }
     }
   }
@@ -98,5 +198,275 @@ impl From<bdk::keys::KeyError> for WalletError {
     }
 }
 
+impl From<bdk::sled::Error> for WalletError {
+    fn from(_error: bdk::sled::Error) -> Self {
+        WalletError::DatabaseError
+    }
+}
+
+impl From<bdk::bitcoin::util::bip32::Error> for WalletError {
+    fn from(_error: bdk::bitcoin::util::bip32::Error) -> Self {
+        WalletError::KeyError
+    }
+}
+
+impl From<EncryptionError> for WalletError {
+    fn from(error: EncryptionError) -> Self {
+        match error {
+            EncryptionError::IntegrityCheckFailed => WalletError::IncorrectPassword,
+            EncryptionError::EncryptionFailed => WalletError::KeyError,
+        }
+    }
+}
+
+/// An error encrypting or decrypting data with a passphrase.
+#[derive(Debug)]
+pub enum EncryptionError {
+    /// If the data can not be encrypted, for example because the system is unable to generate randomness.
+    EncryptionFailed,
+    /// If the data can not be decrypted, either the passphrase is wrong or the data has been altered.
+    IntegrityCheckFailed,
+}
+
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+        EncryptionError::EncryptionFailed => write!(f, "Error encrypting"),
+        EncryptionError::IntegrityCheckFailed => write!(f, "Unable to decrypt, the passphrase is wrong or the data has been altered"),
+      }
+    }
+  }
+
+/// An error related to persisting the household to disk.
+#[derive(Debug)]
+pub enum StorageError {
+    /// An error opening or writing to the underlying database.
+    DatabaseError,
+    /// An error converting the household ledger to or from its stored format.
+    SerializationError,
+    /// An error rebuilding the `MasterAccount` wallet from the stored database.
+    /// For example trying to load a household with an invalid mnemonic phrase.
+    WalletError,
+    /// If the stored household was created on a different bitcoin network than the one requested.
+    NetworkMismatch,
+}
+
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+        StorageError::DatabaseError => write!(f, "Error accessing the household database"),
+        StorageError::SerializationError => write!(f, "Error serializing the household ledger"),
+        StorageError::WalletError => write!(f, "Error loading the household wallet"),
+        StorageError::NetworkMismatch => write!(f, "Stored household belongs to a different network"),
+      }
+    }
+  }
+
+
+impl From<bdk::sled::Error> for StorageError {
+    fn from(_error: bdk::sled::Error) -> Self {
+        StorageError::DatabaseError
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(_error: serde_json::Error) -> Self {
+        StorageError::SerializationError
+    }
+}
 
+impl From<AccountError> for StorageError {
+    fn from(_error: AccountError) -> Self {
+        StorageError::WalletError
+    }
+}
+
+impl From<WalletError> for StorageError {
+    fn from(_error: WalletError) -> Self {
+        StorageError::WalletError
+    }
+}
 
+/// An error exporting or importing an encrypted household backup.
+#[derive(Debug)]
+pub enum BackupError {
+    /// If the backup file was written by a newer, or unknown, backup format.
+    UnsupportedVersion(u32),
+    /// If the backup belongs to a different bitcoin network than the one requested.
+    NetworkMismatch,
+    /// If the backup can not be decrypted, either the passphrase is wrong or the file has been altered.
+    IntegrityCheckFailed,
+    /// If the mnemonic words given when exporting do not belong to the household's wallet.
+    MnemonicMismatch,
+    /// An error encrypting the backup.
+    EncryptionError,
+    /// An error converting the backup to or from its stored format.
+    SerializationError,
+    /// An error reading or writing the backup file.
+    IoError,
+    /// If a backup is imported into storage which already holds a household.
+    HouseholdAlreadyExists,
+    /// An error saving the imported household.
+    Storage(StorageError),
+}
+
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+        BackupError::UnsupportedVersion(version) => write!(f, "Backup version {} is not supported", version),
+        BackupError::NetworkMismatch => write!(f, "Backup belongs to a different network"),
+        BackupError::IntegrityCheckFailed => write!(f, "Unable to decrypt the backup, the passphrase is wrong or the backup has been altered"),
+        BackupError::MnemonicMismatch => write!(f, "Mnemonic words do not belong to this household"),
+        BackupError::EncryptionError => write!(f, "Error encrypting the backup"),
+        BackupError::SerializationError => write!(f, "Error serializing the backup"),
+        BackupError::IoError => write!(f, "Error reading or writing the backup file"),
+        BackupError::HouseholdAlreadyExists => write!(f, "A household already exists, a backup can only be imported into an empty data directory"),
+        BackupError::Storage(error) => write!(f, "{}", error),
+      }
+    }
+  }
+
+
+impl From<serde_json::Error> for BackupError {
+    fn from(_error: serde_json::Error) -> Self {
+        BackupError::SerializationError
+    }
+}
+
+impl From<io::Error> for BackupError {
+    fn from(_error: io::Error) -> Self {
+        BackupError::IoError
+    }
+}
+
+impl From<StorageError> for BackupError {
+    fn from(error: StorageError) -> Self {
+        BackupError::Storage(error)
+    }
+}
+
+impl From<EncryptionError> for BackupError {
+    fn from(error: EncryptionError) -> Self {
+        match error {
+            EncryptionError::IntegrityCheckFailed => BackupError::IntegrityCheckFailed,
+            EncryptionError::EncryptionFailed => BackupError::EncryptionError,
+        }
+    }
+}
+
+/// An error from running a command line interface command.
+#[derive(Debug)]
+pub enum CliError {
+    /// If `create`, `restore` or `import-backup` is run where a household has already been saved.
+    HouseholdAlreadyExists,
+    /// If a command other than `create` or `restore` is run before a household has been saved.
+    HouseholdDoesNotExist,
+    /// If a command needs the household's mnemonic words and none were given.
+    MissingMnemonic,
+    /// If a command needs the password the household's seed is encrypted with and none was given.
+    MissingPassword,
+    /// If the id given does not belong to any `Child`.
+    ChildDoesNotExist,
+    /// If the `network` environment variable is set to something other than bitcoin, testnet, signet or regtest.
+    InvalidNetwork,
+    /// If the http server could not be started or stopped unexpectedly.
+    ServerError,
+    /// If a file named on the command line can not be read or written.
+    FileError,
+    Account(AccountError),
+    Wallet(WalletError),
+    Storage(StorageError),
+    Backup(BackupError),
+}
+
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+        CliError::HouseholdAlreadyExists => write!(f, "A household already exists in this data directory"),
+        CliError::HouseholdDoesNotExist => write!(f, "No household exists in this data directory, run create or restore first"),
+        CliError::MissingMnemonic => write!(f, "The household mnemonic words are required, set the mnemonic environment variable, or pass --password if the seed has been encrypted"),
+        CliError::MissingPassword => write!(f, "A password is required, pass --password or set the wallet_password environment variable"),
+        CliError::ChildDoesNotExist => write!(f, "Child does not exist"),
+        CliError::InvalidNetwork => write!(f, "The network environment variable must be one of bitcoin, testnet, signet or regtest"),
+        CliError::ServerError => write!(f, "Error running the http server"),
+        CliError::FileError => write!(f, "Unable to read or write the file"),
+        CliError::Account(error) => write!(f, "{}", error),
+        CliError::Wallet(error) => write!(f, "{}", error),
+        CliError::Storage(error) => write!(f, "{}", error),
+        CliError::Backup(error) => write!(f, "{}", error),
+      }
+    }
+  }
+
+
+impl From<AccountError> for CliError {
+    fn from(error: AccountError) -> Self {
+        CliError::Account(error)
+    }
+}
+
+impl From<WalletError> for CliError {
+    fn from(error: WalletError) -> Self {
+        CliError::Wallet(error)
+    }
+}
+
+impl From<StorageError> for CliError {
+    fn from(error: StorageError) -> Self {
+        CliError::Storage(error)
+    }
+}
+
+impl From<BackupError> for CliError {
+    fn from(error: BackupError) -> Self {
+        CliError::Backup(error)
+    }
+}
+
+/// An error returned from the http api.
+#[derive(Debug)]
+pub enum ApiError {
+    /// If the household is unavailable, for example because a previous request panicked while using it.
+    HouseholdUnavailable,
+    /// If the id given does not belong to any `Child`.
+    ChildDoesNotExist,
+    Account(AccountError),
+    Wallet(WalletError),
+    Storage(StorageError),
+}
+
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+      match self {
+        ApiError::HouseholdUnavailable => write!(f, "Household is unavailable"),
+        ApiError::ChildDoesNotExist => write!(f, "Child does not exist"),
+        ApiError::Account(error) => write!(f, "{}", error),
+        ApiError::Wallet(error) => write!(f, "{}", error),
+        ApiError::Storage(error) => write!(f, "{}", error),
+      }
+    }
+  }
+
+
+impl From<AccountError> for ApiError {
+    fn from(error: AccountError) -> Self {
+        ApiError::Account(error)
+    }
+}
+
+impl From<WalletError> for ApiError {
+    fn from(error: WalletError) -> Self {
+        ApiError::Wallet(error)
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(error: StorageError) -> Self {
+        ApiError::Storage(error)
+    }
+}
@@ -0,0 +1,106 @@
+use std::num::NonZeroU32;
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::EncryptionError;
+
+/// How many PBKDF2-HMAC-SHA256 iterations turn a passphrase into an encryption key.
+pub const KDF_ITERATIONS: u32 = 600_000;
+pub const KDF_ALGORITHM: &str = "pbkdf2-hmac-sha256";
+/// The cipher everything encrypted with a passphrase uses.
+pub const CIPHER: &str = "aes-256-gcm";
+const SALT_LEN: usize = 16;
+
+/// How an encryption key was derived from a passphrase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyDerivation {
+    pub algorithm: String,
+    pub iterations: u32,
+    /// Base64 encoded random salt.
+    pub salt: String,
+}
+
+impl KeyDerivation {
+    /// A PBKDF2-HMAC-SHA256 key derivation with a new random salt.
+    ///
+    /// # Errors
+    /// If the system is unable to generate randomness return an `EncryptionError`.
+    pub fn generate() -> Result<KeyDerivation, EncryptionError> {
+        let mut salt = [0u8; SALT_LEN];
+        SystemRandom::new().fill(&mut salt).map_err(|_e| EncryptionError::EncryptionFailed)?;
+        Ok(KeyDerivation {
+            algorithm: String::from(KDF_ALGORITHM),
+            iterations: KDF_ITERATIONS,
+            salt: base64::encode(salt),
+        })
+    }
+
+    /// Return true if keys can be derived the way this `KeyDerivation` describes, else return false.
+    pub fn is_supported(&self) -> bool {
+        self.algorithm == KDF_ALGORITHM
+    }
+
+    /// Derive the AES-256-GCM key from `passphrase`.
+    ///
+    /// # Errors
+    /// If the salt can not be decoded, or there are no iterations, return an `EncryptionError`.
+    fn derive_key(&self, passphrase: &str) -> Result<LessSafeKey, EncryptionError> {
+        let iterations = NonZeroU32::new(self.iterations).ok_or(EncryptionError::IntegrityCheckFailed)?;
+        let salt = decode_base64(&self.salt)?;
+        let mut key_bytes = [0u8; 32];
+        pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, passphrase.as_bytes(), &mut key_bytes);
+        let unbound_key = UnboundKey::new(&AES_256_GCM, &key_bytes).map_err(|_e| EncryptionError::EncryptionFailed)?;
+        Ok(LessSafeKey::new(unbound_key))
+    }
+}
+
+/// Data encrypted with AES-256-GCM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sealed {
+    /// Base64 encoded nonce the data was encrypted with.
+    pub nonce: String,
+    /// Base64 encoded encrypted data, followed by its authentication tag.
+    pub ciphertext: String,
+}
+
+/// Encrypt `plaintext` with the key `key_derivation` derives from `passphrase`.
+///
+/// The `associated_data` is not encrypted but is authenticated along with the ciphertext, so it must be passed,
+/// unchanged, to `open` for the data to decrypt.
+///
+/// # Errors
+/// If the key can not be derived, or the data can not be encrypted, return an `EncryptionError`.
+pub fn seal(key_derivation: &KeyDerivation, passphrase: &str, associated_data: &[u8], mut plaintext: Vec<u8>) -> Result<Sealed, EncryptionError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|_e| EncryptionError::EncryptionFailed)?;
+    let key = key_derivation.derive_key(passphrase)?;
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(associated_data), &mut plaintext)
+        .map_err(|_e| EncryptionError::EncryptionFailed)?;
+    Ok(Sealed {
+        nonce: base64::encode(nonce),
+        ciphertext: base64::encode(plaintext),
+    })
+}
+
+/// Decrypt data encrypted by `seal`.
+///
+/// # Errors
+/// If the passphrase is wrong, or the ciphertext or associated data have been altered, return an `EncryptionError`.
+pub fn open(key_derivation: &KeyDerivation, passphrase: &str, associated_data: &[u8], sealed: &Sealed) -> Result<Vec<u8>, EncryptionError> {
+    let key = key_derivation.derive_key(passphrase)?;
+    let nonce: [u8; NONCE_LEN] = decode_base64(&sealed.nonce)?.try_into().map_err(|_e| EncryptionError::IntegrityCheckFailed)?;
+    let mut in_out = decode_base64(&sealed.ciphertext)?;
+    let plaintext_len = key.open_in_place(Nonce::assume_unique_for_key(nonce), Aad::from(associated_data), &mut in_out)
+        .map_err(|_e| EncryptionError::IntegrityCheckFailed)?
+        .len();
+    in_out.truncate(plaintext_len);
+    Ok(in_out)
+}
+
+/// Decode a base64 field of encrypted data, a field which is not base64 means the data was altered.
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, EncryptionError> {
+    base64::decode(encoded).map_err(|_e| EncryptionError::IntegrityCheckFailed)
+}
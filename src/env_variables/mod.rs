@@ -1,4 +1,7 @@
 use std::env;
+use std::str::FromStr;
+use bdk::bitcoin::Network;
+use crate::custom_errors::{CliError, WalletError};
 
 /// An environment variable represented as a struct.
 /// 
@@ -21,7 +24,15 @@ pub struct EnvironmentVariable {
 }
 
 /// Set default enviornment variables based off of the current runtime environment (test, dev/prod).
-pub fn set_env_variables(){
+/// 
+/// In dev/prod the `network` environment variable may be set to bitcoin, testnet, signet or regtest
+/// to choose which network the app runs on, if it is not set the app runs on bitcoin mainnet.
+/// The electrum server is chosen to match the network.
+/// 
+/// # Errors
+/// If the `network` environment variable is set but is not one of bitcoin, testnet, signet or regtest return a `CliError`,
+/// rather than falling back to bitcoin mainnet on a typo.
+pub fn set_env_variables()-> Result<(), CliError>{
     let mut env_variables:Vec<EnvironmentVariable> = vec![];
 
     // if we are in testing environmnt set the test env variables
@@ -41,31 +52,104 @@ pub fn set_env_variables(){
             name:"electrum_server",
             value:"127.0.0.1:50000"
         };
+
+        let regtest_network = EnvironmentVariable {
+            name:"network",
+            value:"regtest"
+        };
     
         env_variables.push(test_address);
         env_variables.push(regtest_rpc);
         env_variables.push(nigiri_electrum_server);
+        env_variables.push(regtest_network);
     } else {
         // we are in a dev or production environment, set appropriate env variables
-        let blockstream_electrum_server = EnvironmentVariable {
+        let network = network_or_default(env::var("network")).map_err(|_e| CliError::InvalidNetwork)?;
+
+        let network_variable = EnvironmentVariable {
+            name:"network",
+            value: network_name(network)
+        };
+
+        let electrum_server = EnvironmentVariable {
             name:"electrum_server",
-            value:"ssl://electrum.blockstream.info:60002"
+            value: default_electrum_server(network)
         };
-        env_variables.push(blockstream_electrum_server);
+        env_variables.push(network_variable);
+        env_variables.push(electrum_server);
     }
 
 
     for env_variable in env_variables.iter() {
         env::set_var(env_variable.name, env_variable.value);
     }
+    Ok(())
 }   
 
+/// Get the `Network` the app is configured to run on from the `network` environment variable.
+/// 
+/// # Errors
+/// If the `network` environment variable is not set, or is not one of bitcoin, testnet, signet or regtest
+/// return a `WalletError`.
+pub fn get_network() -> Result<Network, WalletError> {
+    let network_name = env::var("network")?;
+    Network::from_str(&network_name).map_err(|_e| WalletError::NetworkError)
+}
+
+/// Get the `Network` named by the value of the `network` environment variable, bitcoin mainnet if it is not set.
+/// 
+/// # Errors
+/// If the variable is set but is not one of bitcoin, testnet, signet or regtest, or is not unicode, return a `WalletError`.
+fn network_or_default(network_variable: Result<String, env::VarError>) -> Result<Network, WalletError> {
+    match network_variable {
+        Err(env::VarError::NotPresent) => Ok(Network::Bitcoin),
+        Err(_error) => Err(WalletError::NetworkError),
+        Ok(network_name) => Network::from_str(&network_name).map_err(|_e| WalletError::NetworkError),
+    }
+}
+
+/// The name the `network` environment variable uses for a `Network`.
+fn network_name(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "bitcoin",
+        Network::Testnet => "testnet",
+        Network::Signet => "signet",
+        Network::Regtest => "regtest",
+    }
+}
+
+/// The electrum server used by default for a `Network`.
+/// 
+/// Blockstream hosts mainnet and testnet servers, mempool.space hosts a signet server, and regtest
+/// expects a local nigiri electrum server.
+fn default_electrum_server(network: Network) -> &'static str {
+    match network {
+        Network::Bitcoin => "ssl://electrum.blockstream.info:50002",
+        Network::Testnet => "ssl://electrum.blockstream.info:60002",
+        Network::Signet => "ssl://mempool.space:60602",
+        Network::Regtest => "127.0.0.1:50000",
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
     #[test]
     fn test_set_env_variables_sets_env_variables(){
-        set_env_variables();
+        set_env_variables().unwrap();
         assert_eq!(env::var("test_address").unwrap(), "bcrt1q2ltw5646zcdxcj7hvv47mklqy8la6ta83p6egw")
     }
+
+    #[test]
+    fn test_get_network_is_regtest_in_test_environment(){
+        set_env_variables().unwrap();
+        assert_eq!(get_network().unwrap(), Network::Regtest)
+    }
+
+    #[test]
+    fn unset_network_defaults_to_bitcoin_but_a_misspelt_one_is_an_error(){
+        assert_eq!(network_or_default(Err(env::VarError::NotPresent)).unwrap(), Network::Bitcoin);
+        assert_eq!(network_or_default(Ok(String::from("testnet"))).unwrap(), Network::Testnet);
+        assert!(matches!(network_or_default(Ok(String::from("testnt"))), Err(WalletError::NetworkError)));
+    }
 }
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// The fee rate used when a spend does not choose one.
+pub const DEFAULT_SAT_PER_VB: f32 = 1.0;
+
+/// The lowest fee rate nodes will relay a transaction with.
+pub const MINIMUM_SAT_PER_VB: f32 = 1.0;
+
+/// How urgently a spend should confirm, for when the exact fee rate does not matter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeePriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl FeePriority {
+    /// The number of blocks a spend with this priority should confirm within.
+    pub fn confirmation_target(&self) -> usize {
+        match self {
+            FeePriority::Low => 144,
+            FeePriority::Normal => 6,
+            FeePriority::High => 1,
+        }
+    }
+}
+
+impl fmt::Display for FeePriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeePriority::Low => write!(f, "low"),
+            FeePriority::Normal => write!(f, "normal"),
+            FeePriority::High => write!(f, "high"),
+        }
+    }
+}
+
+impl FromStr for FeePriority {
+    type Err = String;
+
+    fn from_str(priority: &str) -> Result<Self, Self::Err> {
+        match priority {
+            "low" => Ok(FeePriority::Low),
+            "normal" => Ok(FeePriority::Normal),
+            "high" => Ok(FeePriority::High),
+            _ => Err(format!("unknown fee priority {}, expected low, normal or high", priority)),
+        }
+    }
+}
+
+/// How the fee rate of a spend is chosen.
+///
+/// Serialized as `{"sat_per_vb": 5.0}`, `{"confirmation_target": 6}` or `{"priority": "high"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeSelection {
+    /// An explicit fee rate in sat/vB.
+    SatPerVb(f32),
+    /// The fee rate the electrum server estimates will confirm within this many blocks.
+    ConfirmationTarget(usize),
+    /// The fee rate estimated for a named priority.
+    Priority(FeePriority),
+}
+
+impl Default for FeeSelection {
+    fn default() -> Self {
+        FeeSelection::SatPerVb(DEFAULT_SAT_PER_VB)
+    }
+}
+
+impl fmt::Display for FeeSelection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeeSelection::SatPerVb(sat_per_vb) => write!(f, "{} sat/vB", sat_per_vb),
+            FeeSelection::ConfirmationTarget(blocks) => write!(f, "confirmation within {} blocks", blocks),
+            FeeSelection::Priority(priority) => write!(f, "{} priority", priority),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fee_selection_serializes_by_its_kind(){
+        assert_eq!(serde_json::to_string(&FeeSelection::SatPerVb(5.0)).unwrap(), r#"{"sat_per_vb":5.0}"#);
+        assert_eq!(serde_json::from_str::<FeeSelection>(r#"{"confirmation_target":3}"#).unwrap(), FeeSelection::ConfirmationTarget(3));
+        assert_eq!(serde_json::from_str::<FeeSelection>(r#"{"priority":"high"}"#).unwrap(), FeeSelection::Priority(FeePriority::High));
+    }
+
+    #[test]
+    fn higher_priority_targets_fewer_blocks(){
+        assert!(FeePriority::High.confirmation_target() < FeePriority::Normal.confirmation_target());
+        assert!(FeePriority::Normal.confirmation_target() < FeePriority::Low.confirmation_target());
+        assert_eq!("normal".parse::<FeePriority>().unwrap(), FeePriority::Normal);
+    }
+}
@@ -2,13 +2,55 @@
 
 use crate::child;
 use crate::permissions::BitcoinPermissions;
-use crate::account::Account;
+use crate::account::{Account, PendingCancellation, PendingOnChainTransfer};
 use crate::master_account::MasterAccount;
 use crate::children::Children;
-use crate::helpers::{convert_float_to_satoshis};
-use crate::custom_errors::{AccountError, WalletError};
-use bdk::{TransactionDetails};
-use bdk::bitcoin::Address;
+use crate::helpers::{convert_float_to_satoshis, get_current_timestamp};
+use crate::custom_errors::{AccountError, BackupError, WalletError, StorageError};
+use crate::backup::{EncryptedBackup, HouseholdBackup};
+use crate::logging::Redacted;
+use crate::multisig::MultisigPolicy;
+use crate::offline_signing::is_finalized;
+use crate::seed_store::{EncryptedSeed, SeedSecrets, WatchOnlyKeys};
+use crate::storage::{HouseholdLedger, HouseholdStorage};
+use crate::spending_limits::{SpendTally, SpendingLimits};
+use crate::allowances::{AllowanceInterval, AllowanceRecord, AllowanceScheduler, InsufficientFundsPolicy};
+use crate::spend_proposals::{SpendProposal, SpendProposals};
+use crate::transfer_requests::{TransferRequest, TransferRequests};
+use crate::address_book::{AddressBook, AddressBookEntry};
+use crate::fees::{FeeSelection, MINIMUM_SAT_PER_VB};
+use crate::restore::{discovered_child_permissions, ChildMetadata, RestoredAccount};
+use crate::batch_payouts::{account_totals, BatchFeePolicy, BatchPayout};
+use tracing::{info, instrument, warn};
+use bdk::{FeeRate, KeychainKind, LocalUtxo, TransactionDetails};
+use bdk::wallet::AddressInfo;
+use bdk::bitcoin::{Address, Network, OutPoint, Script, Transaction, Txid};
+use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
+
+/// The wallet's utxos, sorted by who may spend them in a spend by one `Account`.
+struct AccountCoins {
+    /// Utxos locked to the `Account`'s addresses.
+    own: Vec<OutPoint>,
+    /// Utxos the `MasterAccount` sent to the `Account`'s addresses by confirmed on chain transfers, counted as bitcoin transfered from the `MasterAccount`.
+    transfered: Vec<OutPoint>,
+    /// Utxos which belong to the `MasterAccount`, including those locked to the head of the house's `Account`.
+    master: Vec<OutPoint>,
+    /// Utxos locked to other children's addresses, or already spent by a proposal waiting for approval.
+    unspendable: Vec<OutPoint>,
+}
+
+/// The BIP84 account index of the sub-account wallet a new `Account` is given.
+/// 
+/// A child's sub-account index is its account id, so the head of the house's `Account`, which uses the
+/// `MasterAccount`'s own wallet on account index 0, is the only one without a sub-account.
+fn sub_account_index(account_id: i32) -> Option<u32> {
+    u32::try_from(account_id).ok().filter(|account_index| *account_index > 1)
+}
+
+/// The permissions of the default `Account` created for the head of the house.
+pub fn main_account_permissions() -> Vec<BitcoinPermissions> {
+    vec![BitcoinPermissions::Send, BitcoinPermissions::Receive, BitcoinPermissions::ViewTransactionHistory, BitcoinPermissions::RequestTransfers, BitcoinPermissions::ManageChildren]
+}
 
 /// A struct which manages the relationship between an `Account` and the `MasterAccount`.
 /// 
@@ -21,28 +63,386 @@ pub struct HeadOfTheHouse {
     pub accounts: Vec<Account>,
     pub master_account: MasterAccount,
     pub user_id: i32, //TODO is this user_id even used?
+    pub allowance_scheduler: AllowanceScheduler,
+    pub spend_proposals: SpendProposals,
+    pub transfer_requests: TransferRequests,
 }
 
 impl  HeadOfTheHouse {
     /// Function which creates a new `HeadOfHouse` with a default `MasterAccount` and a default `Child` with full permissions and a default `Account`.  
     /// 
     /// The default `Child` and `Account` share the same user_id and therefore are related.
+    /// The `MasterAccount` wallet will run on the bitcoin `network`.
     /// 
     /// # Errors
     /// If an invalid mnemonic is passed then the fn will return an `AccountError`.
     /// 
     /// TODO do we need this default account which is created?
-    pub fn new(mut children: &mut Children, mnemonic_words: Option<String>)-> Result<HeadOfTheHouse, AccountError> {
+    pub fn new(mut children: &mut Children, mnemonic_words: Option<String>, network: Network)-> Result<HeadOfTheHouse, AccountError> {
         let mut head_of_house = HeadOfTheHouse {
             accounts: vec![],
             user_id: 0,
-            master_account: MasterAccount::new(mnemonic_words)?
+            master_account: MasterAccount::new(mnemonic_words, network)?,
+            allowance_scheduler: AllowanceScheduler::default(),
+            spend_proposals: SpendProposals::default(),
+            transfer_requests: TransferRequests::default(),
+
+        };
+        head_of_house.create_new_user(&mut children, 1, String::from("main"), main_account_permissions());
+        Ok(head_of_house)
+    }
+
+    /// Load the household saved in `storage`, or create a new one there if nothing has been saved yet.
+    /// 
+    /// The `MasterAccount` wallet is built on the storage's wallet database, and the saved `HouseholdLedger`
+    /// is used to restore every `Account`, every `Child` into `children` and the `MasterAccount`'s transfer accounting.
+    /// The mnemonic is not stored, so the same `mnemonic_words` the household was created with must be passed in.
+    /// 
+    /// # Errors
+    /// If the `mnemonic_words` do not match the stored wallet return a `StorageError`.
+    /// If the stored household was created on a different `network` return a `StorageError`.
+    /// If the storage can not be read return a `StorageError`.
+    pub fn load(storage: &HouseholdStorage, children: &mut Children, mnemonic_words: Option<String>, network: Network)-> Result<HeadOfTheHouse, StorageError> {
+        HeadOfTheHouse::load_with_bip39_passphrase(storage, children, mnemonic_words, None, network)
+    }
+
+    /// Load the household saved in `storage`, like `load`, for a household whose seed words are extended with a BIP39 passphrase.
+    /// 
+    /// A household saved as a multisig vault is loaded as one, with the cosigners saved in its ledger.
+    /// 
+    /// # Errors
+    /// If the `mnemonic_words` and `bip39_passphrase` do not match the stored wallet return a `StorageError`.
+    /// If the stored household was created on a different `network` return a `StorageError`.
+    /// If the storage can not be read return a `StorageError`.
+    pub fn load_with_bip39_passphrase(storage: &HouseholdStorage, children: &mut Children, mnemonic_words: Option<String>, bip39_passphrase: Option<String>, network: Network)-> Result<HeadOfTheHouse, StorageError> {
+        let stored_ledger = HeadOfTheHouse::load_ledger_for_network(storage, network)?;
+        let master_account = HeadOfTheHouse::generate_master_account(mnemonic_words, bip39_passphrase, stored_ledger.as_ref(), network, storage)?;
+        HeadOfTheHouse::load_with_master_account(children, stored_ledger, master_account)
+    }
+
+    /// Load the multisig vault saved in `storage`, or create a new one there if nothing has been saved yet, whose spends need
+    /// the signatures of the `multisig` policy's cosigners as well as the seed's.
+    /// 
+    /// Children are accounted for as in any household, a child whose sub-account key every cosigner has given gets a sub-account
+    /// of the vault, and any other child receives on the vault's own addresses.
+    /// 
+    /// # Errors
+    /// If the `mnemonic_words` and `bip39_passphrase` do not match the stored wallet, or the seed is also a cosigner, return a `StorageError`.
+    /// If the stored household was created on a different `network` return a `StorageError`.
+    /// If the storage can not be read return a `StorageError`.
+    pub fn load_multisig(storage: &HouseholdStorage, children: &mut Children, mnemonic_words: Option<String>, bip39_passphrase: Option<String>, multisig: MultisigPolicy, network: Network)-> Result<HeadOfTheHouse, StorageError> {
+        let stored_ledger = HeadOfTheHouse::load_ledger_for_network(storage, network)?;
+        let master_account = MasterAccount::new_multisig(mnemonic_words, bip39_passphrase, multisig, network, storage.wallet_store())?;
+        HeadOfTheHouse::load_with_master_account(children, stored_ledger, master_account)
+    }
+
+    /// Generate the `MasterAccount` of the household `ledger` from its seed, a multisig vault if the ledger's keys say it is one.
+    /// 
+    /// # Errors
+    /// If the seed does not match the wallets stored in `storage` return a `StorageError`.
+    fn generate_master_account(mnemonic_words: Option<String>, bip39_passphrase: Option<String>, ledger: Option<&HouseholdLedger>, network: Network, storage: &HouseholdStorage)-> Result<MasterAccount, StorageError> {
+        let multisig = ledger.and_then(|ledger| ledger.watch_only_keys.as_ref()).and_then(|watch_only_keys| watch_only_keys.multisig.clone());
+        match multisig {
+            Some(multisig) => Ok(MasterAccount::new_multisig(mnemonic_words, bip39_passphrase, multisig, network, storage.wallet_store())?),
+            None => Ok(MasterAccount::new_with_bip39_passphrase(mnemonic_words, bip39_passphrase, network, storage.wallet_store())?),
+        }
+    }
+
+    /// Load the household saved in `storage` without its seed words, with a locked `MasterAccount` which watches its wallets
+    /// from the `WatchOnlyKeys` saved in the ledger.
+    /// 
+    /// Balances, addresses of children whose sub-account has been opened before, and transactions can be read while it is locked,
+    /// and spend proposals can be made, but nothing can be signed until the `MasterAccount` is unlocked.
+    /// 
+    /// # Errors
+    /// If no household has been saved, or it was saved before its keys were, return a `StorageError`.
+    /// If the stored household was created on a different `network` return a `StorageError`.
+    /// If the storage can not be read return a `StorageError`.
+    pub fn load_watch_only(storage: &HouseholdStorage, children: &mut Children, network: Network)-> Result<HeadOfTheHouse, StorageError> {
+        let stored_ledger = HeadOfTheHouse::load_ledger_for_network(storage, network)?;
+        let watch_only_keys = stored_ledger.as_ref().and_then(|ledger| ledger.watch_only_keys.clone()).ok_or(StorageError::WalletError)?;
+        let master_account = MasterAccount::new_watch_only(watch_only_keys, network, storage.wallet_store())?;
+        HeadOfTheHouse::load_with_master_account(children, stored_ledger, master_account)
+    }
+
+    /// Load the household saved in `storage`, or create a new one there if nothing has been saved yet, with a `MasterAccount`
+    /// watching the accounts of `watch_only_keys`.
+    /// 
+    /// This is how a household whose keys are held by an external signer is created, from the keys `WatchOnlyKeys::from_descriptors`
+    /// reads. Addresses, balances, pending transactions and the children's accounting all work, and spends are held as spend
+    /// proposals with their unsigned PSBT, for the external signer to sign.
+    /// 
+    /// # Errors
+    /// If the stored household was created on a different `network`, or with different keys, return a `StorageError`.
+    /// If the storage can not be read return a `StorageError`.
+    pub fn load_from_watch_only_keys(storage: &HouseholdStorage, children: &mut Children, watch_only_keys: WatchOnlyKeys, network: Network)-> Result<HeadOfTheHouse, StorageError> {
+        let stored_ledger = HeadOfTheHouse::load_ledger_for_network(storage, network)?;
+        let master_account = MasterAccount::new_watch_only(watch_only_keys, network, storage.wallet_store())?;
+        HeadOfTheHouse::load_with_master_account(children, stored_ledger, master_account)
+    }
+
+    /// Read the `HouseholdLedger` saved in `storage`, if there is one.
+    /// 
+    /// # Errors
+    /// If the stored household was created on a different `network` return a `StorageError`.
+    /// If the storage can not be read return a `StorageError`.
+    fn load_ledger_for_network(storage: &HouseholdStorage, network: Network)-> Result<Option<HouseholdLedger>, StorageError> {
+        let stored_ledger = storage.load_ledger()?;
+        if let Some(ledger) = &stored_ledger {
+            if ledger.network != network {
+                return Err(StorageError::NetworkMismatch)
+            }
+        }
+        Ok(stored_ledger)
+    }
+
+    /// Build the household around `master_account` from the `stored_ledger`, or as a new household if nothing has been saved yet.
+    /// 
+    /// # Errors
+    /// If a sub-account's wallet can not be opened return a `StorageError`.
+    fn load_with_master_account(children: &mut Children, stored_ledger: Option<HouseholdLedger>, master_account: MasterAccount)-> Result<HeadOfTheHouse, StorageError> {
+        let mut head_of_house = HeadOfTheHouse {
+            accounts: vec![],
+            user_id: 0,
+            master_account,
+            allowance_scheduler: AllowanceScheduler::default(),
+            spend_proposals: SpendProposals::default(),
+            transfer_requests: TransferRequests::default(),
+        };
+
+        match stored_ledger {
+            Some(ledger) => head_of_house.restore_ledger(children, ledger),
+            None => head_of_house.create_new_user(children, 1, String::from("main"), main_account_permissions()),
+        }
+        head_of_house.open_sub_accounts()?;
+        Ok(head_of_house)
+    }
+
+    /// Save the household's `HouseholdLedger` to `storage`.
+    /// 
+    /// The wallet itself is written to the storage's wallet database as it changes, so only the ledger needs saving.
+    /// 
+    /// # Errors
+    /// If the ledger can not be written return a `StorageError`.
+    pub fn save(&self, storage: &HouseholdStorage, children: &Children)-> Result<(), StorageError> {
+        storage.save_ledger(&self.to_ledger(children))
+    }
+
+    /// Build a `HouseholdLedger` from the current state of the household.
+    pub fn to_ledger(&self, children: &Children)-> HouseholdLedger {
+        HouseholdLedger {
+            accounts: self.accounts.clone(),
+            children: children.children.clone(),
+            all_addresses: self.master_account.all_addresses.iter().map(|address| address.into()).collect(),
+            account_addresses: self.master_account.account_addresses.iter().map(|address| address.into()).collect(),
+            pending_transactions: self.master_account.pending_transactions.clone(),
+            amount_transfered_to_children: self.master_account.amount_transfered_to_children,
+            network: self.master_account.network,
+            allowance_scheduler: self.allowance_scheduler.clone(),
+            spend_proposals: self.spend_proposals.clone(),
+            transfer_requests: self.transfer_requests.clone(),
+            watch_only_keys: Some(self.master_account.watch_only_keys().clone()),
+        }
+    }
+
+    /// Encrypt the household's seed with `password`, so the household can be loaded locked with `load_watch_only`
+    /// and unlocked with the password only when it needs to sign.
+    /// 
+    /// # Errors
+    /// If the `mnemonic_words` and `bip39_passphrase` are not the ones the household's wallet was generated from return a `WalletError`.
+    /// If the seed can not be encrypted return a `WalletError`.
+    pub fn encrypt_seed(&self, mnemonic_words: &str, bip39_passphrase: Option<&str>, password: &str)-> Result<EncryptedSeed, WalletError> {
+        if !self.master_account.is_generated_from(mnemonic_words, bip39_passphrase) {
+            return Err(WalletError::KeyError)
+        }
+        let seed_secrets = SeedSecrets {
+            mnemonic_words: Redacted::new(String::from(mnemonic_words)),
+            bip39_passphrase: bip39_passphrase.map(|bip39_passphrase| Redacted::new(String::from(bip39_passphrase))),
+        };
+        EncryptedSeed::encrypt(&seed_secrets, password, self.master_account.watch_only_keys().fingerprint)
+    }
 
+    /// Unlock the `MasterAccount` with the `password` the seed saved in `storage` was encrypted with, then open the sub-accounts
+    /// which could not be opened while it was locked.
+    /// 
+    /// # Errors
+    /// If the seed has not been encrypted with a password return a `WalletError`.
+    /// If the password is wrong return a `WalletError`.
+    pub fn unlock(&mut self, storage: &HouseholdStorage, password: &str)-> Result<(), WalletError> {
+        let encrypted_seed = storage.load_encrypted_seed().map_err(|_e| WalletError::DatabaseError)?.ok_or(WalletError::NoEncryptedSeed)?;
+        self.master_account.unlock(&encrypted_seed, password)?;
+        self.open_sub_accounts()
+    }
+
+    /// Export the household as a backup encrypted with `passphrase`.
+    /// 
+    /// The backup holds the `mnemonic_words`, the `bip39_passphrase` and the household's `HouseholdLedger`, so it can rebuild the
+    /// household with every child, permission, address assignment and transfer.
+    /// 
+    /// # Errors
+    /// If the `mnemonic_words` and `bip39_passphrase` are not the ones the household's wallet was generated from return a `BackupError`.
+    /// If the backup can not be encrypted return a `BackupError`.
+    pub fn export_backup(&self, children: &Children, mnemonic_words: &str, bip39_passphrase: Option<&str>, passphrase: &str)-> Result<EncryptedBackup, BackupError> {
+        if !self.master_account.is_generated_from(mnemonic_words, bip39_passphrase) {
+            return Err(BackupError::MnemonicMismatch)
+        }
+        let backup = HouseholdBackup {
+            mnemonic_words: Redacted::new(String::from(mnemonic_words)),
+            bip39_passphrase: bip39_passphrase.map(|bip39_passphrase| Redacted::new(String::from(bip39_passphrase))),
+            ledger: self.to_ledger(children),
         };
-        head_of_house.create_new_user(&mut children, 1, String::from("main"), vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+        EncryptedBackup::encrypt(&backup, passphrase)
+    }
+
+    /// Import the household in an encrypted backup into `storage`, which must not already hold a household, and save it.
+    /// 
+    /// # Errors
+    /// If the backup can not be decrypted with `passphrase`, or fails its integrity check, return a `BackupError`.
+    /// If the backup was made on a different `network` return a `BackupError`.
+    /// If `storage` already holds a household return a `BackupError`, and leave the household as it is.
+    /// If the household can not be saved to `storage` return a `BackupError`.
+    pub fn import_backup(storage: &HouseholdStorage, children: &mut Children, encrypted_backup: &EncryptedBackup, passphrase: &str, network: Network)-> Result<HeadOfTheHouse, BackupError> {
+        if HeadOfTheHouse::load_ledger_for_network(storage, network)?.is_some() {
+            return Err(BackupError::HouseholdAlreadyExists)
+        }
+        let backup = encrypted_backup.decrypt(passphrase, network)?;
+        let bip39_passphrase = backup.bip39_passphrase.map(|bip39_passphrase| bip39_passphrase.expose().clone());
+        let master_account = HeadOfTheHouse::generate_master_account(Some(backup.mnemonic_words.expose().clone()), bip39_passphrase, Some(&backup.ledger), network, storage)?;
+        let mut head_of_house = HeadOfTheHouse::load_with_master_account(children, None, master_account)?;
+        head_of_house.restore_ledger(children, backup.ledger);
+        head_of_house.open_sub_accounts().map_err(StorageError::from)?;
+        head_of_house.save(storage, children)?;
         Ok(head_of_house)
     }
 
+    /// Replace the household's accounts, children, transfer accounting, allowances, spend proposals and transfer requests with those in the `ledger`.
+    pub fn restore_ledger(&mut self, children: &mut Children, ledger: HouseholdLedger){
+        self.accounts = ledger.accounts;
+        children.children = ledger.children;
+        self.master_account.all_addresses = ledger.all_addresses.into_iter().map(|address| address.into()).collect();
+        self.master_account.account_addresses = ledger.account_addresses.into_iter().map(|address| address.into()).collect();
+        self.master_account.pending_transactions = ledger.pending_transactions;
+        self.master_account.amount_transfered_to_children = ledger.amount_transfered_to_children;
+        self.allowance_scheduler = ledger.allowance_scheduler;
+        self.spend_proposals = ledger.spend_proposals;
+        self.transfer_requests = ledger.transfer_requests;
+    }
+
+    /// Open the sub-account wallet of every `Account` which has one, so their utxos are synced and counted.
+    /// 
+    /// While the `MasterAccount` is locked a sub-account which has never been opened is skipped, its key can not be derived
+    /// until the `MasterAccount` is unlocked.
+    /// 
+    /// # Errors
+    /// If a sub-account's wallet can not be opened return a `WalletError`.
+    pub fn open_sub_accounts(&mut self)-> Result<(), WalletError> {
+        let account_indexes: Vec<u32> = self.accounts.iter().filter_map(|account| account.account_index).collect();
+        for account_index in account_indexes {
+            match self.master_account.open_sub_account(account_index) {
+                Ok(_wallet) => (),
+                Err(WalletError::WalletLocked) => warn!(account_index, "skipping a sub-account which has not been opened while the wallet was unlocked"),
+                Err(WalletError::ExternalSigner) => warn!(account_index, "skipping a sub-account whose key an external signer or cosigner has not given"),
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuild the household's children from the blockchain, after the household was restored from its mnemonic words.
+    /// 
+    /// Every child in `child_metadata` which does not exist yet is added. The wallets are then synced with the electrum server,
+    /// looking `stop_gap` unused addresses past the last used one, and the sub-accounts after the highest child id are scanned
+    /// in order. Each one which has been used is added as a child with the `discovered_child_permissions`, stopping at the first
+    /// unused one as BIP44 account discovery does.
+    /// Each child's used addresses, change addresses, pending transactions and balance are rebuilt from its sub-account, and the
+    /// `MasterAccount`'s pending transactions from every wallet. Transfers from the `MasterAccount` are not on the blockchain,
+    /// so they are not restored. Children without a sub-account share the `MasterAccount`'s wallet and are left as they are.
+    /// 
+    /// # Errors
+    /// If the wallets can not be synced with the electrum server return a `WalletError`.
+    /// If a sub-account's wallet can not be opened or read return a `WalletError`.
+    pub fn restore_accounts(&mut self, children: &mut Children, child_metadata: &[ChildMetadata], electrum_url: Option<&str>, stop_gap: usize)-> Result<Vec<RestoredAccount>, WalletError> {
+        self.add_restored_children(children, child_metadata);
+        self.open_sub_accounts()?;
+        self.master_account.sync_wallet_with_electrum_server_and_stop_gap(electrum_url, stop_gap)?;
+
+        let mut discovered_account_ids = vec![];
+        let mut account_id = self.accounts.iter().map(|account| account.account_id).max().unwrap_or(1).max(1) + 1;
+        while let Some(account_index) = sub_account_index(account_id) {
+            if !self.master_account.discover_sub_account(account_index)? {
+                break;
+            }
+            self.create_new_user(children, account_id, format!("child_{}", account_id), discovered_child_permissions());
+            discovered_account_ids.push(account_id);
+            account_id += 1;
+        }
+
+        let mut restored_accounts = vec![];
+        let sub_account_ids: Vec<i32> = self.accounts.iter().filter(|account| account.account_index.is_some()).map(|account| account.account_id).collect();
+        for account_id in sub_account_ids {
+            let mut restored_account = self.rebuild_account_from_sub_account(account_id)?;
+            restored_account.discovered = discovered_account_ids.contains(&account_id);
+            restored_accounts.push(restored_account);
+        }
+        self.master_account.rescan_pending_transactions()?;
+        Ok(restored_accounts)
+    }
+
+    /// Add a `Child` and `Account` for every child in `child_metadata` which does not exist yet.
+    pub fn add_restored_children(&mut self, children: &mut Children, child_metadata: &[ChildMetadata]){
+        for child in child_metadata {
+            if self.get_account_by_id(child.account_id).is_none() {
+                self.create_new_user(children, child.account_id, child.account_name.clone(), child.permissions.clone());
+            }
+        }
+    }
+
+    /// Add the addresses, change addresses and pending transactions found in an `Account`'s sub-account to the `Account`, and update its balance.
+    /// 
+    /// # Errors
+    /// If the account does not exist or does not have a sub-account return a `WalletError`.
+    /// If the sub-account's wallet can not be read return a `WalletError`.
+    fn rebuild_account_from_sub_account(&mut self, user_id: i32)-> Result<RestoredAccount, WalletError> {
+        let account_index = self.get_account_by_id(user_id).and_then(|account| account.account_index).ok_or(WalletError::AddressError)?;
+        let used_addresses = self.master_account.list_used_addresses(Some(account_index))?;
+        let wallet = self.master_account.sub_account_wallets.get(&account_index).ok_or(WalletError::AddressError)?;
+        let pending_transactions: Vec<TransactionDetails> = wallet.list_transactions(false)?.into_iter()
+            .filter(|transaction_details| transaction_details.confirmation_time.is_none() && transaction_details.sent > 0)
+            .collect();
+
+        for used_address in &used_addresses {
+            let is_known = self.master_account.all_addresses.iter().any(|address_info| address_info.address == used_address.address);
+            if used_address.keychain == KeychainKind::External && !is_known {
+                self.master_account.all_addresses.push(AddressInfo { index: used_address.index, address: used_address.address.clone(), keychain: used_address.keychain });
+            }
+        }
+        let account = self.get_mut_account_by_id(user_id).ok_or(WalletError::AddressError)?;
+        for used_address in used_addresses {
+            let is_known = account.addresses.contains(&used_address.address) || account.change_addresses.contains(&used_address.address);
+            match used_address.keychain {
+                _ if is_known => {},
+                KeychainKind::External => account.add_address(used_address.address),
+                KeychainKind::Internal => account.add_change_address(used_address.address),
+            }
+        }
+        for pending_transaction in pending_transactions {
+            if !account.pending_transactions.iter().any(|transaction_details| transaction_details.txid == pending_transaction.txid) {
+                account.add_pending_transaction(pending_transaction);
+            }
+        }
+
+        let bitcoin_amount = self.get_and_update_account_balance(user_id)?;
+        let account = self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?;
+        Ok(RestoredAccount {
+            account_id: user_id,
+            discovered: false,
+            addresses: account.addresses.len(),
+            change_addresses: account.change_addresses.len(),
+            pending_transactions: account.pending_transactions.len(),
+            bitcoin_amount,
+        })
+    }
+
     /// Create a new `Child` and `Account` with the same id.
     pub fn create_new_user(&mut self, children:&mut Children,  account_id: i32,account_name: String, permissions: Vec<BitcoinPermissions> ){
         children.add_child(account_id, account_name);
@@ -58,6 +458,17 @@ impl  HeadOfTheHouse {
             addresses:  vec![],
             pending_transactions: vec![],
             bitcoin_transfered_from_master: 0,
+            spending_limits: SpendingLimits::default(),
+            spend_records: vec![],
+            approval_threshold: None,
+            address_book: AddressBook::default(),
+            change_addresses: vec![],
+            max_fee_rate: None,
+            // a child whose sub-account key the external signer has not given receives on the master account's addresses instead
+            account_index: sub_account_index(account_id).filter(|account_index| self.master_account.can_open_sub_account(*account_index)),
+            pending_cancellations: vec![],
+            pending_on_chain_transfers: vec![],
+            on_chain_transfers: vec![],
         };
 
         self.accounts.push(new_account)
@@ -85,59 +496,881 @@ impl  HeadOfTheHouse {
     
     /// A function which spends bitcoin from the `MasterAccount`, and updates the associated `Account`.
     /// 
-    /// Right now the transaction will be made with 1 sat per vbtye.
-    /// TODO, make sat per vbyte variable.
+    /// The transaction is made with the default fee rate, see `spend_bitcoin_with_fee` to choose one.
+    pub fn spend_bitcoin(&mut self, user_id: i32, amount: u64, address: &str)-> Result<&'static str, AccountError> {
+        self.spend_bitcoin_with_fee(user_id, amount, address, FeeSelection::default())
+    }
+
+    /// A function which spends bitcoin from the `MasterAccount` with the fee rate chosen by `fee_selection`, and updates the associated `Account`.
+    /// 
     /// When the transaction is made, the pending_transaction will be added to the `Account`'s pending_transactions.
+    /// If the amount is above the `Account`'s approval threshold the transaction is built but not broadcast, it is stored
+    /// as a `SpendProposal` for the head of the house to approve or reject and "AWAITING_APPROVAL" is returned.
+    /// If the `MasterAccount`'s keys are held by an external signer the transaction is stored as a `SpendProposal` too, holding the
+    /// unsigned PSBT for the external signer to sign, and "AWAITING_SIGNATURE" is returned.
+    /// A multisig vault signs the transaction with the seed's key, and if its cosigners' signatures are still needed it is stored
+    /// as a `SpendProposal` holding the partially signed PSBT, and "AWAITING_SIGNATURE" is returned.
     /// 
     /// # Errors 
+    /// If the address is invalid or belongs to a different network than the `MasterAccount` wallet it will return an `AccountError`.
+    /// If the `Account` does not have permission to send, or to send to this address, it will return an `AccountError`.
+    /// If the spend would exceed one of the `Account`'s spending limits it will return an `AccountError` with the `Account`'s spend tally.
+    /// If the fee rate can not be estimated, is below the minimum relay fee rate or is above the `Account`'s maximum fee rate it will return an `AccountError`.
     /// If the `Account` or the `MasterAccount` does not have sufficient funds then it will return an `AccountError`.
     /// If the wallet can not make the transaction and send it to the bitcoin network it will return an `AccountError`.
     /// If the user_id is not associated with any active Account then return an `AccountError`.
-    pub fn spend_bitcoin(&mut self, user_id: i32, amount: u64, address: &str)-> Result<&'static str, AccountError> {
-        let sufficient_funds = self.does_user_have_sufficient_funds_to_spend(user_id, amount).or_else(|_e| Err(AccountError::InsufficientAccount))?;
-        if self.does_user_have_permission_to_spend(user_id) && sufficient_funds {
-            let user_btc_utxo_non_transfer_amount = match self.get_account_balance_without_transfered_amount(user_id) {    
-                Err(_wallet_error) => {
-                    return Err(AccountError::Default(""))
-                },
-                Ok(result) => result,
-            };
+    #[instrument(target = "audit", name = "spend", skip(self, fee_selection), ret, err(level = "warn"))]
+    pub fn spend_bitcoin_with_fee(&mut self, user_id: i32, amount: u64, address: &str, fee_selection: FeeSelection)-> Result<&'static str, AccountError> {
+        let now = get_current_timestamp();
+        let (psbt, tx_details, change_address) = self.build_checked_spend_transaction(user_id, amount, address, fee_selection, now)?;
+        let user_btc_utxo_input_amount = self.get_account_input_amount(user_id, &psbt);
+
+        if self.does_spend_require_approval(user_id, amount) {
+            let proposal_id = self.spend_proposals.add(user_id, amount, address, now, psbt, tx_details);
+            self.spend_proposals.get_mut(proposal_id)?.change_address = change_address;
+            return Ok("AWAITING_APPROVAL")
+        }
+        // the external signer signs the PSBT of the held spend, its coins stay reserved in the meantime
+        if self.master_account.signs_externally() {
+            let proposal_id = self.spend_proposals.add(user_id, amount, address, now, psbt, tx_details);
+            self.spend_proposals.get_mut(proposal_id)?.change_address = change_address;
+            return Ok("AWAITING_SIGNATURE")
+        }
 
-            let spend_bitcoin_result = self.master_account.spend_bitcoin(amount, address, 1.0);
-            match spend_bitcoin_result {
-                Err(_) =>  return Err(AccountError::InsufficientAccount),
-                Ok(spend_bitcoin_result) => if spend_bitcoin_result.confirmation_time == None {
-                    // remove any transfer amount used for user and master
-                    // if the user amount we are spending is more than the bitcoin non transfer amount
-                    // then remove the difference
-                    let is_user_not_master_account = user_id !=1;
-
-                    let is_spent_btc_more_than_child_utxo_btc = amount > user_btc_utxo_non_transfer_amount;
-
-                    if is_user_not_master_account && is_spent_btc_more_than_child_utxo_btc  {
-                        let user_account = self.get_mut_account_by_id(user_id).unwrap(); //we can use unwrap since the spend result was successful
-                        // remove from transfer amount whatever we can't cover in our utxo value amount 
-                        let total_spent = amount + spend_bitcoin_result.fee.unwrap(); //we can use unwrap because we know spend was successful
-                        let amount_needed_to_cover_from_transfered_amount =  total_spent - user_btc_utxo_non_transfer_amount;  // difference between amount spent and utxo amount
-                        user_account.send_transfered_bitcoin(amount_needed_to_cover_from_transfered_amount);
-                        self.master_account.amount_transfered_to_children -= amount_needed_to_cover_from_transfered_amount;
-                    }
-                    // update the users account
-                    // if transaction still pending add it to the pending list
-                    self.add_pending_transaction_to_user_account(user_id, spend_bitcoin_result)?;
-                    Ok("PENDING")
-                } else {
-                    Ok("Success")
-                }
+        let mut psbt = psbt;
+        self.master_account.sign_transaction(&mut psbt).map_err(|error| match error {
+            WalletError::WalletLocked => AccountError::WalletLocked,
+            WalletError::ExternalSigner => AccountError::ExternalSigner,
+            _ => AccountError::InsufficientAccount,
+        })?;
+        // a multisig vault's spend is held, with the seed's signature, until its cosigners have signed too
+        if self.master_account.multisig_policy().is_some() && !is_finalized(&psbt) {
+            let proposal_id = self.spend_proposals.add(user_id, amount, address, now, psbt, tx_details);
+            self.spend_proposals.get_mut(proposal_id)?.change_address = change_address;
+            return Ok("AWAITING_SIGNATURE")
+        }
+        let spend_bitcoin_result = self.master_account.broadcast_transaction(psbt, tx_details).map_err(|_e| AccountError::InsufficientAccount)?;
+        self.record_account_spend(user_id, amount, now, user_btc_utxo_input_amount, change_address, spend_bitcoin_result)
+    }
+
+    /// Build an unsigned PSBT for an `Account`'s spend, to be signed on another device, and hold it as a `SpendProposal`
+    /// whose id is returned.
+    /// 
+    /// The spend is checked as `spend_bitcoin_with_fee` checks it and its coins are reserved until the proposal is broadcast or rejected.
+    /// The PSBT is exported from the proposal, signed elsewhere, imported back with `import_signed_psbt` and then broadcast
+    /// with `broadcast_spend_proposal`, which updates the `Account` as if it had been spent with `spend_bitcoin_with_fee`.
+    /// 
+    /// # Errors
+    /// If `spend_bitcoin_with_fee` would refuse the spend, for the same reasons, return an `AccountError`.
+    #[instrument(target = "audit", name = "build_unsigned_spend", skip(self, fee_selection), ret, err(level = "warn"))]
+    pub fn build_unsigned_spend(&mut self, user_id: i32, amount: u64, address: &str, fee_selection: FeeSelection)-> Result<u32, AccountError> {
+        let now = get_current_timestamp();
+        let (psbt, tx_details, change_address) = self.build_checked_spend_transaction(user_id, amount, address, fee_selection, now)?;
+        let proposal_id = self.spend_proposals.add(user_id, amount, address, now, psbt, tx_details);
+        self.spend_proposals.get_mut(proposal_id)?.change_address = change_address;
+        Ok(proposal_id)
+    }
+
+    /// Pay several `payouts`, to children or to outside addresses, in one transaction with the fee rate chosen by `fee_selection`,
+    /// sharing its fee between the `Account`s paying out under the `fee_policy`, and return the transaction.
+    /// 
+    /// Each payout is checked as `spend_bitcoin_with_fee` checks a spend, against the `Account` paying it. The transaction is paid
+    /// with the `MasterAccount`'s coins and its change goes back to the `MasterAccount`, so what each child pays, its payouts and its
+    /// share of the fee, is taken from the bitcoin it was transfered from the `MasterAccount`. Each `Account` paying in the batch
+    /// gets the transaction in its pending_transactions, with what it paid as the amount sent and its share of the fee as the fee.
+    /// 
+    /// # Errors
+    /// If there are no payouts return an `AccountError`.
+    /// If an address is invalid, an `Account` may not send to it or the payouts would exceed one of its spending limits return an `AccountError`.
+    /// If an `Account`'s payouts are above its approval threshold return an `AccountError`, such spends are made one at a time.
+    /// If the fee rate can not be estimated, is below the minimum relay fee rate or is above an `Account`'s maximum fee rate return an `AccountError`.
+    /// If a child's transfered bitcoin, or the `MasterAccount`'s coins, do not cover what it pays return an `AccountError`.
+    /// If the transaction can not be signed and broadcast, for example because its keys are held by an external signer, return an `AccountError`.
+    /// If a payout's user_id is not associated with any active Account then return an `AccountError`.
+    #[instrument(target = "audit", name = "spend_batch", skip(self, fee_selection), ret, err(level = "warn"))]
+    pub fn spend_bitcoin_batch(&mut self, payouts: &[BatchPayout], fee_policy: BatchFeePolicy, fee_selection: FeeSelection)-> Result<TransactionDetails, AccountError> {
+        if payouts.is_empty() {
+            return Err(AccountError::EmptyBatch)
+        }
+        let now = get_current_timestamp();
+        let fee_rate = self.master_account.get_fee_rate(&fee_selection).map_err(|_e| AccountError::Default("Unable to estimate the fee rate"))?;
+        for payout in payouts {
+            let receiving_address = self.master_account.validate_address(&payout.address).map_err(|_e| AccountError::InvalidAddress)?;
+            self.check_send_permission(payout.user_id, &receiving_address)?;
+        }
+        for share in account_totals(payouts) {
+            self.check_spending_limits(share.user_id, share.amount, now)?;
+            self.check_fee_rate(share.user_id, fee_rate)?;
+            if self.does_spend_require_approval(share.user_id, share.amount) {
+                return Err(AccountError::ApprovalRequired)
             }
-        }else{
+        }
+
+        // only the master account's coins pay for the batch, the children's coins are left for their own spends
+        let unspendable = self.get_account_coins(1).map_err(|_e| AccountError::Default("Unable to list the wallet's coins"))?.unspendable;
+        let recipients: Vec<(String, u64)> = payouts.iter().map(|payout| (payout.address.clone(), payout.amount)).collect();
+        let (psbt, tx_details) = self.master_account.build_batch_spend_transaction(&recipients, fee_rate, vec![], unspendable, None)
+            .map_err(|_e| AccountError::InsufficientAccount)?;
+        let shares = fee_policy.split(payouts, tx_details.fee.unwrap_or(0));
+        for share in shares.iter().filter(|share| share.user_id != 1) {
+            let account = self.get_account_by_id(share.user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+            if share.total() > account.bitcoin_transfered_from_master {
+                return Err(AccountError::InsufficientAccount)
+            }
+        }
+
+        let batch_transaction = self.master_account.sign_and_broadcast_transaction(psbt, tx_details).map_err(|error| match error {
+            WalletError::WalletLocked => AccountError::WalletLocked,
+            WalletError::ExternalSigner => AccountError::ExternalSigner,
+            WalletError::UnsignedTransaction => AccountError::UnsignedTransaction,
+            _ => AccountError::InsufficientAccount,
+        })?;
+        for share in &shares {
+            let account = self.get_mut_account_by_id(share.user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+            if share.amount > 0 {
+                account.record_spend(share.amount, now);
+            }
+            if share.user_id != 1 {
+                account.send_transfered_bitcoin(share.total());
+                self.master_account.amount_transfered_to_children -= share.total();
+            }
+            if batch_transaction.confirmation_time.is_none() {
+                let account_transaction = TransactionDetails { sent: share.amount, received: 0, fee: Some(share.fee), ..batch_transaction.clone() };
+                self.add_pending_transaction_to_user_account(share.user_id, account_transaction)?;
+            }
+        }
+        info!(txid = %batch_transaction.txid, payouts = payouts.len(), accounts = shares.len(), fee = ?batch_transaction.fee, %fee_policy, "broadcast batched spend");
+        Ok(batch_transaction)
+    }
+
+    /// Replace an `Account`'s pending spend `txid` with one paying the higher fee rate chosen by `fee_selection`, and return the replacement.
+    /// 
+    /// The replacement makes the same payment from the same inputs and is swapped for the original in the `MasterAccount`'s
+    /// and the `Account`'s pending_transactions. The extra fee is charged to the `Account` which made the spend, it comes out of
+    /// the child's change, or more of the child's own utxos, and whatever part of it the `MasterAccount`'s coins pay is taken
+    /// from the bitcoin the child was transfered from the `MasterAccount`.
+    /// 
+    /// # Errors
+    /// If the transaction is not pending on any `Account`, or is a batched spend of several `Account`s, return an `AccountError`.
+    /// If the fee rate can not be estimated, is below the minimum relay fee rate or is above the `Account`'s maximum fee rate return an `AccountError`.
+    /// If the `Account` can not pay the extra fee, or the replacement can not be built, signed or broadcast, return an `AccountError`.
+    #[instrument(target = "audit", name = "bump_fee", skip(self, fee_selection), ret, err(level = "warn"))]
+    pub fn bump_fee(&mut self, txid: Txid, fee_selection: FeeSelection)-> Result<TransactionDetails, AccountError> {
+        let user_id = self.get_pending_transaction_account_id(txid)?;
+        let fee_rate = self.master_account.get_fee_rate(&fee_selection).map_err(|_e| AccountError::Default("Unable to estimate the fee rate"))?;
+        self.check_fee_rate(user_id, fee_rate)?;
+
+        let account_coins = self.get_account_coins(user_id).map_err(|_e| AccountError::Default("Unable to list the wallet's coins"))?;
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        let bitcoin_transfered_from_master = account.bitcoin_transfered_from_master;
+        // a child only adds the master account's coins to pay the extra fee if it has bitcoin transfered from the master account
+        let unspendable = if user_id != 1 && bitcoin_transfered_from_master == 0 {
+            [account_coins.unspendable, account_coins.transfered, account_coins.master].concat()
+        } else {
+            account_coins.unspendable
+        };
+        let shrinkable = account.change_addresses.iter().map(|change_address| change_address.script_pubkey()).collect();
+        let (psbt, tx_details) = self.master_account.build_fee_bump_transaction(txid, fee_rate, unspendable, shrinkable).map_err(|error| match error {
+            WalletError::TransactionNotPending => AccountError::TransactionNotPending,
+            _ => AccountError::InsufficientAccount,
+        })?;
+
+        let original = self.master_account.get_transaction(&txid, true).ok().flatten().ok_or(AccountError::TransactionNotPending)?;
+        let original_transaction = original.transaction.as_ref().ok_or(AccountError::TransactionNotPending)?;
+        let extra_fee = tx_details.fee.unwrap_or(0).saturating_sub(original.fee.unwrap_or(0));
+        let extra_account_spend = self.get_account_net_spend(user_id, &psbt, &psbt.unsigned_tx).saturating_sub(self.get_account_net_spend(user_id, &psbt, original_transaction));
+        let extra_fee_from_transfered_bitcoin = if user_id == 1 { 0 } else { extra_fee.saturating_sub(extra_account_spend) };
+        if extra_fee_from_transfered_bitcoin > bitcoin_transfered_from_master {
             return Err(AccountError::InsufficientAccount)
         }
+
+        let replacement = self.master_account.sign_and_broadcast_replacement(txid, psbt, tx_details).map_err(|error| match error {
+            WalletError::WalletLocked => AccountError::WalletLocked,
+            WalletError::ExternalSigner => AccountError::ExternalSigner,
+            WalletError::UnsignedTransaction => AccountError::UnsignedTransaction,
+            _ => AccountError::Default("The replacement transaction could not be broadcast"),
+        })?;
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.replace_pending_transaction(txid, replacement.clone());
+        if extra_fee_from_transfered_bitcoin > 0 {
+            account.send_transfered_bitcoin(extra_fee_from_transfered_bitcoin);
+            self.master_account.amount_transfered_to_children -= extra_fee_from_transfered_bitcoin;
+        }
+        info!(user_id, replaced_txid = %txid, txid = %replacement.txid, extra_fee, extra_fee_from_transfered_bitcoin, "bumped the fee of a pending spend");
+        Ok(replacement)
+    }
+
+    /// Cancel an `Account`'s pending spend `txid`, replacing it with a transaction which pays its coins back to the household at the
+    /// higher fee rate chosen by `fee_selection`, and return the replacement.
+    /// 
+    /// The child's own coins the spend used go back to a new change address of the child, less the fee, and the `MasterAccount`'s
+    /// coins go back to the `MasterAccount`. If the child's own coins do not cover the fee everything goes back to the `MasterAccount`,
+    /// and the child pays the fee out of the bitcoin transfered to it. The replacement is swapped for the original in the `MasterAccount`'s
+    /// and the `Account`'s pending_transactions, and the bitcoin transfered from the `MasterAccount` which the spend used is given back
+    /// to the child once the replacement is confirmed, see `get_account_pending_transactions`.
+    /// 
+    /// # Errors
+    /// If the transaction is not pending on any `Account`, or is a batched spend of several `Account`s, return an `AccountError`.
+    /// If the fee rate can not be estimated, is below the minimum relay fee rate or is above the `Account`'s maximum fee rate return an `AccountError`.
+    /// If the spend's coins do not cover the fee, or the replacement can not be built, signed or broadcast, return an `AccountError`, and
+    /// the original stays pending with no cancellation recorded.
+    #[instrument(target = "audit", name = "cancel_transaction", skip(self, fee_selection), ret, err(level = "warn"))]
+    pub fn cancel_transaction(&mut self, txid: Txid, fee_selection: FeeSelection)-> Result<TransactionDetails, AccountError> {
+        let user_id = self.get_pending_transaction_account_id(txid)?;
+        let fee_rate = self.master_account.get_fee_rate(&fee_selection).map_err(|_e| AccountError::Default("Unable to estimate the fee rate"))?;
+        self.check_fee_rate(user_id, fee_rate)?;
+
+        let original = self.master_account.get_transaction(&txid, true).ok().flatten().ok_or(AccountError::TransactionNotPending)?;
+        let original_transaction = original.transaction.ok_or(AccountError::TransactionNotPending)?;
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        let account_index = account.account_index;
+        let account_script_pub_keys = account.get_addresses_as_script_pub_keys();
+        let on_chain_transfer_outpoints = account.get_on_chain_transfer_outpoints();
+
+        // split what the spend's inputs were worth between the child's own coins and the master account's,
+        // the coins on chain transfers sent the child count as the master account's
+        let mut account_input_amount = 0;
+        let mut master_input_amount = 0;
+        for input in &original_transaction.input {
+            let previous_output = self.master_account.get_previous_output(input.previous_output)
+                .map_err(|_e| AccountError::Default("Unable to list the wallet's coins"))?
+                .ok_or(AccountError::TransactionNotPending)?;
+            if account_script_pub_keys.contains(&previous_output.script_pubkey) && !on_chain_transfer_outpoints.contains(&input.previous_output) {
+                account_input_amount += previous_output.value;
+            } else {
+                master_input_amount += previous_output.value;
+            }
+        }
+        // the spend paid its payment and fee out of the household, whatever the child's own coins did not cover came from its transfered bitcoin
+        let mut household_output_amount = 0;
+        let mut account_output_amount = 0;
+        for output in &original_transaction.output {
+            if self.master_account.is_mine(&output.script_pubkey).map_err(|_e| AccountError::Default("Unable to list the wallet's coins"))? {
+                household_output_amount += output.value;
+            }
+            if account_script_pub_keys.contains(&output.script_pubkey) {
+                account_output_amount += output.value;
+            }
+        }
+        let amount_spent = (account_input_amount + master_input_amount).saturating_sub(household_output_amount);
+        let transfered_bitcoin_spent = if user_id == 1 { 0 } else { amount_spent.saturating_sub(account_input_amount.saturating_sub(account_output_amount)) };
+
+        let master_refund_script = self.master_account.generate_new_change_address().map_err(|_e| AccountError::Default("Unable to generate a change address"))?.script_pubkey();
+        let mut cancel_to_account = None;
+        if user_id != 1 && account_input_amount > 0 {
+            let change_address = match account_index {
+                Some(account_index) => self.master_account.generate_new_sub_account_change_address(account_index),
+                None => self.master_account.generate_new_address(),
+            }.map_err(|_e| AccountError::Default("Unable to generate a change address"))?;
+            let refunds = if master_input_amount > 0 { vec![(master_refund_script.clone(), master_input_amount)] } else { vec![] };
+            match self.master_account.build_cancel_transaction(txid, fee_rate, &refunds, change_address.script_pubkey()) {
+                Ok((psbt, tx_details)) => cancel_to_account = Some((change_address, psbt, tx_details)),
+                Err(WalletError::TransactionNotPending) => return Err(AccountError::TransactionNotPending),
+                Err(_error) => {},
+            }
+        }
+        let (psbt, tx_details, account_change_address, transfered_bitcoin_to_restore) = match cancel_to_account {
+            Some((change_address, psbt, tx_details)) => (psbt, tx_details, Some(change_address), transfered_bitcoin_spent),
+            None => {
+                // the child's own coins do not cover the fee, so they go back to the master account with everything else
+                let (psbt, tx_details) = self.master_account.build_cancel_transaction(txid, fee_rate, &[], master_refund_script).map_err(|error| match error {
+                    WalletError::TransactionNotPending => AccountError::TransactionNotPending,
+                    _ => AccountError::InsufficientAccount,
+                })?;
+                let fee = tx_details.fee.unwrap_or(0);
+                let transfered_bitcoin_to_restore = if user_id == 1 { 0 } else { (transfered_bitcoin_spent + account_input_amount).saturating_sub(fee) };
+                (psbt, tx_details, None, transfered_bitcoin_to_restore)
+            },
+        };
+
+        let replacement = self.master_account.sign_and_broadcast_replacement(txid, psbt, tx_details).map_err(|error| match error {
+            WalletError::WalletLocked => AccountError::WalletLocked,
+            WalletError::ExternalSigner => AccountError::ExternalSigner,
+            WalletError::UnsignedTransaction => AccountError::UnsignedTransaction,
+            _ => AccountError::Default("The replacement transaction could not be broadcast"),
+        })?;
+        // only a replacement the electrum server accepted is recorded, one it rejected could never settle the cancellation
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        if let Some(change_address) = account_change_address {
+            account.add_change_address(change_address);
+        }
+        account.replace_pending_transaction(txid, replacement.clone());
+        account.add_pending_cancellation(PendingCancellation { txid: replacement.txid, transfered_bitcoin_to_restore });
+        info!(user_id, replaced_txid = %txid, txid = %replacement.txid, fee = ?replacement.fee, transfered_bitcoin_to_restore, "cancelled a pending spend");
+        Ok(replacement)
+    }
+
+    /// Get the payments from outside the household an `Account` has received which are still waiting to be confirmed.
+    /// 
+    /// # Errors
+    /// If there is an issue listing the wallet's utxos return a `WalletError`.
+    /// If the account does not exist then return a `WalletError`.
+    pub fn get_unconfirmed_incoming_utxos(&self, user_id: i32)-> Result<Vec<LocalUtxo>, WalletError> {
+        let account_script_pub_keys = self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?.get_addresses_as_script_pub_keys();
+        let reserved_outpoints = self.spend_proposals.get_reserved_outpoints();
+        Ok(self.master_account.list_unconfirmed_incoming_utxos()?.into_iter()
+            .filter(|utxo| account_script_pub_keys.contains(&utxo.txout.script_pubkey) && !reserved_outpoints.contains(&utxo.outpoint))
+            .collect())
+    }
+
+    /// Speed up the confirmation of the payments an `Account` is waiting for, by spending them back to a new change address
+    /// of the `Account` with a fee which raises the fee rate of the payments and the spend together to the rate chosen by `fee_selection`.
+    /// 
+    /// The spend is tracked in the `MasterAccount`'s and the `Account`'s pending_transactions like any other spend.
+    /// Its fee is paid from the payments, so the `Account`'s balance drops by the fee.
+    /// 
+    /// # Errors
+    /// If the `Account` has no unconfirmed payments from outside the household return an `AccountError`.
+    /// If the fee rate can not be estimated, is below the minimum relay fee rate or is above the `Account`'s maximum fee rate return an `AccountError`.
+    /// If the payments do not cover the fee, or the spend can not be built, signed or broadcast, return an `AccountError`, and neither
+    /// the spend nor its change address is added to the `Account`.
+    /// If the account does not exist then return an `AccountError`.
+    #[instrument(target = "audit", name = "accelerate_incoming_payments", skip(self, fee_selection), ret, err(level = "warn"))]
+    pub fn accelerate_incoming_payments(&mut self, user_id: i32, fee_selection: FeeSelection)-> Result<TransactionDetails, AccountError> {
+        let account_index = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?.account_index;
+        let fee_rate = self.master_account.get_fee_rate(&fee_selection).map_err(|_e| AccountError::Default("Unable to estimate the fee rate"))?;
+        self.check_fee_rate(user_id, fee_rate)?;
+        let outpoints: Vec<OutPoint> = self.get_unconfirmed_incoming_utxos(user_id)
+            .map_err(|_e| AccountError::Default("Unable to list the wallet's coins"))?
+            .iter().map(|utxo| utxo.outpoint).collect();
+        if outpoints.is_empty() {
+            return Err(AccountError::NoUnconfirmedPayments)
+        }
+
+        let change_address = match account_index {
+            Some(account_index) => self.master_account.generate_new_sub_account_change_address(account_index),
+            None => self.master_account.generate_new_address(),
+        }.map_err(|_e| AccountError::Default("Unable to generate a change address"))?;
+        let (psbt, tx_details) = self.master_account.build_cpfp_transaction(account_index, &outpoints, &change_address.to_string(), fee_rate)
+            .map_err(|_e| AccountError::InsufficientAccount)?;
+
+        let cpfp_transaction = self.master_account.sign_and_broadcast_transaction(psbt, tx_details).map_err(|error| match error {
+            WalletError::WalletLocked => AccountError::WalletLocked,
+            WalletError::ExternalSigner => AccountError::ExternalSigner,
+            WalletError::UnsignedTransaction => AccountError::UnsignedTransaction,
+            _ => AccountError::Default("The child pays for parent transaction could not be broadcast"),
+        })?;
+        // the change address is only the account's once the transaction paying to it has been accepted
+        self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?.add_change_address(change_address);
+        self.add_pending_transaction_to_user_account(user_id, cpfp_transaction.clone())?;
+        info!(user_id, txid = %cpfp_transaction.txid, payments = outpoints.len(), fee = ?cpfp_transaction.fee, "accelerated incoming payments");
+        Ok(cpfp_transaction)
+    }
+
+    /// Check an `Account` may make a spend, then build its unsigned transaction, returning it with the `Account`'s change address it pays, if any.
+    /// 
+    /// # Errors
+    /// If the address is invalid, the `Account` does not have permission to send to it, the spend would exceed a spending limit,
+    /// the fee rate is not allowed, or the `Account` does not have sufficient funds return an `AccountError`.
+    fn build_checked_spend_transaction(&mut self, user_id: i32, amount: u64, address: &str, fee_selection: FeeSelection, now: u64)-> Result<(PartiallySignedTransaction, TransactionDetails, Option<Address>), AccountError> {
+        let receiving_address = self.master_account.validate_address(address).map_err(|_e| AccountError::InvalidAddress)?;
+        self.check_send_permission(user_id, &receiving_address)?;
+        self.check_spending_limits(user_id, amount, now)?;
+        let fee_rate = self.master_account.get_fee_rate(&fee_selection).map_err(|_e| AccountError::Default("Unable to estimate the fee rate"))?;
+        self.check_fee_rate(user_id, fee_rate)?;
+        let sufficient_funds = self.does_user_have_sufficient_funds_to_spend(user_id, amount).or_else(|_e| Err(AccountError::InsufficientAccount))?;
+        if !(self.does_user_have_permission_to_spend(user_id) && sufficient_funds) {
+            return Err(AccountError::InsufficientAccount)
+        }
+        self.build_account_spend_transaction(user_id, amount, address, fee_rate)
+    }
+
+    /// Build a transaction spending `amount` to `address` from the coins an `Account` may use.
+    /// 
+    /// A child's spend is built only from the utxos locked to its own addresses, other than the coins on chain transfers sent it,
+    /// and its change is sent to a new change address from the internal keychain, of the child's sub-account if it has one. The change address is returned with the transaction
+    /// so it can be added to the child's `Account` once the transaction is broadcast, the child's utxo balance then only drops
+    /// by the amount spent and the fee.
+    /// If the child's utxos do not cover the spend and the child has bitcoin transfered from the `MasterAccount`, every one of
+    /// the child's utxos, and of the coins confirmed on chain transfers sent it, is spent and the `MasterAccount`'s coins are added to cover the rest. The change then goes back to the
+    /// `MasterAccount`, since it came from the `MasterAccount`'s coins. The head of the house's `Account` spends the `MasterAccount`'s coins.
+    /// No spend uses another child's utxos, or coins already spent by a proposal waiting for approval.
+    /// 
+    /// # Errors
+    /// If the coins the `Account` may use do not cover the spend return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    fn build_account_spend_transaction(&mut self, user_id: i32, amount: u64, address: &str, fee_rate: FeeRate)-> Result<(PartiallySignedTransaction, TransactionDetails, Option<Address>), AccountError> {
+        let account_coins = self.get_account_coins(user_id).map_err(|_e| AccountError::Default("Unable to list the wallet's coins"))?;
+        if user_id == 1 {
+            let (psbt, tx_details) = self.master_account.build_spend_transaction(amount, address, fee_rate, vec![], account_coins.unspendable, None).map_err(|_e| AccountError::InsufficientAccount)?;
+            return Ok((psbt, tx_details, None))
+        }
+
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        let has_transfered_bitcoin = account.bitcoin_transfered_from_master > 0;
+        let (change_address, own_coins_result) = match account.account_index {
+            Some(account_index) => {
+                let change_address = self.master_account.generate_new_sub_account_change_address(account_index).map_err(|_e| AccountError::Default("Unable to generate a change address"))?;
+                let own_coins_only = [account_coins.unspendable.clone(), account_coins.transfered.clone()].concat();
+                let own_coins_result = self.master_account.build_sub_account_spend_transaction(account_index, amount, address, fee_rate, own_coins_only, Some(change_address.script_pubkey()));
+                (change_address, own_coins_result)
+            },
+            None => {
+                let change_address = self.master_account.generate_new_change_address().map_err(|_e| AccountError::Default("Unable to generate a change address"))?;
+                let own_coins_only = [account_coins.unspendable.clone(), account_coins.transfered.clone(), account_coins.master].concat();
+                let own_coins_result = self.master_account.build_spend_transaction(amount, address, fee_rate, vec![], own_coins_only, Some(change_address.script_pubkey()));
+                (change_address, own_coins_result)
+            },
+        };
+        match own_coins_result {
+            Ok((psbt, tx_details)) => {
+                let has_change = psbt.unsigned_tx.output.iter().any(|output| output.script_pubkey == change_address.script_pubkey());
+                let change_address = if has_change { Some(change_address) } else { None };
+                Ok((psbt, tx_details, change_address))
+            },
+            Err(_wallet_error) if has_transfered_bitcoin => {
+                let must_spend = [account_coins.own, account_coins.transfered].concat();
+                let (psbt, tx_details) = self.master_account.build_spend_transaction(amount, address, fee_rate, must_spend, account_coins.unspendable, None).map_err(|_e| AccountError::InsufficientAccount)?;
+                Ok((psbt, tx_details, None))
+            },
+            Err(_wallet_error) => Err(AccountError::InsufficientAccount),
+        }
+    }
+
+    /// Get the total value of the inputs of `psbt` which are locked to an `Account`'s addresses, other than the coins on chain transfers sent it.
+    fn get_account_input_amount(&self, user_id: i32, psbt: &PartiallySignedTransaction)-> u64 {
+        let (account_script_pub_keys, on_chain_transfer_outpoints) = match self.get_account_by_id(user_id) {
+            Some(account) => (account.get_addresses_as_script_pub_keys(), account.get_on_chain_transfer_outpoints()),
+            None => return 0,
+        };
+        psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter())
+            .filter(|(tx_input, _)| !on_chain_transfer_outpoints.contains(&tx_input.previous_output))
+            .filter_map(|(_, input)| input.witness_utxo.as_ref())
+            .filter(|previous_output| account_script_pub_keys.contains(&previous_output.script_pubkey))
+            .map(|previous_output| previous_output.value)
+            .sum()
+    }
+
+    /// Get how much an `Account`'s own utxos pay into `transaction`, the value of its inputs locked to the `Account`'s addresses,
+    /// other than the coins on chain transfers sent it, less the value of its outputs back to them.
+    /// 
+    /// The value of each input is read from `psbt`, which must spend every input of `transaction`.
+    fn get_account_net_spend(&self, user_id: i32, psbt: &PartiallySignedTransaction, transaction: &Transaction)-> u64 {
+        let (account_script_pub_keys, on_chain_transfer_outpoints) = match self.get_account_by_id(user_id) {
+            Some(account) => (account.get_addresses_as_script_pub_keys(), account.get_on_chain_transfer_outpoints()),
+            None => return 0,
+        };
+        let input_amount: u64 = psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter())
+            .filter(|(tx_input, _)| transaction.input.iter().any(|input| input.previous_output == tx_input.previous_output))
+            .filter(|(tx_input, _)| !on_chain_transfer_outpoints.contains(&tx_input.previous_output))
+            .filter_map(|(_, input)| input.witness_utxo.as_ref())
+            .filter(|previous_output| account_script_pub_keys.contains(&previous_output.script_pubkey))
+            .map(|previous_output| previous_output.value)
+            .sum();
+        let change_amount: u64 = transaction.output.iter()
+            .filter(|output| account_script_pub_keys.contains(&output.script_pubkey))
+            .map(|output| output.value)
+            .sum();
+        input_amount.saturating_sub(change_amount)
+    }
+
+    /// Get the id of the `Account` whose pending spend `txid` is, to be replaced.
+    /// 
+    /// # Errors
+    /// If the transaction is not pending on any `Account` return an `AccountError`.
+    /// If the transaction is a batched spend, pending on several `Account`s, return an `AccountError`, since its replacement
+    /// could not be charged to one `Account`.
+    fn get_pending_transaction_account_id(&self, txid: Txid)-> Result<i32, AccountError> {
+        let account_ids: Vec<i32> = self.accounts.iter()
+            .filter(|account| account.pending_transactions.iter().any(|transaction_details| transaction_details.txid == txid))
+            .map(|account| account.account_id)
+            .collect();
+        match account_ids[..] {
+            [] => Err(AccountError::TransactionNotPending),
+            [user_id] => Ok(user_id),
+            _ => Err(AccountError::Default("A batched spend can not be replaced")),
+        }
+    }
+
+    /// Sort the wallet's utxos by who may spend them in a spend by an `Account`.
+    /// 
+    /// # Errors
+    /// If there is an issue listing the wallet's utxos return a `WalletError`.
+    /// If the account does not exist then return a `WalletError`.
+    fn get_account_coins(&self, user_id: i32)-> Result<AccountCoins, WalletError> {
+        let account = self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?;
+        let account_script_pub_keys = account.get_addresses_as_script_pub_keys();
+        let other_children_script_pub_keys: Vec<Script> = self.accounts.iter()
+            .filter(|account| account.account_id != user_id && account.account_id != 1)
+            .flat_map(|account| account.get_addresses_as_script_pub_keys())
+            .collect();
+        // coins already spent by a proposal waiting for approval can not be used again
+        let mut account_coins = AccountCoins { own: vec![], transfered: vec![], master: vec![], unspendable: self.spend_proposals.get_reserved_outpoints() };
+        // coins of an on chain transfer which is not confirmed yet are not the `Account`'s to spend
+        account_coins.unspendable.extend(account.pending_on_chain_transfers.iter().map(|pending_on_chain_transfer| pending_on_chain_transfer.outpoint));
+
+        for utxo in self.master_account.list_unspent()? {
+            if account_coins.unspendable.contains(&utxo.outpoint) {
+                continue;
+            }
+            if account.on_chain_transfers.contains(&utxo.outpoint) {
+                account_coins.transfered.push(utxo.outpoint);
+            } else if account_script_pub_keys.contains(&utxo.txout.script_pubkey) {
+                account_coins.own.push(utxo.outpoint);
+            } else if other_children_script_pub_keys.contains(&utxo.txout.script_pubkey) {
+                account_coins.unspendable.push(utxo.outpoint);
+            } else {
+                account_coins.master.push(utxo.outpoint);
+            }
+        }
+        Ok(account_coins)
+    }
+
+    /// Update an `Account` after a spend it made has been broadcast.
+    /// 
+    /// The `change_address` the spend pays is added to the `Account`'s change addresses. The spend counts towards the `Account`'s spending limits, and if it is still pending it is added to
+    /// the `Account`'s pending_transactions. Whatever the `Account`'s own utxos spent as inputs, `user_btc_utxo_input_amount`,
+    /// could not cover is taken from the bitcoin it was transfered from the `MasterAccount`.
+    fn record_account_spend(&mut self, user_id: i32, amount: u64, now: u64, user_btc_utxo_input_amount: u64, change_address: Option<Address>, spend_bitcoin_result: TransactionDetails)-> Result<&'static str, AccountError> {
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        if let Some(change_address) = change_address {
+            account.add_change_address(change_address);
+        }
+        account.record_spend(amount, now);
+        if spend_bitcoin_result.confirmation_time.is_none() {
+            // remove any transfer amount used for user and master
+            // if the amount and fee we are spending is more than the value of the user's own utxos spent
+            // then remove the difference
+            let is_user_not_master_account = user_id !=1;
+
+            let total_spent = amount + spend_bitcoin_result.fee.unwrap_or(0);
+            let is_spent_btc_more_than_child_utxo_btc = total_spent > user_btc_utxo_input_amount;
+
+            if is_user_not_master_account && is_spent_btc_more_than_child_utxo_btc  {
+                let user_account = self.get_mut_account_by_id(user_id).unwrap(); //we can use unwrap since the spend result was successful
+                // remove from transfer amount whatever we can't cover in our utxo value amount 
+                let amount_needed_to_cover_from_transfered_amount =  total_spent - user_btc_utxo_input_amount;  // difference between amount spent and utxo amount
+                user_account.send_transfered_bitcoin(amount_needed_to_cover_from_transfered_amount);
+                self.master_account.amount_transfered_to_children -= amount_needed_to_cover_from_transfered_amount;
+            }
+            // update the users account
+            // if transaction still pending add it to the pending list
+            self.add_pending_transaction_to_user_account(user_id, spend_bitcoin_result)?;
+            Ok("PENDING")
+        } else {
+            Ok("Success")
+        }
+    }
+
+    /// Check that an `Account` may send to `address`.
+    /// 
+    /// # Errors
+    /// If the account does not have the `Send` or `SendToWhitelistOnly` permission return an `AccountError`.
+    /// If the account may only send to addresses in its address book and `address` is not one of them return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn check_send_permission(&self, user_id:i32, address: &Address)->Result<(), AccountError>{
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        if !account.has_permission_to_spend() {
+            return Err(AccountError::MissingPermission(BitcoinPermissions::Send))
+        }
+        if !account.can_send_to_address(address) {
+            return Err(AccountError::AddressNotWhitelisted)
+        }
+        Ok(())
+    }
+
+    /// Check that a spend by an `Account` may use `fee_rate`.
+    /// 
+    /// # Errors
+    /// If the fee rate is below the minimum relay fee rate return an `AccountError`.
+    /// If the fee rate is above the `Account`'s maximum fee rate return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn check_fee_rate(&self, user_id:i32, fee_rate: FeeRate)->Result<(), AccountError>{
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        if fee_rate.as_sat_vb() < MINIMUM_SAT_PER_VB {
+            return Err(AccountError::FeeRateTooLow)
+        }
+        match account.max_fee_rate {
+            Some(max_fee_rate) if fee_rate.as_sat_vb() > max_fee_rate => Err(AccountError::FeeRateAboveMaximum(max_fee_rate)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the highest fee rate, in sat/vB, an `Account`'s spends may use. `None` removes the maximum.
+    /// 
+    /// # Errors
+    /// If the maximum is below the minimum relay fee rate, no spend could be made, so return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn set_max_fee_rate(&mut self, user_id:i32, max_fee_rate: Option<f32>)->Result<(), AccountError>{
+        if max_fee_rate.is_some_and(|max_fee_rate| max_fee_rate < MINIMUM_SAT_PER_VB) {
+            return Err(AccountError::FeeRateTooLow)
+        }
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.max_fee_rate = max_fee_rate;
+        Ok(())
+    }
+
+    /// Add a labelled address to an `Account`'s address book.
+    /// 
+    /// # Errors
+    /// If the address is invalid or belongs to a different network than the `MasterAccount` wallet return an `AccountError`.
+    /// If the address or label is already in the address book return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn add_address_book_entry(&mut self, user_id:i32, label: String, address: &str)->Result<AddressBookEntry, AccountError>{
+        let address = self.master_account.validate_address(address).map_err(|_e| AccountError::InvalidAddress)?;
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.address_book.add(label.clone(), address.clone())?;
+        Ok(AddressBookEntry { label, address })
+    }
+
+    /// Remove an address from an `Account`'s address book, returning its entry.
+    /// 
+    /// # Errors
+    /// If the address is invalid or is not in the address book return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn remove_address_book_entry(&mut self, user_id:i32, address: &str)->Result<AddressBookEntry, AccountError>{
+        let address = self.master_account.validate_address(address).map_err(|_e| AccountError::InvalidAddress)?;
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.address_book.remove(&address)
+    }
+
+    /// Get an `Account`'s address book.
+    /// 
+    /// # Errors
+    /// If the account does not exist then return an `AccountError`.
+    pub fn get_address_book(&self, user_id:i32)->Result<&AddressBook, AccountError>{
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        Ok(&account.address_book)
+    }
+
+    /// Turn whitelist only mode on or off for an `Account`.
+    /// 
+    /// In whitelist only mode the `Account` has the `SendToWhitelistOnly` permission and may only spend to addresses in its address book.
+    /// 
+    /// # Errors
+    /// If the account does not exist then return an `AccountError`.
+    pub fn set_whitelist_only(&mut self, user_id:i32, whitelist_only: bool)->Result<(), AccountError>{
+        if whitelist_only {
+            self.grant_permission(user_id, BitcoinPermissions::SendToWhitelistOnly)
+        } else {
+            self.revoke_permission(user_id, BitcoinPermissions::SendToWhitelistOnly)
+        }
+    }
+
+    /// Check that an `Account` has a permission.
+    /// 
+    /// # Errors
+    /// If the account does not have the permission return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn check_permission(&self, user_id:i32, permission: BitcoinPermissions)->Result<(), AccountError>{
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        if account.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(AccountError::MissingPermission(permission))
+        }
+    }
+
+    /// Give an `Account` a permission.
+    /// 
+    /// # Errors
+    /// If the account does not exist then return an `AccountError`.
+    pub fn grant_permission(&mut self, user_id:i32, permission: BitcoinPermissions)->Result<(), AccountError>{
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.grant_permission(permission);
+        Ok(())
+    }
+
+    /// Take a permission away from an `Account`.
+    /// 
+    /// # Errors
+    /// If the account does not exist then return an `AccountError`.
+    pub fn revoke_permission(&mut self, user_id:i32, permission: BitcoinPermissions)->Result<(), AccountError>{
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.revoke_permission(permission);
+        Ok(())
+    }
+
+    /// Check that a child managing other children, `manager_id`, may change the `Account` of `child_id`.
+    /// 
+    /// A manager needs the `ManageChildren` permission, can not change its own or the head of the house's `Account`,
+    /// and can not hand out the `ManageChildren` permission itself.
+    /// 
+    /// # Errors
+    /// If the manager is not allowed to make the change return an `AccountError`.
+    pub fn check_can_manage_child(&self, manager_id:i32, child_id:i32, permission: BitcoinPermissions)->Result<(), AccountError>{
+        self.check_permission(manager_id, BitcoinPermissions::ManageChildren)?;
+        let is_managing_self_or_head_of_house = child_id == manager_id || child_id == 1;
+        if is_managing_self_or_head_of_house || permission == BitcoinPermissions::ManageChildren {
+            return Err(AccountError::MissingPermission(BitcoinPermissions::ManageChildren))
+        }
+        Ok(())
+    }
+
+    /// Request a transfer of `amount` from the `MasterAccount` to a child, for the head of the house to approve, and return the request's id.
+    /// 
+    /// # Errors
+    /// If the account does not have the `RequestTransfers` permission return an `AccountError`.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn request_transfer(&mut self, child_id:i32, amount: u64)->Result<u32, AccountError>{
+        self.check_permission(child_id, BitcoinPermissions::RequestTransfers)?;
+        Ok(self.transfer_requests.add(child_id, amount, get_current_timestamp()))
+    }
+
+    /// Get every transfer request waiting for the head of the house.
+    pub fn get_transfer_requests(&self)-> &Vec<TransferRequest>{
+        &self.transfer_requests.requests
+    }
+
+    /// Approve a transfer request, transfering the amount from the `MasterAccount` to the child.
+    /// 
+    /// # Errors
+    /// If the request does not exist return an `AccountError`.
+    /// If the `MasterAccount` can not cover the transfer return an `AccountError` and keep the request.
+    pub fn approve_transfer_request(&mut self, request_id: u32)->Result<TransferRequest, AccountError>{
+        let request = self.transfer_requests.remove(request_id)?;
+        match self.transfer_bitcoin_from_master_to_child(request.amount, request.child_id) {
+            Ok(()) => Ok(request),
+            Err(error) => {
+                self.transfer_requests.restore(request);
+                Err(error)
+            },
+        }
+    }
+
+    /// Reject a transfer request.
+    /// 
+    /// # Errors
+    /// If the request does not exist return an `AccountError`.
+    pub fn reject_transfer_request(&mut self, request_id: u32)->Result<TransferRequest, AccountError>{
+        self.transfer_requests.remove(request_id)
+    }
+
+    /// Return true if spending `amount` must first be approved by the head of the house, else return false.
+    pub fn does_spend_require_approval(&self, user_id:i32, amount: u64)->bool{
+        match self.get_account_by_id(user_id) {
+            Some(user_account) => user_account.does_spend_require_approval(amount),
+            None => false
+        }
+    }
+
+    /// Set the amount above which an `Account`'s spends must be approved, `None` removes the need for approval.
+    /// 
+    /// # Errors
+    /// If the account does not exist then return an `AccountError`.
+    pub fn set_approval_threshold(&mut self, user_id:i32, approval_threshold: Option<u64>)->Result<(), AccountError>{
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.approval_threshold = approval_threshold;
+        Ok(())
+    }
+
+    /// Get every spend proposal waiting for approval.
+    pub fn get_spend_proposals(&self)-> &Vec<SpendProposal>{
+        &self.spend_proposals.proposals
+    }
+
+    /// How many more signatures a spend proposal needs before it can be broadcast.
+    /// 
+    /// # Errors
+    /// If the proposal does not exist return an `AccountError`.
+    pub fn get_signatures_needed(&self, proposal_id: u32)-> Result<usize, AccountError>{
+        let proposal = self.spend_proposals.get(proposal_id)?;
+        Ok(self.master_account.signatures_needed(&proposal.psbt))
+    }
+
+    /// Get a spend proposal, for example to export its PSBT.
+    /// 
+    /// # Errors
+    /// If the proposal does not exist return an `AccountError`.
+    pub fn get_spend_proposal(&self, proposal_id: u32)-> Result<&SpendProposal, AccountError>{
+        self.spend_proposals.get(proposal_id)
+    }
+
+    /// Sign and broadcast a spend proposal, then update the `Account` which requested it.
+    /// 
+    /// A proposal whose imported PSBT is already signed is broadcast as it is. A multisig vault's proposal which still needs its
+    /// cosigners' signatures once the seed has signed is kept, and "AWAITING_SIGNATURE" is returned.
+    /// 
+    /// # Errors
+    /// If the proposal does not exist return an `AccountError`.
+    /// If the `MasterAccount` is locked, or its keys are held by an external signer, return an `AccountError`.
+    /// If the transaction can not be signed or broadcast, for example because its coins were spent since it was built,
+    /// return an `AccountError` and keep the proposal so it can be rejected.
+    #[instrument(target = "audit", name = "approve_spend_proposal", skip(self), ret, err(level = "warn"))]
+    pub fn approve_spend_proposal(&mut self, proposal_id: u32)-> Result<&'static str, AccountError>{
+        let proposal = self.spend_proposals.get_mut(proposal_id)?;
+        if !is_finalized(&proposal.psbt) {
+            // sign a copy, so a proposal which can not be signed keeps the signatures it already has
+            let mut psbt = proposal.psbt.clone();
+            self.master_account.sign_transaction(&mut psbt).map_err(|error| match error {
+                WalletError::WalletLocked => AccountError::WalletLocked,
+                WalletError::ExternalSigner => AccountError::ExternalSigner,
+                _ => AccountError::Default("Spend proposal could not be signed"),
+            })?;
+            let awaits_cosigners = self.master_account.multisig_policy().is_some() && !is_finalized(&psbt);
+            proposal.psbt = psbt;
+            if awaits_cosigners {
+                return Ok("AWAITING_SIGNATURE")
+            }
+        }
+        self.broadcast_spend_proposal(proposal_id)
+    }
+
+    /// Add the signatures of a PSBT signed on another device to a spend proposal, and return true if the proposal's
+    /// transaction is then fully signed and ready to broadcast.
+    /// 
+    /// The imported PSBT is combined with the proposal's, so partially signed PSBTs from several devices can be imported one
+    /// after the other, and every input with the signatures it needs is finalized.
+    /// 
+    /// # Errors
+    /// If the proposal does not exist return an `AccountError`.
+    /// If the PSBT is not of the proposal's transaction return an `AccountError`.
+    #[instrument(target = "audit", name = "import_signed_psbt", skip(self, signed_psbt), ret, err(level = "warn"))]
+    pub fn import_signed_psbt(&mut self, proposal_id: u32, signed_psbt: PartiallySignedTransaction)-> Result<bool, AccountError>{
+        let proposal = self.spend_proposals.get_mut(proposal_id)?;
+        if signed_psbt.unsigned_tx != proposal.psbt.unsigned_tx {
+            return Err(AccountError::PsbtMismatch)
+        }
+        let mut psbt = proposal.psbt.clone();
+        psbt.combine(signed_psbt).map_err(|_e| AccountError::PsbtMismatch)?;
+        let is_ready = self.master_account.finalize_psbt(&mut psbt).map_err(|_e| AccountError::Default("Unable to finalize the PSBT"))?;
+        proposal.psbt = psbt;
+        Ok(is_ready)
+    }
+
+    /// Broadcast the signed transaction of a spend proposal, then update the `Account` which requested it as `spend_bitcoin_with_fee` does.
+    /// 
+    /// # Errors
+    /// If the proposal does not exist return an `AccountError`.
+    /// If the proposal's transaction is not fully signed return an `AccountError` and keep the proposal.
+    /// If the transaction can not be broadcast, for example because its coins were spent since it was built,
+    /// return an `AccountError` and keep the proposal so it can be rejected.
+    #[instrument(target = "audit", name = "broadcast_spend_proposal", skip(self), ret, err(level = "warn"))]
+    pub fn broadcast_spend_proposal(&mut self, proposal_id: u32)-> Result<&'static str, AccountError>{
+        let proposal = self.spend_proposals.remove(proposal_id)?;
+        let user_btc_utxo_input_amount = self.get_account_input_amount(proposal.user_id, &proposal.psbt);
+
+        match self.master_account.broadcast_transaction(proposal.psbt.clone(), proposal.transaction_details.clone()) {
+            Ok(spend_bitcoin_result) => self.record_account_spend(proposal.user_id, proposal.amount, get_current_timestamp(), user_btc_utxo_input_amount, proposal.change_address, spend_bitcoin_result),
+            Err(WalletError::UnsignedTransaction) => {
+                self.spend_proposals.restore(proposal);
+                Err(AccountError::UnsignedTransaction)
+            },
+            Err(_wallet_error) => {
+                self.spend_proposals.restore(proposal);
+                Err(AccountError::Default("Spend proposal could not be broadcast"))
+            },
+        }
+    }
+
+    /// Reject a spend proposal, releasing the amount it held from the `Account`'s balance.
+    /// 
+    /// # Errors
+    /// If the proposal does not exist return an `AccountError`.
+    pub fn reject_spend_proposal(&mut self, proposal_id: u32)-> Result<SpendProposal, AccountError>{
+        self.spend_proposals.remove(proposal_id)
+    }
+
+    /// Get the total held from an `Account`'s balance by its spend proposals waiting for approval.
+    pub fn get_account_held_amount(&self, user_id:i32)-> u64{
+        self.spend_proposals.get_user_held_amount(user_id)
     }
 
     /// Return true if the user account has more bitcoin than the `amount_to_spend`, else return false.
+    /// 
+    /// Bitcoin held by the user's spend proposals waiting for approval is not available to spend.
     pub fn does_user_have_sufficient_funds_to_spend(&mut self, user_id:i32, amount_to_spend:u64)->Result<bool, WalletError>{
-        let account_balance = self.get_account_balance_utxo_amount_plus_transfer_balance(user_id)?;
+        let account_balance = self.get_account_available_balance(user_id)?;
 
         if account_balance > amount_to_spend{
             return Ok(true)
@@ -155,6 +1388,38 @@ impl  HeadOfTheHouse {
         }
     }
 
+    /// Check that spending `amount` at `now` is within the `Account`'s spending limits.
+    /// 
+    /// # Errors
+    /// If the spend would exceed a spending limit return an `AccountError` with the `Account`'s spend tally.
+    /// If the account does not exist then return an `AccountError`.
+    pub fn check_spending_limits(&self, user_id:i32, amount: u64, now: u64)->Result<(), AccountError>{
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        match account.get_exceeded_spending_period(amount, now) {
+            Some(period) => Err(AccountError::SpendingLimitExceeded(period, account.get_spend_tally(now))),
+            None => Ok(()),
+        }
+    }
+
+    /// Replace an `Account`'s spending limits.
+    /// 
+    /// # Errors
+    /// If the account does not exist then return an `AccountError`.
+    pub fn set_spending_limits(&mut self, user_id:i32, spending_limits: SpendingLimits)->Result<(), AccountError>{
+        let account = self.get_mut_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        account.spending_limits = spending_limits;
+        Ok(())
+    }
+
+    /// Get the total an `Account` has spent in each rolling spending period.
+    /// 
+    /// # Errors
+    /// If the account does not exist then return an `AccountError`.
+    pub fn get_account_spend_tally(&self, user_id:i32)->Result<SpendTally, AccountError>{
+        let account = self.get_account_by_id(user_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        Ok(account.get_spend_tally(get_current_timestamp()))
+    }
+
     /// Subtract amount from an `Account`'s bitcoin amount.
     /// 
     /// # Errors
@@ -181,13 +1446,21 @@ impl  HeadOfTheHouse {
         Ok(())
     }
 
-    /// Generate a new address from the master account and add it to the users account.
+    /// Generate a new address from the master account, or from the account's sub-account if it has one, and add it to the users account.
     /// 
     /// # Errors 
     /// If there is complications generating a new address return a `WalletError`.
+    /// If the account does not have the `Receive` permission return a `WalletError`.
     /// If the account does not exist then return a `WalletError`.
     pub fn get_new_address(&mut self, user_id:i32,)-> Result<Address, WalletError> {
-        let new_address = self.master_account.generate_new_address()?;
+        let account = self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?;
+        if !account.has_permission(BitcoinPermissions::Receive) {
+            return Err(WalletError::MissingPermission(BitcoinPermissions::Receive))
+        }
+        let new_address = match account.account_index {
+            Some(account_index) => self.master_account.generate_new_sub_account_address(account_index)?,
+            None => self.master_account.generate_new_address()?,
+        };
         // add new address to the users account 
         let account = self.get_mut_account_by_id(user_id).ok_or(WalletError::AddressError)?;
         account.add_address(new_address.clone());
@@ -196,6 +1469,9 @@ impl  HeadOfTheHouse {
 
     /// Get the utxo bitcoin balance from an `Account`.
     /// 
+    /// The coins on chain transfers from the `MasterAccount` sent the `Account` are not counted, they are counted in its
+    /// `bitcoin_transfered_from_master` once confirmed, see `transfer_bitcoin_from_master_to_child_on_chain`.
+    /// 
     /// # Errors
     /// If there is trouble syncing the wallet return `WalletError`.
     /// If the account does not exist then return a `WalletError`.
@@ -204,11 +1480,12 @@ impl  HeadOfTheHouse {
         self.master_account.sync_wallet()?;
         let account = self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?;
         let account_script_pub_keys = account.get_addresses_as_script_pub_keys();
-        let wallet_utxos = self.master_account.wallet.list_unspent()?;
+        let on_chain_transfer_outpoints = account.get_on_chain_transfer_outpoints();
+        let wallet_utxos = self.master_account.list_unspent()?;
 
         for txd in &wallet_utxos{
             // if this address is part of a utxo then add it to the balance
-            if account_script_pub_keys.contains(&txd.txout.script_pubkey){
+            if account_script_pub_keys.contains(&txd.txout.script_pubkey) && !on_chain_transfer_outpoints.contains(&txd.outpoint){
                 total_balance += txd.txout.value
             }
          }
@@ -251,6 +1528,16 @@ impl  HeadOfTheHouse {
         Ok(utxo_balance + amount_transfered_from_master)
     }
 
+    /// Get an `Account`'s total bitcoin balance minus what is held by its spend proposals waiting for approval.
+    /// 
+    /// # Errors 
+    /// If the account does not exist then return a `WalletError`.
+    /// If there is an issue getting the utxo balance then return a `WalletError`.
+    pub fn get_account_available_balance(&mut self, user_id:i32)-> Result<u64, WalletError>{
+        let account_balance = self.get_account_balance_utxo_amount_plus_transfer_balance(user_id)?;
+        Ok(account_balance.saturating_sub(self.get_account_held_amount(user_id)))
+    }
+
     /// Get the bitcoin spend total that is currently pending.
     /// 
     /// # Errors 
@@ -258,21 +1545,118 @@ impl  HeadOfTheHouse {
     pub fn get_pending_spend_amount(&mut self,  user_id:i32)-> Result<u64, AccountError>{ 
         let account_option = self.get_account_by_id(user_id);
 
-        // if no account, throw AccountError
-        let account = match account_option {
-            Some(account) => account,
-            None => return Err(AccountError::AccountDoesNotExist("AccountDoesNotExist"))
+        // if no account, throw AccountError
+        let account = match account_option {
+            Some(account) => account,
+            None => return Err(AccountError::AccountDoesNotExist("AccountDoesNotExist"))
+        };
+
+        let pending_transactions = &account.pending_transactions;
+        
+        let mut pending_spend_amount = 0;
+        for transaction in pending_transactions{
+          pending_spend_amount += transaction.sent;
+          pending_spend_amount += transaction.fee.ok_or(AccountError::InsufficientAccount)?;
+        }
+        Ok(pending_spend_amount)
+      }
+
+    /// Get an `Account`'s pending transactions, first removing any which are no longer pending.
+    /// 
+    /// A transaction is no longer pending once it has been confirmed, or once it is no longer known to the wallet.
+    /// When the replacement of a cancelled spend is confirmed the bitcoin transfered from the `MasterAccount` which the spend
+    /// used is given back to the `Account`, and if the replacement is no longer known, because the spend was confirmed instead,
+    /// the cancellation is dropped.
+    /// 
+    /// # Errors
+    /// If there is an issue syncing with the bitcoin network return a `WalletError`.
+    /// If the account does not exist then return a `WalletError`.
+    pub fn get_account_pending_transactions(&mut self, user_id:i32)-> Result<&Vec<TransactionDetails>, WalletError>{
+        self.master_account.sync_wallet()?;
+        let account = self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?;
+
+        let mut transactions_that_are_no_longer_pending = vec![];
+        let mut confirmed_transactions = vec![];
+        for transaction_detail in &account.pending_transactions {
+            match self.master_account.get_transaction(&transaction_detail.txid, false)? {
+                Some(wallet_transaction) if wallet_transaction.confirmation_time.is_none() => {},
+                Some(_wallet_transaction) => {
+                    transactions_that_are_no_longer_pending.push(transaction_detail.txid);
+                    confirmed_transactions.push(transaction_detail.txid);
+                },
+                None => transactions_that_are_no_longer_pending.push(transaction_detail.txid),
+            }
+        }
+
+        let account = self.get_mut_account_by_id(user_id).ok_or(WalletError::AddressError)?;
+        account.pending_transactions.retain(|transaction_detail| !transactions_that_are_no_longer_pending.contains(&transaction_detail.txid));
+        let transfered_bitcoin_restored: u64 = account.pending_cancellations.iter()
+            .filter(|pending_cancellation| confirmed_transactions.contains(&pending_cancellation.txid))
+            .map(|pending_cancellation| pending_cancellation.transfered_bitcoin_to_restore)
+            .sum();
+        account.pending_cancellations.retain(|pending_cancellation| !transactions_that_are_no_longer_pending.contains(&pending_cancellation.txid));
+        if transfered_bitcoin_restored > 0 {
+            account.receive_transfered_bitcoin(transfered_bitcoin_restored);
+            self.master_account.amount_transfered_to_children += transfered_bitcoin_restored;
+            info!(user_id, transfered_bitcoin_restored, "restored the bitcoin transfered to a child used by a cancelled spend");
+        }
+        Ok(&self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?.pending_transactions)
+    }
+
+    /// Check the replacements of every `Account`'s cancelled spends, giving back the bitcoin transfered from the `MasterAccount`
+    /// which the spends used once their replacements are confirmed, see `get_account_pending_transactions`.
+    /// 
+    /// # Errors
+    /// If there is an issue syncing with the bitcoin network return a `WalletError`.
+    pub fn settle_pending_cancellations(&mut self)-> Result<(), WalletError>{
+        let account_ids: Vec<i32> = self.accounts.iter()
+            .filter(|account| !account.pending_cancellations.is_empty())
+            .map(|account| account.account_id)
+            .collect();
+        for account_id in account_ids {
+            self.get_account_pending_transactions(account_id)?;
+        }
+        Ok(())
+    }
+
+    /// Get every transaction which paid to or spent from an `Account`'s addresses, oldest first with unconfirmed transactions last.
+    /// 
+    /// # Errors
+    /// If there is an issue syncing with the bitcoin network return a `WalletError`.
+    /// If the account does not exist then return a `WalletError`.
+    pub fn get_account_transaction_history(&mut self, user_id:i32)-> Result<Vec<TransactionDetails>, WalletError>{
+        self.master_account.sync_wallet()?;
+        let account = self.get_account_by_id(user_id).ok_or(WalletError::AddressError)?;
+        let account_script_pub_keys = account.get_addresses_as_script_pub_keys();
+        let master_account = &self.master_account;
+
+        let is_account_output = |txid, vout: u32| -> Result<bool, WalletError> {
+            let previous_transaction = master_account.get_transaction(&txid, true)?.and_then(|details| details.transaction);
+            Ok(previous_transaction
+                .and_then(|transaction| transaction.output.get(vout as usize).cloned())
+                .is_some_and(|output| account_script_pub_keys.contains(&output.script_pubkey)))
         };
 
-        let pending_transactions = &account.pending_transactions;
-        
-        let mut pending_spend_amount = 0;
-        for transaction in pending_transactions{
-          pending_spend_amount += transaction.sent;
-          pending_spend_amount += transaction.fee.ok_or(AccountError::InsufficientAccount)?;
+        let mut transaction_history = vec![];
+        for transaction_details in master_account.list_transactions(true)? {
+            let transaction = match &transaction_details.transaction {
+                Some(transaction) => transaction,
+                None => continue,
+            };
+            let pays_account = transaction.output.iter().any(|output| account_script_pub_keys.contains(&output.script_pubkey));
+            let mut spends_from_account = false;
+            for input in &transaction.input {
+                if is_account_output(input.previous_output.txid, input.previous_output.vout)? {
+                    spends_from_account = true;
+                }
+            }
+            if pays_account || spends_from_account {
+                transaction_history.push(transaction_details);
+            }
         }
-        Ok(pending_spend_amount)
-      }
+        transaction_history.sort_by_key(|transaction_details| transaction_details.confirmation_time.as_ref().map_or(u32::MAX, |block_time| block_time.height));
+        Ok(transaction_history)
+    }
 
     /// Transfer bitcoin from the `MasterAccount` to another `Account`.
     /// 
@@ -283,8 +1667,9 @@ impl  HeadOfTheHouse {
     /// # Errors
     /// If the `MasterAccount` does not have the funds to transfer to the `Account` Throw an `AccountError`.
     /// If the `Account` does not exist throw an `AccountError`.
+    #[instrument(target = "audit", name = "transfer_to_child", skip(self), err(level = "warn"))]
     pub fn transfer_bitcoin_from_master_to_child(&mut self, transfer_amount: u64, child_id:i32)-> Result<(), AccountError>{
-        let total_bitcoin_in_master_account = self.master_account.get_bitcoin_total_minus_transfers_to_children().or(Err(AccountError::InsufficientAccount))?;
+        let total_bitcoin_in_master_account = self.get_master_account_balance_minus_pending_on_chain_transfers()?;
         
         if total_bitcoin_in_master_account  >= transfer_amount{
             let account_option = self.get_mut_account_by_id(child_id);
@@ -300,12 +1685,123 @@ impl  HeadOfTheHouse {
 
             // remove btc from master account
             self.master_account.transfer_bitcoin(transfer_amount);
+            info!(target: "audit", "transfered bitcoin from the master account");
             Ok(())
         }else {
             Err(AccountError::InsufficientAccount)
         }
     }
 
+    /// Transfer bitcoin from the `MasterAccount` to another `Account` on chain, and return the transfer.
+    /// 
+    /// Unlike `transfer_bitcoin_from_master_to_child` the bitcoin is actually sent, the `MasterAccount`'s coins pay `transfer_amount`
+    /// to a new address of the `Account` at the fee rate chosen by `fee_selection`, and the `MasterAccount` pays the fee. Until the
+    /// transfer is confirmed it is kept in the `Account`'s pending_on_chain_transfers and neither the `MasterAccount`'s
+    /// `amount_transfered_to_children` nor the `Account`'s `bitcoin_transfered_from_master` changes, once it is confirmed both are
+    /// increased by `transfer_amount`, see `settle_pending_on_chain_transfers`. The coins sent are not counted in the `Account`'s
+    /// utxo balance, so they are only counted once, as bitcoin transfered from the `MasterAccount`.
+    /// 
+    /// # Errors
+    /// If the `Account` is the head of the house's own `Account` return an `AccountError`.
+    /// If the `Account` does not have the `Receive` permission return an `AccountError`.
+    /// If the fee rate can not be estimated or is below the minimum relay fee rate return an `AccountError`.
+    /// If the `MasterAccount` does not have the funds to transfer to the `Account`, and pay the fee, return an `AccountError`.
+    /// If the transaction can not be signed, for example because its keys are held by an external signer, return an `AccountError`.
+    /// If the transaction can not be broadcast return an `AccountError`, and no transfer is recorded.
+    /// If the `Account` does not exist return an `AccountError`.
+    #[instrument(target = "audit", name = "transfer_to_child_on_chain", skip(self, fee_selection), ret, err(level = "warn"))]
+    pub fn transfer_bitcoin_from_master_to_child_on_chain(&mut self, transfer_amount: u64, child_id:i32, fee_selection: FeeSelection)-> Result<TransactionDetails, AccountError>{
+        self.get_account_by_id(child_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        if child_id == 1 {
+            return Err(AccountError::Default("Can not transfer to the master account"))
+        }
+        let fee_rate = self.master_account.get_fee_rate(&fee_selection).map_err(|_e| AccountError::Default("Unable to estimate the fee rate"))?;
+        self.check_fee_rate(1, fee_rate)?;
+        let total_bitcoin_in_master_account = self.get_master_account_balance_minus_pending_on_chain_transfers()?;
+        if total_bitcoin_in_master_account < transfer_amount {
+            return Err(AccountError::InsufficientAccount)
+        }
+
+        let child_address = self.get_new_address(child_id).map_err(|error| match error {
+            WalletError::MissingPermission(permission) => AccountError::MissingPermission(permission),
+            _ => AccountError::Default("Unable to generate an address for the child"),
+        })?;
+        // only the master account's coins pay for the transfer, the children's coins stay their own
+        let unspendable = self.get_account_coins(1).map_err(|_e| AccountError::Default("Unable to list the wallet's coins"))?.unspendable;
+        let (psbt, tx_details) = self.master_account.build_spend_transaction(transfer_amount, &child_address.to_string(), fee_rate, vec![], unspendable, None)
+            .map_err(|_e| AccountError::InsufficientAccount)?;
+        if total_bitcoin_in_master_account < transfer_amount + tx_details.fee.unwrap_or(0) {
+            return Err(AccountError::InsufficientAccount)
+        }
+        let vout = psbt.unsigned_tx.output.iter().position(|output| output.script_pubkey == child_address.script_pubkey())
+            .ok_or(AccountError::Default("Unable to build the transfer"))?;
+
+        let transfer_transaction = self.master_account.sign_and_broadcast_transaction(psbt, tx_details).map_err(|error| match error {
+            WalletError::WalletLocked => AccountError::WalletLocked,
+            WalletError::ExternalSigner => AccountError::ExternalSigner,
+            WalletError::UnsignedTransaction => AccountError::UnsignedTransaction,
+            WalletError::BroadcastTransactionError => AccountError::Default("The transfer could not be broadcast"),
+            _ => AccountError::Default("The transfer could not be signed"),
+        })?;
+        let outpoint = OutPoint { txid: transfer_transaction.txid, vout: vout as u32 };
+        let child_account = self.get_mut_account_by_id(child_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        child_account.add_pending_on_chain_transfer(PendingOnChainTransfer { outpoint, amount: transfer_amount });
+        info!(target: "audit", txid = %transfer_transaction.txid, address = %child_address, fee = ?transfer_transaction.fee, "sent bitcoin from the master account on chain");
+        Ok(transfer_transaction)
+    }
+
+    /// Check every `Account`'s pending on chain transfers, see `transfer_bitcoin_from_master_to_child_on_chain`.
+    /// 
+    /// Once a transfer is confirmed its amount is added to both the `MasterAccount`'s `amount_transfered_to_children` and the
+    /// `Account`'s `bitcoin_transfered_from_master`, and transfers which are no longer known to the wallet, because they were
+    /// dropped or replaced, are removed.
+    /// 
+    /// # Errors
+    /// If there is an issue syncing with the bitcoin network return a `WalletError`.
+    pub fn settle_pending_on_chain_transfers(&mut self)-> Result<(), WalletError>{
+        self.master_account.sync_wallet()?;
+        for account in self.accounts.iter_mut() {
+            let mut transfers_that_are_no_longer_pending = vec![];
+            let mut confirmed_transfers = vec![];
+            for pending_on_chain_transfer in &account.pending_on_chain_transfers {
+                let txid = pending_on_chain_transfer.outpoint.txid;
+                match self.master_account.get_transaction(&txid, false)? {
+                    Some(wallet_transaction) if wallet_transaction.confirmation_time.is_none() => {},
+                    Some(_wallet_transaction) => {
+                        transfers_that_are_no_longer_pending.push(pending_on_chain_transfer.outpoint);
+                        confirmed_transfers.push(pending_on_chain_transfer.clone());
+                    },
+                    None => {
+                        transfers_that_are_no_longer_pending.push(pending_on_chain_transfer.outpoint);
+                        warn!(user_id = account.account_id, %txid, "on chain transfer to a child is no longer known to the wallet");
+                    },
+                }
+            }
+            account.pending_on_chain_transfers.retain(|pending_on_chain_transfer| !transfers_that_are_no_longer_pending.contains(&pending_on_chain_transfer.outpoint));
+            for confirmed_transfer in confirmed_transfers {
+                account.on_chain_transfers.push(confirmed_transfer.outpoint);
+                account.receive_transfered_bitcoin(confirmed_transfer.amount);
+                self.master_account.transfer_bitcoin(confirmed_transfer.amount);
+                info!(user_id = account.account_id, txid = %confirmed_transfer.outpoint.txid, amount = confirmed_transfer.amount, "on chain transfer to a child confirmed");
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the `MasterAccount`'s bitcoin total minus what it has transfered to other `Account`s, and minus what its pending
+    /// on chain transfers are sending, which are already on the `Account`s' addresses but not yet counted as transfered.
+    /// 
+    /// # Errors
+    /// If there is an issue connecting to the bitcoin network return an `AccountError`.
+    fn get_master_account_balance_minus_pending_on_chain_transfers(&self)-> Result<u64, AccountError>{
+        let total_bitcoin_in_master_account = self.master_account.get_bitcoin_total_minus_transfers_to_children().or(Err(AccountError::InsufficientAccount))?;
+        let pending_on_chain_transfer_amount: u64 = self.accounts.iter()
+            .flat_map(|account| account.pending_on_chain_transfers.iter())
+            .map(|pending_on_chain_transfer| pending_on_chain_transfer.amount)
+            .sum();
+        Ok(total_bitcoin_in_master_account.saturating_sub(pending_on_chain_transfer_amount))
+    }
+
     /// Transfer previously `MasterAccount` -> `Account` back to the `MasterAccount` from the `Account`.
     /// 
     /// This will only transfer bitcoin internally, no actual bitcoin transaction will be made.
@@ -314,6 +1810,7 @@ impl  HeadOfTheHouse {
     /// # Errors 
     /// If the amount that wants to be transfered from the `Account` to the `MasterAccount` is more than the `Account` 
     /// has been transfered and has not spent, then throw an `AccountError`.
+    #[instrument(target = "audit", name = "transfer_to_master", skip(self), err(level = "warn"))]
     pub fn transfer_bitcoin_from_child_to_master(&mut self, transfer_amount: u64, child_id:i32)-> Result<(), AccountError>{
         // if transfer_amount <= the amount the child has been transfered then make the swap
         let account_option = self.get_mut_account_by_id(child_id);
@@ -327,15 +1824,50 @@ impl  HeadOfTheHouse {
         if child_account.bitcoin_transfered_from_master >= transfer_amount {
             child_account.send_transfered_bitcoin(transfer_amount);
             self.master_account.receive_bitcoin_transfer_from_child(transfer_amount);
+            info!(target: "audit", "transfered bitcoin back to the master account");
             Ok(())
         }else {
             Err(AccountError::InsufficientAccount)
         }
     }
+
+    /// Schedule a recurring allowance of `amount` from the `MasterAccount` to a child, first due at `first_due`, and return the rule's id.
+    /// 
+    /// # Errors
+    /// If the child's `Account` does not exist return an `AccountError`.
+    pub fn add_allowance(&mut self, child_id:i32, amount: u64, interval: AllowanceInterval, first_due: u64, insufficient_funds_policy: InsufficientFundsPolicy)-> Result<u32, AccountError>{
+        self.get_account_by_id(child_id).ok_or(AccountError::AccountDoesNotExist("AccountDoesNotExist"))?;
+        Ok(self.allowance_scheduler.add_rule(child_id, amount, interval, first_due, insufficient_funds_policy))
+    }
+
+    /// Stop a recurring allowance, dropping any of its queued transfers.
+    /// 
+    /// # Errors
+    /// If the allowance rule does not exist return an `AccountError`.
+    pub fn remove_allowance(&mut self, rule_id: u32)-> Result<(), AccountError>{
+        self.allowance_scheduler.remove_rule(rule_id)?;
+        Ok(())
+    }
+
+    /// Apply every allowance due at or before `now` with `transfer_bitcoin_from_master_to_child`, returning what happened to each.
+    /// 
+    /// An allowance the `MasterAccount` can not cover is skipped or queued depending on its rule.
+    pub fn tick_allowances(&mut self, now: u64)-> Vec<AllowanceRecord>{
+        let mut allowance_scheduler = std::mem::take(&mut self.allowance_scheduler);
+        let handled = allowance_scheduler.tick(now, |child_id, amount| self.transfer_bitcoin_from_master_to_child(amount, child_id));
+        self.allowance_scheduler = allowance_scheduler;
+        handled
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::spending_limits::SpendingPeriod;
+    use crate::allowances::AllowanceOutcome;
+    use crate::restore::DEFAULT_STOP_GAP;
+    use crate::master_account::SpendOutcome;
+    use bdk::bitcoin::TxIn;
+    use bdk::bitcoin::hashes::Hash;
     use crate::testing_helpers::{attach_wallet_to_regtest_electrum_server, get_default_mnenomic_words, mine_a_block, sleep_while_block_being_mined, get_random_mnenomic_words, test_result_type_is_not_err, get_base_address, set_up, build_mock_transaction};
 
     // used to handle async await functions
@@ -350,7 +1882,7 @@ mod tests {
     fn get_account_by_id(){
         set_up();
         let mut mock_children = Children::new();
-        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None).unwrap();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
 
         //create child 
         new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Send]);
@@ -364,18 +1896,64 @@ mod tests {
     fn add_account_automatically_when_adding_new_user() {
         set_up();
         let mut mock_children = Children::new();
-        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None).unwrap();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
         new_head_of_house.create_new_user(&mut mock_children,1, String::from("my new user"), vec![BitcoinPermissions::Send]);
         let new_account = new_head_of_house.accounts.get(0).unwrap();
         assert_eq!(new_account.bitcoin_amount, 0);
         assert_eq!(new_account.account_id, 1);
     }
 
+    #[test]
+    fn whitelist_only_spend_to_address_outside_the_address_book_is_rejected(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Send]);
+        new_head_of_house.add_address_book_entry(2, String::from("grandma"), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20").unwrap();
+        new_head_of_house.set_whitelist_only(2, true).unwrap();
+
+        let spend_result = new_head_of_house.spend_bitcoin(2, 1000, "bcrt1q8wmlcmsw6756u87swmx8ux0d8m2ukjjn9q9g4k");
+
+        assert!(matches!(spend_result, Err(AccountError::AddressNotWhitelisted)));
+        let address_book = new_head_of_house.get_address_book(2).unwrap();
+        assert_eq!(address_book.entries[0].label, String::from("grandma"));
+    }
+
+    #[test]
+    fn spend_above_the_accounts_max_fee_rate_is_rejected(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Send]);
+        new_head_of_house.set_max_fee_rate(2, Some(10.0)).unwrap();
+
+        let expensive_spend = new_head_of_house.spend_bitcoin_with_fee(2, 1000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", FeeSelection::SatPerVb(50.0));
+        let underpaying_spend = new_head_of_house.spend_bitcoin_with_fee(2, 1000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", FeeSelection::SatPerVb(0.5));
+
+        assert!(matches!(expensive_spend, Err(AccountError::FeeRateAboveMaximum(max_fee_rate)) if max_fee_rate == 10.0));
+        assert!(matches!(underpaying_spend, Err(AccountError::FeeRateTooLow)));
+        assert!(matches!(new_head_of_house.set_max_fee_rate(2, Some(0.5)), Err(AccountError::FeeRateTooLow)));
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().max_fee_rate, Some(10.0));
+    }
+
+    #[test]
+    fn address_book_rejects_addresses_for_another_network(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Send]);
+
+        let mainnet_entry = new_head_of_house.add_address_book_entry(2, String::from("shop"), "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq");
+
+        assert!(matches!(mainnet_entry, Err(AccountError::InvalidAddress)));
+        assert!(new_head_of_house.get_address_book(2).unwrap().entries.is_empty());
+    }
+
     #[test]
     fn test_initiating_new_head_of_house_hold() {
         set_up();
         let mut mock_children = Children::new();
-        let new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None).unwrap();
+        let new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
         // automatically create a child for a master
         assert_eq!(mock_children.children.len(), 1);
 
@@ -388,7 +1966,8 @@ mod tests {
         let let_head_of_household_regular_account = new_head_of_house.get_account_by_id(1).unwrap();
         assert_eq!(let_head_of_household_regular_account.bitcoin_amount, 0);
         assert_eq!(let_head_of_household_regular_account.account_id, 1);
-        assert_eq!(let_head_of_household_regular_account.permissions, vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+        assert_eq!(let_head_of_household_regular_account.permissions, main_account_permissions());
+        assert!(let_head_of_household_regular_account.has_permission(BitcoinPermissions::ManageChildren));
 
 
         // create a master account
@@ -421,13 +2000,423 @@ mod tests {
         assert_eq!(master_account_total.unwrap(), 149999859)
     }
 
+    #[test]
+    fn child_spend_uses_only_the_childs_own_coins(){
+        set_up();
+        let (_mock_children, mut new_head_of_house) = set_up_user_with_no_bitcoin_and_one_child();
+        let master_address = new_head_of_house.master_account.generate_new_address().unwrap();
+        let child_address = new_head_of_house.get_new_address(2).unwrap();
+
+        // give the master account and the child a coin each
+        aw!(mine_a_block(&master_address.to_string()));
+        aw!(mine_a_block(&child_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.master_account.sync_wallet().unwrap();
+        let master_coin = new_head_of_house.master_account.wallet.list_unspent().unwrap().into_iter()
+            .find(|utxo| utxo.txout.script_pubkey == master_address.script_pubkey())
+            .unwrap();
+
+        let spend_result = new_head_of_house.spend_bitcoin(2, convert_float_to_satoshis(0.5), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+        test_result_type_is_not_err(spend_result);
+
+        let unspent_outpoints: Vec<OutPoint> = new_head_of_house.master_account.wallet.list_unspent().unwrap().iter().map(|utxo| utxo.outpoint).collect();
+        assert!(unspent_outpoints.contains(&master_coin.outpoint));
+        // the child's change is sent back to the child, so its balance only drops by the amount and the fee
+        assert_eq!(new_head_of_house.get_account_balance_without_transfered_amount(2).unwrap(), convert_float_to_satoshis(0.5) - 141);
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().change_addresses.len(), 1);
+    }
+
+    #[test]
+    fn batched_spend_charges_each_paying_account_its_share(){
+        set_up();
+        let (_mock_children, mut new_head_of_house) = set_up_user_with_no_bitcoin_and_one_child();
+        let master_address = new_head_of_house.master_account.generate_new_address().unwrap();
+        aw!(mine_a_block(&master_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.master_account.sync_wallet().unwrap();
+        new_head_of_house.transfer_bitcoin_from_master_to_child(convert_float_to_satoshis(1.0), 2).unwrap();
+        let child_address = new_head_of_house.get_new_address(2).unwrap();
+        let payouts = vec![
+            BatchPayout { user_id: 1, address: child_address.to_string(), amount: convert_float_to_satoshis(0.3) },
+            BatchPayout { user_id: 2, address: String::from("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"), amount: convert_float_to_satoshis(0.2) },
+        ];
+
+        let batch_transaction = new_head_of_house.spend_bitcoin_batch(&payouts, BatchFeePolicy::Even, FeeSelection::SatPerVb(2.0)).unwrap();
+
+        let fee = batch_transaction.fee.unwrap();
+        assert_eq!(batch_transaction.transaction.unwrap().output.len(), 3);
+        // the head of the house and the child each pay half the fee, the child out of its transfered bitcoin
+        let child_fee = fee / 2;
+        let child_account = new_head_of_house.get_account_by_id(2).unwrap();
+        assert_eq!(child_account.bitcoin_transfered_from_master, convert_float_to_satoshis(0.8) - child_fee);
+        assert_eq!(child_account.pending_transactions[0].txid, batch_transaction.txid);
+        assert_eq!(new_head_of_house.get_pending_spend_amount(2).unwrap(), convert_float_to_satoshis(0.2) + child_fee);
+        assert_eq!(new_head_of_house.get_pending_spend_amount(1).unwrap(), convert_float_to_satoshis(0.3) + fee - child_fee);
+        assert_eq!(new_head_of_house.master_account.amount_transfered_to_children, convert_float_to_satoshis(0.8) - child_fee);
+        assert!(matches!(new_head_of_house.bump_fee(batch_transaction.txid, FeeSelection::SatPerVb(5.0)), Err(AccountError::Default(_))));
+    }
+
+    #[test]
+    fn batched_spend_is_checked_against_each_paying_account(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Send]);
+        new_head_of_house.create_new_user(&mut mock_children, 3, String::from("two"), vec![BitcoinPermissions::Receive]);
+        new_head_of_house.set_approval_threshold(2, Some(10_000)).unwrap();
+        let payout = |user_id: i32, amount: u64| BatchPayout { user_id, address: String::from("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"), amount };
+
+        assert!(matches!(new_head_of_house.spend_bitcoin_batch(&[], BatchFeePolicy::Proportional, FeeSelection::default()), Err(AccountError::EmptyBatch)));
+        let without_send_permission = new_head_of_house.spend_bitcoin_batch(&[payout(2, 5_000), payout(3, 5_000)], BatchFeePolicy::Proportional, FeeSelection::default());
+        assert!(matches!(without_send_permission, Err(AccountError::MissingPermission(BitcoinPermissions::Send))));
+        // two payouts under the threshold which are over it together
+        let above_approval_threshold = new_head_of_house.spend_bitcoin_batch(&[payout(2, 6_000), payout(2, 6_000)], BatchFeePolicy::Proportional, FeeSelection::default());
+        assert!(matches!(above_approval_threshold, Err(AccountError::ApprovalRequired)));
+        let unknown_account = new_head_of_house.spend_bitcoin_batch(&[payout(4, 5_000)], BatchFeePolicy::Proportional, FeeSelection::default());
+        assert!(matches!(unknown_account, Err(AccountError::AccountDoesNotExist(_))));
+    }
+
+    #[test]
+    fn on_chain_transfer_is_counted_as_transfered_once_confirmed(){
+        set_up();
+        let (_mock_children, mut new_head_of_house) = set_up_user_with_no_bitcoin_and_one_child();
+        let master_address = new_head_of_house.master_account.generate_new_address().unwrap();
+        aw!(mine_a_block(&master_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.master_account.sync_wallet().unwrap();
+
+        let transfer_transaction = new_head_of_house.transfer_bitcoin_from_master_to_child_on_chain(convert_float_to_satoshis(0.3), 2, FeeSelection::SatPerVb(2.0)).unwrap();
+        let fee = transfer_transaction.fee.unwrap();
+
+        // until the transfer is confirmed neither the master account nor the child counts it as transfered
+        new_head_of_house.settle_pending_on_chain_transfers().unwrap();
+        assert_eq!(new_head_of_house.master_account.amount_transfered_to_children, 0);
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().bitcoin_transfered_from_master, 0);
+        assert_eq!(new_head_of_house.get_account_balance_utxo_amount_plus_transfer_balance(2).unwrap(), 0);
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().pending_on_chain_transfers[0].outpoint.txid, transfer_transaction.txid);
+        assert!(matches!(new_head_of_house.transfer_bitcoin_from_master_to_child(convert_float_to_satoshis(0.8), 2), Err(AccountError::InsufficientAccount)));
+
+        aw!(mine_a_block("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"));
+        sleep_while_block_being_mined();
+        new_head_of_house.settle_pending_on_chain_transfers().unwrap();
+
+        let master_balance = convert_float_to_satoshis(0.7) - fee;
+        let account = new_head_of_house.get_account_by_id(2).unwrap();
+        assert!(account.pending_on_chain_transfers.is_empty());
+        assert_eq!(account.bitcoin_transfered_from_master, convert_float_to_satoshis(0.3));
+        assert_eq!(new_head_of_house.master_account.amount_transfered_to_children, convert_float_to_satoshis(0.3));
+        assert_eq!(new_head_of_house.get_account_balance_utxo_amount_plus_transfer_balance(2).unwrap(), convert_float_to_satoshis(0.3));
+        assert_eq!(new_head_of_house.master_account.get_bitcoin_total_minus_transfers_to_children().unwrap(), master_balance);
+
+        // the child's spend is taken from the bitcoin it was transfered, the master account's balance is not touched
+        let spend_result = new_head_of_house.spend_bitcoin(2, convert_float_to_satoshis(0.1), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+        test_result_type_is_not_err(spend_result);
+        aw!(mine_a_block("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"));
+        sleep_while_block_being_mined();
+
+        let spend_fee = new_head_of_house.get_account_transaction_history(2).unwrap().last().unwrap().fee.unwrap();
+        assert_eq!(new_head_of_house.get_account_balance_utxo_amount_plus_transfer_balance(2).unwrap(), convert_float_to_satoshis(0.2) - spend_fee);
+        assert_eq!(new_head_of_house.master_account.get_bitcoin_total_minus_transfers_to_children().unwrap(), master_balance);
+    }
+
+    #[test]
+    fn on_chain_transfer_is_checked_before_anything_is_sent(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Receive]);
+
+        let to_unknown_account = new_head_of_house.transfer_bitcoin_from_master_to_child_on_chain(5_000, 3, FeeSelection::default());
+        assert!(matches!(to_unknown_account, Err(AccountError::AccountDoesNotExist(_))));
+        let to_head_of_the_house = new_head_of_house.transfer_bitcoin_from_master_to_child_on_chain(5_000, 1, FeeSelection::default());
+        assert!(matches!(to_head_of_the_house, Err(AccountError::Default("Can not transfer to the master account"))));
+        let below_minimum_fee_rate = new_head_of_house.transfer_bitcoin_from_master_to_child_on_chain(5_000, 2, FeeSelection::SatPerVb(0.5));
+        assert!(matches!(below_minimum_fee_rate, Err(AccountError::FeeRateTooLow)));
+        assert!(new_head_of_house.get_account_by_id(2).unwrap().pending_on_chain_transfers.is_empty());
+    }
+
+    #[test]
+    fn bumped_child_spend_replaces_the_pending_transaction_and_charges_the_child(){
+        set_up();
+        let (_mock_children, mut new_head_of_house) = set_up_user_with_no_bitcoin_and_one_child();
+        let child_address = new_head_of_house.get_new_address(2).unwrap();
+        aw!(mine_a_block(&child_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.master_account.sync_wallet().unwrap();
+
+        let spend_result = new_head_of_house.spend_bitcoin(2, convert_float_to_satoshis(0.5), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+        test_result_type_is_not_err(spend_result);
+        let original = new_head_of_house.get_account_by_id(2).unwrap().pending_transactions[0].clone();
+
+        let replacement = new_head_of_house.bump_fee(original.txid, FeeSelection::SatPerVb(5.0)).unwrap();
+
+        assert!(replacement.fee.unwrap() > original.fee.unwrap());
+        let child_pending_txids: Vec<Txid> = new_head_of_house.get_account_by_id(2).unwrap().pending_transactions.iter().map(|transaction| transaction.txid).collect();
+        let master_pending_txids: Vec<Txid> = new_head_of_house.master_account.pending_transactions.iter().map(|transaction| transaction.txid).collect();
+        assert_eq!(child_pending_txids, vec![replacement.txid]);
+        assert!(master_pending_txids.contains(&replacement.txid) && !master_pending_txids.contains(&original.txid));
+        // the extra fee comes out of the child's change
+        assert_eq!(new_head_of_house.get_account_balance_without_transfered_amount(2).unwrap(), convert_float_to_satoshis(0.5) - replacement.fee.unwrap());
+        assert!(matches!(new_head_of_house.bump_fee(original.txid, FeeSelection::SatPerVb(10.0)), Err(AccountError::TransactionNotPending)));
+    }
+
+    #[test]
+    fn replacement_rejected_by_the_electrum_server_leaves_the_ledger_unchanged(){
+        set_up();
+        let (_mock_children, mut new_head_of_house) = set_up_user_with_no_bitcoin_and_one_child();
+        let master_address = new_head_of_house.master_account.generate_new_address().unwrap();
+        aw!(mine_a_block(&master_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.master_account.sync_wallet().unwrap();
+        new_head_of_house.transfer_bitcoin_from_master_to_child(convert_float_to_satoshis(1.0), 2).unwrap();
+
+        let spend_result = new_head_of_house.spend_bitcoin(2, convert_float_to_satoshis(0.5), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+        test_result_type_is_not_err(spend_result);
+        let original = new_head_of_house.get_account_by_id(2).unwrap().pending_transactions[0].clone();
+        let transfered_after_spend = new_head_of_house.get_account_by_id(2).unwrap().bitcoin_transfered_from_master;
+
+        // a higher fee rate, but the extra fee does not pay for the replacement's own size at the minimum relay fee rate
+        let bump_result = new_head_of_house.bump_fee(original.txid, FeeSelection::SatPerVb(1.5));
+
+        assert!(matches!(bump_result, Err(AccountError::Default(_))));
+        let account = new_head_of_house.get_account_by_id(2).unwrap();
+        assert_eq!(account.pending_transactions.iter().map(|transaction| transaction.txid).collect::<Vec<Txid>>(), vec![original.txid]);
+        assert_eq!(account.bitcoin_transfered_from_master, transfered_after_spend);
+        assert_eq!(new_head_of_house.master_account.amount_transfered_to_children, transfered_after_spend);
+        let master_pending_txids: Vec<Txid> = new_head_of_house.master_account.pending_transactions.iter().map(|transaction| transaction.txid).collect();
+        assert_eq!(master_pending_txids, vec![original.txid]);
+    }
+
+    #[test]
+    fn bump_fee_of_a_transaction_which_is_not_pending_returns_transaction_not_pending(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+
+        let bump_result = new_head_of_house.bump_fee(Txid::from_slice(&[1; 32]).unwrap(), FeeSelection::SatPerVb(5.0));
+
+        assert!(matches!(bump_result, Err(AccountError::TransactionNotPending)));
+    }
+
+    #[test]
+    fn cancelled_child_spend_restores_the_transfered_bitcoin_once_confirmed(){
+        set_up();
+        let (_mock_children, mut new_head_of_house) = set_up_user_with_no_bitcoin_and_one_child();
+        let master_address = new_head_of_house.master_account.generate_new_address().unwrap();
+        aw!(mine_a_block(&master_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.master_account.sync_wallet().unwrap();
+        new_head_of_house.transfer_bitcoin_from_master_to_child(convert_float_to_satoshis(1.0), 2).unwrap();
+
+        // the child has no coins of its own, so the spend is paid with the master account's coins
+        let spend_result = new_head_of_house.spend_bitcoin(2, convert_float_to_satoshis(0.5), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+        test_result_type_is_not_err(spend_result);
+        let original = new_head_of_house.get_account_by_id(2).unwrap().pending_transactions[0].clone();
+        let transfered_after_spend = new_head_of_house.get_account_by_id(2).unwrap().bitcoin_transfered_from_master;
+
+        let replacement = new_head_of_house.cancel_transaction(original.txid, FeeSelection::SatPerVb(5.0)).unwrap();
+
+        assert!(replacement.fee.unwrap() > original.fee.unwrap());
+        let master_pending_txids: Vec<Txid> = new_head_of_house.master_account.pending_transactions.iter().map(|transaction| transaction.txid).collect();
+        assert!(master_pending_txids.contains(&replacement.txid) && !master_pending_txids.contains(&original.txid));
+        // nothing is given back until the replacement is confirmed
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().bitcoin_transfered_from_master, transfered_after_spend);
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().pending_cancellations[0].txid, replacement.txid);
+
+        aw!(mine_a_block("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"));
+        sleep_while_block_being_mined();
+        assert!(new_head_of_house.get_account_pending_transactions(2).unwrap().is_empty());
+
+        // the child gets back everything the spend took from its transfered bitcoin except the cancellation's fee
+        let account = new_head_of_house.get_account_by_id(2).unwrap();
+        assert!(account.pending_cancellations.is_empty());
+        assert_eq!(account.bitcoin_transfered_from_master, convert_float_to_satoshis(1.0) - replacement.fee.unwrap());
+        assert_eq!(new_head_of_house.master_account.amount_transfered_to_children, convert_float_to_satoshis(1.0) - replacement.fee.unwrap());
+    }
+
+    #[test]
+    fn cancel_of_a_transaction_which_is_not_pending_returns_transaction_not_pending(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+
+        let cancel_result = new_head_of_house.cancel_transaction(Txid::from_slice(&[1; 32]).unwrap(), FeeSelection::SatPerVb(5.0));
+
+        assert!(matches!(cancel_result, Err(AccountError::TransactionNotPending)));
+    }
+
+    #[test]
+    fn accelerating_a_childs_low_fee_payment_raises_the_package_fee_rate(){
+        set_up();
+        let (_mock_children, mut new_head_of_house) = set_up_user_with_no_bitcoin_and_one_child();
+        let child_address = new_head_of_house.get_new_address(2).unwrap();
+        // a grandparent's wallet sends the child a payment at 1 sat/vB, which is not mined
+        let mut grandparent = MasterAccount::new(get_random_mnenomic_words(), Network::Regtest).unwrap();
+        attach_wallet_to_regtest_electrum_server(&mut grandparent);
+        aw!(mine_a_block(&grandparent.generate_new_address().unwrap().to_string()));
+        sleep_while_block_being_mined();
+        let payment = grandparent.spend_bitcoin(convert_float_to_satoshis(0.5), &child_address.to_string(), 1.0).unwrap();
+        let payment_transaction = match payment {
+            SpendOutcome::Broadcast(payment) => payment.transaction.unwrap(),
+            SpendOutcome::AwaitingSignature(_, _) => panic!("expected the grandparent's payment to be broadcast"),
+        };
+        new_head_of_house.master_account.sync_wallet().unwrap();
+        assert_eq!(new_head_of_house.get_unconfirmed_incoming_utxos(2).unwrap().len(), 1);
+
+        let cpfp_transaction = new_head_of_house.accelerate_incoming_payments(2, FeeSelection::SatPerVb(10.0)).unwrap();
+
+        let package_fee = grandparent.get_transaction(&payment_transaction.txid(), false).unwrap().unwrap().fee.unwrap() + cpfp_transaction.fee.unwrap();
+        let package_vsize = payment_transaction.vsize() + cpfp_transaction.transaction.as_ref().unwrap().vsize();
+        assert!(package_fee >= 10 * package_vsize as u64);
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().pending_transactions[0].txid, cpfp_transaction.txid);
+        assert_eq!(new_head_of_house.get_account_balance_without_transfered_amount(2).unwrap(), convert_float_to_satoshis(0.5) - cpfp_transaction.fee.unwrap());
+        assert!(matches!(new_head_of_house.accelerate_incoming_payments(2, FeeSelection::SatPerVb(10.0)), Err(AccountError::NoUnconfirmedPayments)));
+    }
+
+    #[test]
+    fn accelerate_without_unconfirmed_payments_returns_no_unconfirmed_payments(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Receive]);
+
+        let accelerate_result = new_head_of_house.accelerate_incoming_payments(2, FeeSelection::SatPerVb(10.0));
+
+        assert!(matches!(accelerate_result, Err(AccountError::NoUnconfirmedPayments)));
+        assert!(matches!(new_head_of_house.accelerate_incoming_payments(3, FeeSelection::SatPerVb(10.0)), Err(AccountError::AccountDoesNotExist(_))));
+    }
+
+    #[test]
+    fn spend_bitcoin_over_spending_limit_returns_spending_limit_exceeded(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        new_head_of_house.create_new_user(&mut mock_children, 2, String::from("one"), vec![BitcoinPermissions::Send]);
+        new_head_of_house.set_spending_limits(2, SpendingLimits { max_per_transaction: None, daily: Some(10000), weekly: None, monthly: None }).unwrap();
+        new_head_of_house.get_mut_account_by_id(2).unwrap().record_spend(8000, get_current_timestamp());
+
+        let spend_result = new_head_of_house.spend_bitcoin(2, 5000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+
+        match spend_result {
+            Err(AccountError::SpendingLimitExceeded(period, tally)) => {
+                assert_eq!(period, SpendingPeriod::Daily);
+                assert_eq!(tally, SpendTally { daily: 8000, weekly: 8000, monthly: 8000 });
+            },
+            _ => panic!("expected the daily spending limit to be exceeded"),
+        }
+    }
+
+    #[test]
+    fn tick_allowances_queues_allowance_until_master_can_cover_it(){
+        set_up();
+        let (_mock_children, mut new_head_of_house ) = set_up_user_with_no_bitcoin_and_one_child();
+        assert!(matches!(new_head_of_house.add_allowance(3, 100000000, AllowanceInterval::Weekly, 0, InsufficientFundsPolicy::Queue), Err(AccountError::AccountDoesNotExist(_))));
+        new_head_of_house.add_allowance(2, 100000000, AllowanceInterval::Weekly, 0, InsufficientFundsPolicy::Queue).unwrap();
+
+        // the master account has no bitcoin yet
+        let handled = new_head_of_house.tick_allowances(60);
+        assert_eq!(handled[0].outcome, AllowanceOutcome::Queued);
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().bitcoin_transfered_from_master, 0);
+
+        // add one bitcoin to master account
+        aw!(mine_a_block(&new_head_of_house.master_account.generate_new_address().unwrap().to_string()));
+        sleep_while_block_being_mined();
+
+        let handled = new_head_of_house.tick_allowances(120);
+        assert_eq!(handled.len(), 1);
+        assert_eq!(handled[0].outcome, AllowanceOutcome::Applied);
+        assert_eq!(new_head_of_house.get_account_by_id(2).unwrap().bitcoin_transfered_from_master, 100000000);
+        assert_eq!(new_head_of_house.allowance_scheduler.history.len(), 2);
+    }
+
+    #[test]
+    fn spend_above_approval_threshold_is_held_until_approved(){
+        set_up();
+        let  (mut new_head_of_house, _children) = set_up_random_user_with_two_bitcoin();
+        let default_child_address = new_head_of_house.get_new_address(1).unwrap();
+        aw!(mine_a_block(&default_child_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.set_approval_threshold(1, Some(convert_float_to_satoshis(0.1))).unwrap();
+
+        let spend_result = new_head_of_house.spend_bitcoin(1, convert_float_to_satoshis(0.5), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
+        assert_eq!(spend_result.unwrap(), "AWAITING_APPROVAL");
+
+        let proposal = new_head_of_house.get_spend_proposals()[0].clone();
+        let balance = new_head_of_house.get_account_balance_utxo_amount_plus_transfer_balance(1).unwrap();
+        assert_eq!(new_head_of_house.get_account_held_amount(1), convert_float_to_satoshis(0.5) + proposal.fee);
+        assert_eq!(new_head_of_house.get_account_available_balance(1).unwrap(), balance - proposal.held_amount());
+        assert_eq!(new_head_of_house.master_account.pending_transactions.len(), 0);
+
+        let approve_result = new_head_of_house.approve_spend_proposal(proposal.proposal_id);
+        assert_eq!(approve_result.unwrap(), "PENDING");
+        assert!(new_head_of_house.get_spend_proposals().is_empty());
+        assert_eq!(new_head_of_house.get_account_by_id(1).unwrap().pending_transactions.len(), 1);
+    }
+
+    #[test]
+    fn rejecting_spend_proposal_releases_held_amount(){
+        set_up();
+        let  (mut new_head_of_house, _children) = set_up_random_user_with_two_bitcoin();
+        let default_child_address = new_head_of_house.get_new_address(1).unwrap();
+        aw!(mine_a_block(&default_child_address.to_string()));
+        sleep_while_block_being_mined();
+        new_head_of_house.set_approval_threshold(1, Some(0)).unwrap();
+
+        new_head_of_house.spend_bitcoin(1, convert_float_to_satoshis(0.5), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20").unwrap();
+        let proposal_id = new_head_of_house.get_spend_proposals()[0].proposal_id;
+        new_head_of_house.reject_spend_proposal(proposal_id).unwrap();
+
+        assert_eq!(new_head_of_house.get_account_held_amount(1), 0);
+        assert!(matches!(new_head_of_house.approve_spend_proposal(proposal_id), Err(AccountError::SpendProposalDoesNotExist)));
+    }
+
+    #[test]
+    fn spend_signed_on_another_device_is_imported_then_broadcast(){
+        set_up();
+        let  (mut new_head_of_house, _children) = set_up_random_user_with_two_bitcoin();
+        let default_child_address = new_head_of_house.get_new_address(1).unwrap();
+        aw!(mine_a_block(&default_child_address.to_string()));
+        sleep_while_block_being_mined();
+
+        let proposal_id = new_head_of_house.build_unsigned_spend(1, convert_float_to_satoshis(0.5), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", FeeSelection::default()).unwrap();
+        let mut exported_psbt = new_head_of_house.get_spend_proposal(proposal_id).unwrap().psbt.clone();
+        assert!(matches!(new_head_of_house.broadcast_spend_proposal(proposal_id), Err(AccountError::UnsignedTransaction)));
+
+        // the master account stands in for the other device
+        new_head_of_house.master_account.sign_transaction(&mut exported_psbt).unwrap();
+        assert!(new_head_of_house.import_signed_psbt(proposal_id, exported_psbt).unwrap());
+        assert_eq!(new_head_of_house.broadcast_spend_proposal(proposal_id).unwrap(), "PENDING");
+        assert!(new_head_of_house.get_spend_proposals().is_empty());
+        assert_eq!(new_head_of_house.get_account_by_id(1).unwrap().pending_transactions.len(), 1);
+    }
+
+    #[test]
+    fn psbt_of_another_transaction_is_not_imported_and_unsigned_proposal_is_not_broadcast(){
+        set_up();
+        let mut mock_children = Children::new();
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, None, Network::Regtest).unwrap();
+        let unsigned_psbt = |vout| {
+            let previous_output = OutPoint::new(Txid::from_slice(&[1; 32]).unwrap(), vout);
+            let unsigned_tx = Transaction { version: 2, lock_time: 0, input: vec![TxIn { previous_output, ..Default::default() }], output: vec![] };
+            PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).unwrap()
+        };
+        let psbt = unsigned_psbt(0);
+        let transaction_details = TransactionDetails { txid: psbt.unsigned_tx.txid(), transaction: None, received: 0, sent: 10_000, fee: Some(200), confirmation_time: None };
+        let proposal_id = new_head_of_house.spend_proposals.add(1, 9_800, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", 0, psbt, transaction_details);
+
+        assert!(matches!(new_head_of_house.import_signed_psbt(proposal_id, unsigned_psbt(1)), Err(AccountError::PsbtMismatch)));
+        assert!(matches!(new_head_of_house.broadcast_spend_proposal(proposal_id), Err(AccountError::UnsignedTransaction)));
+        assert_eq!(new_head_of_house.get_spend_proposal(proposal_id).unwrap().psbt, unsigned_psbt(0));
+        assert!(matches!(new_head_of_house.import_signed_psbt(proposal_id + 1, unsigned_psbt(0)), Err(AccountError::SpendProposalDoesNotExist)));
+    }
+
     #[test]
     fn test_spend_bitcoin_unsuccess_from_head_of_house_child_because_insufficient_funds(){
         set_up();
         let  (mut new_head_of_house, children) = set_up_random_user_with_two_bitcoin();
         let deafult_child = children.get_child_by_id(1).unwrap();
 
-        let insuffiecient_funds_error = deafult_child.spend_bitcoin(&mut new_head_of_house,convert_float_to_satoshis(3.0), "tb1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6whp7ax");
+        let insuffiecient_funds_error = deafult_child.spend_bitcoin(&mut new_head_of_house,convert_float_to_satoshis(3.0), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20");
         match insuffiecient_funds_error {
             Err(AccountError::InsufficientAccount) => assert_eq!(true, true), // this is the error it should be 
             _ => assert_eq!(false, true), // if it is any other type of error it should be a failed test
@@ -442,6 +2431,16 @@ mod tests {
         assert_eq!(master_account_total, 100000000);
     }
 
+    #[test]
+    fn test_spend_bitcoin_to_address_from_another_network_is_rejected(){
+        set_up();
+        let (mock_children, mut new_head_of_house ) = set_up_user_with_no_bitcoin_and_one_child();
+        let second_child = mock_children.get_child_by_id(2).unwrap();
+
+        let testnet_spend_result = second_child.spend_bitcoin(&mut new_head_of_house, convert_float_to_satoshis(0.1), "tb1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6whp7ax");
+        assert!(matches!(testnet_spend_result, Err(AccountError::InvalidAddress)));
+    }
+
     #[test]
     fn test_child_adding_new_address_adds_to_master_account_then_childs_account(){
         set_up();
@@ -470,6 +2469,50 @@ mod tests {
 
     }
 
+    #[test]
+    fn child_addresses_come_from_a_sub_account_which_can_be_rediscovered_from_the_seed(){
+        set_up();
+        let mut children = Children::new();
+        let mut head_of_house = HeadOfTheHouse::new(&mut children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+        let mut restored_children = Children::new();
+        let mut restored_head_of_house = HeadOfTheHouse::new(&mut restored_children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+        head_of_house.create_new_user(&mut children, 2, String::from("user_2"), vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+        restored_head_of_house.create_new_user(&mut restored_children, 2, String::from("user_2"), vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+
+        let child_address = head_of_house.get_new_address(2).unwrap();
+
+        assert_eq!(head_of_house.get_account_by_id(1).unwrap().account_index, None);
+        assert_eq!(head_of_house.get_account_by_id(2).unwrap().account_index, Some(2));
+        assert_eq!(restored_head_of_house.get_new_address(2).unwrap(), child_address);
+        assert!(head_of_house.master_account.sub_account_wallets.get(&2).unwrap().is_mine(&child_address.script_pubkey()).unwrap());
+        assert!(!head_of_house.master_account.wallet.is_mine(&child_address.script_pubkey()).unwrap());
+    }
+
+    #[test]
+    fn restore_accounts_finds_a_childs_coins_from_the_seed_alone(){
+        set_up();
+        let mnemonic_words = get_random_mnenomic_words();
+        let mut children = Children::new();
+        let mut head_of_house = HeadOfTheHouse::new(&mut children, mnemonic_words.clone(), Network::Regtest).unwrap();
+        head_of_house.create_new_user(&mut children, 2, String::from("user_2"), vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+        let child_address = head_of_house.get_new_address(2).unwrap();
+        aw!(mine_a_block(&child_address.to_string()));
+        sleep_while_block_being_mined();
+
+        let mut restored_children = Children::new();
+        let mut restored_head_of_house = HeadOfTheHouse::new(&mut restored_children, mnemonic_words, Network::Regtest).unwrap();
+        let restored_accounts = restored_head_of_house.restore_accounts(&mut restored_children, &[], None, DEFAULT_STOP_GAP).unwrap();
+
+        assert_eq!(restored_accounts.len(), 1);
+        assert!(restored_accounts[0].discovered);
+        assert_eq!(restored_accounts[0].account_id, 2);
+        assert_eq!(restored_accounts[0].bitcoin_amount, convert_float_to_satoshis(1.0));
+        assert_eq!(restored_head_of_house.get_account_by_id(2).unwrap().addresses, vec![child_address]);
+        assert_eq!(restored_children.get_child_by_id(2).unwrap().account_name, String::from("child_2"));
+        // the next address is not one which was already handed out
+        assert_ne!(restored_head_of_house.get_new_address(2).unwrap(), restored_head_of_house.get_account_by_id(2).unwrap().addresses[0]);
+    }
+
     #[test]
     fn test_get_account_balance_returns_current_account_amount(){
         set_up();
@@ -651,7 +2694,7 @@ mod tests {
 
     fn set_up_user_with_two_bitcoin(mnemonic_words: Option<String>) -> (HeadOfTheHouse, Children){
         let mut mock_children = Children::new();
-        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, mnemonic_words).unwrap();        
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, mnemonic_words, Network::Regtest).unwrap();        
         let default_acconut = new_head_of_house.get_mut_account_by_id(1).unwrap();
         default_acconut.bitcoin_amount = convert_float_to_satoshis(2.0);
 
@@ -668,7 +2711,7 @@ mod tests {
     fn set_up_user_with_no_bitcoin_and_one_child()-> (Children, HeadOfTheHouse){
         let mut mock_children = Children::new();
         let mnemonic_words = get_random_mnenomic_words();
-        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, mnemonic_words).unwrap();        
+        let mut new_head_of_house = HeadOfTheHouse::new(&mut mock_children, mnemonic_words, Network::Regtest).unwrap();        
         new_head_of_house.get_mut_account_by_id(1).unwrap();
         attach_wallet_to_regtest_electrum_server(&mut new_head_of_house.master_account);
 
@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 /// BDK used a u64 as its spending amount, which represent satoshis.
 /// currently this app uses a f64 and therefore must convert values to be interoporable
 /// with BDK.
@@ -7,3 +9,7 @@ pub fn convert_float_to_satoshis(amount:f64) -> u64{
     satoshis
 }
 
+/// Get the current unix timestamp in seconds.
+pub fn get_current_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::EnvFilter;
+
+/// The environment variable which chooses what is logged, using the `tracing_subscriber::EnvFilter` syntax,
+/// for example `family_wallet::master_account=debug,audit=info`.
+pub const LOG_FILTER_VARIABLE: &str = "family_wallet_log";
+
+/// What is logged when the `family_wallet_log` environment variable is not set.
+///
+/// Each module logs under its own target, `family_wallet::<module>`. Spends and transfers are also recorded
+/// in spans under the `audit` target, so they can be kept while everything else is turned off with `warn,audit=info`.
+pub const DEFAULT_LOG_FILTER: &str = "warn,family_wallet=info,audit=info";
+
+/// Start writing logs to stderr, leaving stdout for the output of the command line interface.
+///
+/// Logs from the `log` crate, which bdk uses, are written as well. Calling this more than once has no effect.
+pub fn init_logging(){
+    let env_filter = EnvFilter::try_from_env(LOG_FILTER_VARIABLE).unwrap_or_else(|_e| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+/// A secret which can be passed around, or put in a log field, without its value being shown.
+///
+/// Both `Display` and `Debug` print `[REDACTED]`, use `Redacted::expose` where the real value is needed.
+/// It serializes as the secret itself, so only wrap values which are serialized somewhere safe, such as an encrypted backup.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Redacted<T>(T);
+
+impl<T> Redacted<T> {
+    pub fn new(secret: T) -> Redacted<T> {
+        Redacted(secret)
+    }
+
+    /// Get the secret, it must not be logged.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
+
+impl<T> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_secret_is_not_shown_when_formatted(){
+        let mnemonic_words = Redacted::new(String::from("abandon ability able"));
+
+        assert_eq!(format!("{}", mnemonic_words), "[REDACTED]");
+        assert_eq!(format!("{:?}", mnemonic_words), "[REDACTED]");
+        assert_eq!(mnemonic_words.expose(), "abandon ability able");
+    }
+}
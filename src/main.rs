@@ -1,4 +1,3 @@
-
 pub mod head_of_the_house;
 pub mod child;
 pub mod permissions;
@@ -9,21 +8,44 @@ pub mod helpers;
 pub mod testing_helpers;
 pub mod env_variables;
 pub mod custom_errors;
+pub mod storage;
+pub mod cli;
+pub mod server;
+pub mod spending_limits;
+pub mod allowances;
+pub mod spend_proposals;
+pub mod transfer_requests;
+pub mod address_book;
+pub mod fees;
+pub mod restore;
+pub mod backup;
+pub mod logging;
+pub mod encryption;
+pub mod seed_store;
+pub mod offline_signing;
+pub mod multisig;
+pub mod batch_payouts;
 
 use head_of_the_house::HeadOfTheHouse;
-// use permissions::BitcoinPermissions;
-use crate::testing_helpers::mine_a_block;
-use futures::executor::block_on;
-use tokio;
+use clap::Parser;
+use cli::Cli;
 use env_variables::{set_env_variables};
+use logging::init_logging;
 
 #[tokio::main]
 async fn main() {
-    set_env_variables()
-    // block_on( mine_a_block("bcrt1q2ltw5646zcdxcj7hvv47mklqy8la6ta83p6egw"));
-    // let mut master = HeadOfTheHouse::new();
-    // master.master_account.sync_wallet_with_electrum_server(None) // sync with blockstream by default
-    // master.add_child(2, String::from("my new child"),  vec![BitcoinPermissions::Send, BitcoinPermissions::Receive] );
-    
-    // println!("Hello, world! {:?}", master.children.get(0));
+    if let Err(error) = set_env_variables() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+    init_logging();
+    let cli = Cli::parse();
+
+    match cli::run(cli).await {
+        Ok(output) => println!("{}", output),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
 }
@@ -1,21 +1,85 @@
-use bdk::{miniscript, Wallet, KeychainKind, SyncOptions, SignOptions};
-use bdk::database::MemoryDatabase;
+use bdk::{miniscript, Wallet, KeychainKind, LocalUtxo, SyncOptions, SignOptions};
+use bdk::database::{AnyDatabase, Database, MemoryDatabase};
+use bdk::miniscript::descriptor::DescriptorTrait;
+use bdk::sled;
 use bdk::wallet::AddressIndex::New;
 use bdk::wallet::AddressInfo;
-use bdk::bitcoin::{Address, Network, Txid};
+use bdk::bitcoin::{Address, Network, OutPoint, Script, Transaction, TxOut, Txid};
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint};
+use bdk::bitcoin::util::psbt::{self, PartiallySignedTransaction};
 use bdk::FeeRate;
 use bdk::keys::{DerivableKey, GeneratableKey, GeneratedKey, ExtendedKey, bip39::{Mnemonic, WordCount, Language}};
-use bdk::template::Bip84;
+use bdk::miniscript::descriptor::{DescriptorXKey, Wildcard};
+use bdk::wallet::signer::{SignerContext, SignerWrapper, TransactionSigner};
+use std::collections::BTreeMap;
 use std::env;
+use std::iter;
 use std::str::FromStr;
-use bdk::electrum_client::Client;
-use bdk::blockchain::ElectrumBlockchain;
-use bdk::blockchain::Blockchain;
+use std::time::{Duration, Instant};
+use bdk::blockchain::{ConfigurableBlockchain, ElectrumBlockchain, ElectrumBlockchainConfig};
+use bdk::blockchain::{Blockchain, GetTx};
 use bdk::TransactionDetails;
 use crate::helpers::{convert_float_to_satoshis};
 use crate::custom_errors::{WalletError, AccountError};
+use crate::fees::{FeeSelection, MINIMUM_SAT_PER_VB};
+use crate::restore::DEFAULT_STOP_GAP;
+use crate::multisig::MultisigPolicy;
+use crate::offline_signing::{is_finalized, signatures_needed};
+use crate::seed_store::{EncryptedSeed, WatchOnlyKeys, DEFAULT_RELOCK_TIMEOUT};
 use bdk::{Error};
+use tracing::{debug, info, warn};
 
+/// The sled tree holding the bdk wallet database of the `MasterAccount`'s own wallet.
+const WALLET_TREE_NAME: &str = "wallet";
+
+/// The root key of an unlocked `MasterAccount`, and when it locks itself again.
+struct SigningKey {
+    root_key: ExtendedPrivKey,
+    /// None if the `MasterAccount` was generated from its seed words and stays unlocked.
+    relock_at: Option<Instant>,
+}
+
+/// A utxo of a sub-account spent in a transaction built by the `MasterAccount`'s own wallet,
+/// with its psbt input and the weight its witness adds to the transaction.
+type ForeignUtxo = (OutPoint, psbt::Input, usize);
+
+/// What `MasterAccount::spend_bitcoin` did with a spend.
+#[derive(Debug)]
+pub enum SpendOutcome {
+    /// The spend was signed and broadcast.
+    Broadcast(TransactionDetails),
+    /// The spend was built but not signed, the PSBT needs to be signed by the external signer holding the root key.
+    AwaitingSignature(PartiallySignedTransaction, TransactionDetails),
+}
+
+/// Where the `MasterAccount` keeps the bdk databases of its wallets.
+#[derive(Clone)]
+pub enum WalletStore {
+    /// Keep every wallet in memory, it is lost once the `MasterAccount` is dropped.
+    Memory,
+    /// Keep every wallet in its own tree of a sled database.
+    Sled(sled::Db),
+}
+
+impl WalletStore {
+    /// Open the database of the `MasterAccount`'s own wallet, or of the sub-account at `account_index`.
+    /// 
+    /// # Errors
+    /// If the sled tree can not be opened return a `WalletError`.
+    fn open_database(&self, account_index: Option<u32>) -> Result<AnyDatabase, WalletError> {
+        match self {
+            WalletStore::Memory => Ok(AnyDatabase::Memory(MemoryDatabase::default())),
+            WalletStore::Sled(db) => {
+                let tree_name = match account_index {
+                    Some(account_index) => format!("{}_account_{}", WALLET_TREE_NAME, account_index),
+                    None => String::from(WALLET_TREE_NAME),
+                };
+                Ok(AnyDatabase::Sled(db.open_tree(tree_name)?))
+            },
+        }
+    }
+}
 
 /// A struct representing the MasterAccount, which controls all interactions with an actual bitcoin wallet.
 /// 
@@ -26,75 +90,494 @@ use bdk::{Error};
 /// As well the MasterAccount keeps track of two different sets of addresses, `all_addresses` which include the addresses given to other 
 /// `Account`s to use, and the addresses that are unique to the `MasterAccount`. The `account_addresses` are the addresses just for the 
 /// MasterAccount and are addresses to funds that other `Account`s do not have access to.
+/// 
+/// The `wallet` uses the first BIP84 account of the seed, `m/84'/coin'/0'`. A child can be given a sub-account instead,
+/// a wallet of its own on the BIP84 account `m/84'/coin'/n'`, so that its addresses and utxos can be found again from the seed alone.
+/// The sub-account wallets are kept in `sub_account_wallets` by their account index.
+/// 
+/// Every wallet watches its account from the account's xpub, the `watch_only_keys`, and holds no private keys.
+/// The root key is only kept while the MasterAccount is unlocked, to sign transactions and derive the keys of new sub-accounts.
+/// A MasterAccount generated from its seed words is unlocked, one generated from its `WatchOnlyKeys` is locked
+/// until it is unlocked with the password its seed was encrypted with, and locks itself again after the `relock_timeout`.
+/// One generated from the public descriptors of keys held by an external signer, such as a hardware wallet, never signs,
+/// its spends are returned as unsigned PSBTs for the external signer to sign.
+/// 
+/// A multisig vault's wallets are `wsh(sortedmulti(...))` wallets of the seed's BIP48 account keys, `m/48'/coin'/n'/2'`, and the
+/// keys of its cosigners, see `MultisigPolicy`. The MasterAccount signs with the seed's key only, and a spend needs the
+/// signatures of the cosigners as well, collected as PSBTs, before it can be broadcast.
 pub struct MasterAccount {
     pub bitcoin_amount: u64,
     pub all_addresses: Vec<AddressInfo>,
     pub account_addresses: Vec<AddressInfo>,
-    pub wallet: Wallet<MemoryDatabase>,
+    pub wallet: Wallet<AnyDatabase>,
+    pub sub_account_wallets: BTreeMap<u32, Wallet<AnyDatabase>>,
+    watch_only_keys: WatchOnlyKeys,
+    signing_key: Option<SigningKey>,
+    relock_timeout: Duration,
+    wallet_store: WalletStore,
     pub blockchain: Option<ElectrumBlockchain>,
     pub pending_transactions:Vec<TransactionDetails>,
-    pub amount_transfered_to_children: u64
+    pub amount_transfered_to_children: u64,
+    pub network: Network,
 }
 
 impl MasterAccount {
     /// Generate a new MasterAccount, also generate the associated wallet with this account from the 
-    /// passed in seed `words`.
+    /// passed in seed `words` on the bitcoin `network`.
     /// 
     /// # Errors
     /// If there is an error generating the wallet with the seed `words` then return an `AccountError`.
     /// This could happen if you try to use invalid seed words.
-    pub fn new(words:Option<String>) -> Result<MasterAccount, AccountError> {
-        let wallet = MasterAccount::generate_wallet(words).map_err(
+    pub fn new(words:Option<String>, network: Network) -> Result<MasterAccount, AccountError> {
+        MasterAccount::new_with_wallet_store(words, network, WalletStore::Memory)
+    }
+
+    /// Generate a new MasterAccount whose wallets are stored in `wallet_store`.
+    /// 
+    /// Passing a store that already holds wallet data, for example the sled database of a `HouseholdStorage`,
+    /// will resume the wallets from their stored addresses and transactions.
+    /// 
+    /// # Errors
+    /// If there is an error generating the wallet with the seed `words` then return an `AccountError`.
+    pub fn new_with_wallet_store(words:Option<String>, network: Network, wallet_store: WalletStore) -> Result<MasterAccount, AccountError> {
+        MasterAccount::new_with_bip39_passphrase(words, None, network, wallet_store)
+    }
+
+    /// Generate a new MasterAccount from the seed `words` extended with a BIP39 passphrase, whose wallets are stored in `wallet_store`.
+    /// 
+    /// The same words with a different passphrase, or none, generate a different wallet.
+    /// 
+    /// # Errors
+    /// If there is an error generating the wallet with the seed `words` then return an `AccountError`.
+    pub fn new_with_bip39_passphrase(words:Option<String>, bip39_passphrase: Option<String>, network: Network, wallet_store: WalletStore) -> Result<MasterAccount, AccountError> {
+        let root_key = MasterAccount::generate_root_key_with_bip39_passphrase(words, bip39_passphrase, network).map_err(
+            |_err| AccountError::InsufficientAccount
+        )?;
+        let watch_only_keys = MasterAccount::generate_watch_only_keys(root_key).map_err(|_err| AccountError::InsufficientAccount)?;
+        let mut master_account = MasterAccount::new_watch_only(watch_only_keys, network, wallet_store).map_err(
             |_err| AccountError::InsufficientAccount
         )?;
+        master_account.signing_key = Some(SigningKey { root_key, relock_at: None });
+        Ok(master_account)
+    }
+
+    /// Generate a MasterAccount for a multisig vault, from the seed `words` extended with a BIP39 passphrase and the keys of the
+    /// `multisig` policy's cosigners, whose wallets are stored in `wallet_store`.
+    /// 
+    /// The seed's key is one of the vault's keys, so the MasterAccount adds its own signature to every spend.
+    /// 
+    /// # Errors
+    /// If the seed `words` are invalid, or the seed is also one of the cosigners, return a `WalletError`.
+    /// If the `wallet_store` holds wallets created from different keys return a `WalletError`.
+    pub fn new_multisig(words:Option<String>, bip39_passphrase: Option<String>, multisig: MultisigPolicy, network: Network, wallet_store: WalletStore) -> Result<MasterAccount, WalletError> {
+        let root_key = MasterAccount::generate_root_key_with_bip39_passphrase(words, bip39_passphrase, network)?;
+        let watch_only_keys = MasterAccount::generate_multisig_watch_only_keys(root_key, multisig)?;
+        let mut master_account = MasterAccount::new_watch_only(watch_only_keys, network, wallet_store)?;
+        master_account.signing_key = Some(SigningKey { root_key, relock_at: None });
+        Ok(master_account)
+    }
+
+    /// Generate a locked MasterAccount which watches the accounts of `watch_only_keys`, whose wallets are stored in `wallet_store`.
+    /// 
+    /// If the `watch_only_keys` are of an external signer the MasterAccount can not be unlocked, see `WatchOnlyKeys::from_descriptors`.
+    /// 
+    /// # Errors
+    /// If the `wallet_store` holds wallets created from different keys return a `WalletError`.
+    pub fn new_watch_only(watch_only_keys: WatchOnlyKeys, network: Network, wallet_store: WalletStore) -> Result<MasterAccount, WalletError> {
+        let account_xpub = *watch_only_keys.account_xpubs.get(&0).ok_or(WalletError::KeyError)?;
+        let database = wallet_store.open_database(None)?;
+        let wallet = MasterAccount::generate_account_wallet(&watch_only_keys, account_xpub, network, 0, database)?;
 
         Ok(MasterAccount {
             bitcoin_amount: 0,
             all_addresses: Vec::new(),
             account_addresses: Vec::new(),
             wallet: wallet,
+            sub_account_wallets: BTreeMap::new(),
+            watch_only_keys,
+            signing_key: None,
+            relock_timeout: DEFAULT_RELOCK_TIMEOUT,
+            wallet_store,
             blockchain: None,
             pending_transactions: vec![],
             amount_transfered_to_children: 0,
+            network,
         })
     }
 
-    /// Generate a wallet from seed `words`.
+    /// Generate the root extended private key on the bitcoin `network` from seed `words`.
     /// 
-    /// # Panics
-    /// If the words are invalid panic the app. TODO allow for the ability to retry generating a wallet. 
-    pub fn generate_wallet(words:Option<String>)-> Result<Wallet<MemoryDatabase>, WalletError> {
+    /// # Errors
+    /// If the words are invalid return a `WalletError`.
+    pub fn generate_root_key(words:Option<String>, network: Network)-> Result<ExtendedPrivKey, WalletError> {
+        MasterAccount::generate_root_key_with_bip39_passphrase(words, None, network)
+    }
+
+    /// Generate the root extended private key on the bitcoin `network` from seed `words` extended with a BIP39 passphrase.
+    /// 
+    /// # Errors
+    /// If the words are invalid return a `WalletError`.
+    pub fn generate_root_key_with_bip39_passphrase(words:Option<String>, bip39_passphrase: Option<String>, network: Network)-> Result<ExtendedPrivKey, WalletError> {
         // if provided words, then use them to generate a wallet,
         // if not then generate your own randomly
+        // the words are a secret, they are never logged
         let mnemonic_words = match words {
             Some(w) => w,
             _ => {
-                let mnemonic: GeneratedKey<_, miniscript::Segwitv0> = Mnemonic::generate((WordCount::Words12, Language::English)).expect("mnemonic unable to be generated");
-                // Convert mnemonic to string
-                let mnemonic_words = mnemonic.to_string();
-                mnemonic_words
+                debug!("no mnemonic words were given, generating new ones");
+                MasterAccount::generate_mnemonic_words()
             },
         };
 
-        println!("my mnemonic words {}", mnemonic_words);
-
-        let network = Network::Regtest; // Or this can be Network::Bitcoin, Network::Signet or Network::Testnet
-
         // Parse a mnemonic
         let mnemonic  = Mnemonic::parse(&mnemonic_words)?;
         // Generate the extended key
-        let xkey: ExtendedKey = mnemonic.into_extended_key()?;
+        let xkey: ExtendedKey = (mnemonic, bip39_passphrase).into_extended_key()?;
         // Get xprv from the extended key
-        let xprv = xkey.into_xprv(network).ok_or(WalletError::KeyError)?;
+        xkey.into_xprv(network).ok_or(WalletError::KeyError)
+    }
 
-        let wallet = Wallet::new(
-            Bip84(xprv, KeychainKind::External),
-            Some(Bip84(xprv, KeychainKind::Internal)),
-            network,
-            MemoryDatabase::default(),
-        ).expect("Error instantiating wallet");
+    /// Return true if the seed `words` and BIP39 passphrase are the ones this `MasterAccount` was generated from, else return false.
+    pub fn is_generated_from(&self, words: &str, bip39_passphrase: Option<&str>) -> bool {
+        let secp = Secp256k1::new();
+        let account_derivation_path = match self.own_account_derivation_path(0) {
+            Ok(account_derivation_path) => account_derivation_path,
+            Err(_error) => return false,
+        };
+        MasterAccount::generate_root_key_with_bip39_passphrase(Some(String::from(words)), bip39_passphrase.map(String::from), self.network)
+            .and_then(|root_key| Ok((root_key.fingerprint(&secp), MasterAccount::derive_account_xpub(root_key, &account_derivation_path)?)))
+            .is_ok_and(|(fingerprint, account_xpub)| fingerprint == self.watch_only_keys.fingerprint
+                && Some(&account_xpub) == self.watch_only_keys.account_xpubs.get(&0))
+    }
+
+    /// Return true if the `MasterAccount`'s root key is held by an external signer, so it never signs its own transactions, else return false.
+    pub fn signs_externally(&self) -> bool {
+        self.watch_only_keys.external_signer
+    }
+
+    /// Return true if the sub-account at `account_index` can be opened, else return false.
+    /// 
+    /// A sub-account whose key is not known can be derived from the seed, unless the root key is held by an external signer.
+    /// A multisig vault's sub-account also needs every cosigner's key of the account.
+    pub fn can_open_sub_account(&self, account_index: u32) -> bool {
+        let is_own_key_known = !self.signs_externally() || self.watch_only_keys.account_xpubs.contains_key(&account_index);
+        let are_cosigner_keys_known = self.watch_only_keys.multisig.as_ref().is_none_or(|multisig| multisig.has_account(account_index));
+        is_own_key_known && are_cosigner_keys_known
+    }
+
+    /// Get the policy of the multisig vault, or None if the `MasterAccount` is a single signature wallet.
+    pub fn multisig_policy(&self) -> Option<&MultisigPolicy> {
+        self.watch_only_keys.multisig.as_ref()
+    }
+
+    /// Get the public keys the `MasterAccount`'s wallets watch their accounts with.
+    pub fn watch_only_keys(&self) -> &WatchOnlyKeys {
+        &self.watch_only_keys
+    }
+
+    /// Generate the `WatchOnlyKeys` of the `root_key`, holding the xpub of the `MasterAccount`'s own account.
+    /// 
+    /// # Errors
+    /// If the account's key can not be derived return a `WalletError`.
+    pub fn generate_watch_only_keys(root_key: ExtendedPrivKey) -> Result<WatchOnlyKeys, WalletError> {
+        let secp = Secp256k1::new();
+        let account_xpub = MasterAccount::derive_account_xpub(root_key, &MasterAccount::account_derivation_path(root_key.network, 0)?)?;
+        Ok(WatchOnlyKeys {
+            fingerprint: root_key.fingerprint(&secp),
+            account_xpubs: BTreeMap::from([(0, account_xpub)]),
+            external_signer: false,
+            multisig: None,
+        })
+    }
+
+    /// Generate the `WatchOnlyKeys` of a multisig vault, holding the xpub of the `root_key`'s BIP48 account of the vault's own wallet
+    /// and the `multisig` policy.
+    /// 
+    /// # Errors
+    /// If the `root_key` is also one of the cosigners, or the account's key can not be derived, return a `WalletError`.
+    pub fn generate_multisig_watch_only_keys(root_key: ExtendedPrivKey, multisig: MultisigPolicy) -> Result<WatchOnlyKeys, WalletError> {
+        let secp = Secp256k1::new();
+        let fingerprint = root_key.fingerprint(&secp);
+        if multisig.cosigners.iter().any(|cosigner| cosigner.fingerprint == fingerprint) {
+            return Err(WalletError::InvalidMultisigPolicy)
+        }
+        let account_xpub = MasterAccount::derive_account_xpub(root_key, &MasterAccount::multisig_account_derivation_path(root_key.network, 0)?)?;
+        Ok(WatchOnlyKeys {
+            fingerprint,
+            account_xpubs: BTreeMap::from([(0, account_xpub)]),
+            external_signer: false,
+            multisig: Some(multisig),
+        })
+    }
+
+    /// Derive the xpub of the account at `account_derivation_path` of the `root_key`.
+    /// 
+    /// # Errors
+    /// If the key can not be derived return a `WalletError`.
+    fn derive_account_xpub(root_key: ExtendedPrivKey, account_derivation_path: &DerivationPath) -> Result<ExtendedPubKey, WalletError> {
+        let secp = Secp256k1::new();
+        let account_key = root_key.derive_priv(&secp, account_derivation_path)?;
+        Ok(ExtendedPubKey::from_priv(&secp, &account_key))
+    }
+
+    /// The derivation path of the BIP84 account `m/84'/coin'/account_index'`.
+    /// 
+    /// The coin type is 0 on mainnet and 1 on every test network, as BIP44 specifies.
+    /// 
+    /// # Errors
+    /// If the account index is not below 2^31 return a `WalletError`.
+    pub fn account_derivation_path(network: Network, account_index: u32) -> Result<DerivationPath, WalletError> {
+        let coin_type = match network {
+            Network::Bitcoin => 0,
+            _ => 1,
+        };
+        Ok(DerivationPath::from(vec![
+            ChildNumber::from_hardened_idx(84)?,
+            ChildNumber::from_hardened_idx(coin_type)?,
+            ChildNumber::from_hardened_idx(account_index)?,
+        ]))
+    }
+
+    /// The derivation path of the BIP48 P2WSH account `m/48'/coin'/account_index'/2'`, the accounts of a multisig vault.
+    /// 
+    /// # Errors
+    /// If the account index is not below 2^31 return a `WalletError`.
+    pub fn multisig_account_derivation_path(network: Network, account_index: u32) -> Result<DerivationPath, WalletError> {
+        let coin_type = match network {
+            Network::Bitcoin => 0,
+            _ => 1,
+        };
+        Ok(DerivationPath::from(vec![
+            ChildNumber::from_hardened_idx(48)?,
+            ChildNumber::from_hardened_idx(coin_type)?,
+            ChildNumber::from_hardened_idx(account_index)?,
+            ChildNumber::from_hardened_idx(2)?,
+        ]))
+    }
+
+    /// The derivation path of the seed's own key of the account at `account_index`, the BIP48 P2WSH account of a multisig vault
+    /// or else the BIP84 account.
+    /// 
+    /// # Errors
+    /// If the account index is not below 2^31 return a `WalletError`.
+    fn own_account_derivation_path(&self, account_index: u32) -> Result<DerivationPath, WalletError> {
+        match self.watch_only_keys.multisig {
+            Some(_) => MasterAccount::multisig_account_derivation_path(self.network, account_index),
+            None => MasterAccount::account_derivation_path(self.network, account_index),
+        }
+    }
+
+    /// Generate the watch-only wallet of the account at `account_index` from the seed's `account_xpub` and the `watch_only_keys`,
+    /// a `wsh(sortedmulti(...))` wallet of a multisig vault or else a BIP84 wallet, storing its state in `database`.
+    /// 
+    /// # Errors
+    /// If a cosigner has not given its key of the account, or the `database` was created by a wallet with different keys, return a `WalletError`.
+    fn generate_account_wallet(watch_only_keys: &WatchOnlyKeys, account_xpub: ExtendedPubKey, network: Network, account_index: u32, database: AnyDatabase)-> Result<Wallet<AnyDatabase>, WalletError> {
+        match &watch_only_keys.multisig {
+            Some(multisig) => {
+                let external_descriptor = multisig.keychain_descriptor(watch_only_keys.fingerprint, account_xpub, network, account_index, 0)?;
+                let internal_descriptor = multisig.keychain_descriptor(watch_only_keys.fingerprint, account_xpub, network, account_index, 1)?;
+                Wallet::new(&external_descriptor, Some(&internal_descriptor), network, database).map_err(|_e| WalletError::KeyError)
+            },
+            None => MasterAccount::generate_watch_only_wallet(watch_only_keys.fingerprint, account_xpub, network, account_index, database),
+        }
+    }
+
+    /// Generate the watch-only wallet of the BIP84 account `m/84'/coin'/account_index'` from its `account_xpub`, storing its state in `database`.
+    /// 
+    /// The `fingerprint` of the root key is kept in the descriptors so the transactions the wallet builds say which key signs them.
+    /// 
+    /// # Errors
+    /// If the account index is not below 2^31, or the `database` was created by a wallet with different keys return a `WalletError`.
+    pub fn generate_watch_only_wallet(fingerprint: Fingerprint, account_xpub: ExtendedPubKey, network: Network, account_index: u32, database: AnyDatabase)-> Result<Wallet<AnyDatabase>, WalletError> {
+        let account_derivation_path = MasterAccount::account_derivation_path(network, account_index)?;
+        // the derivation path prints as m/84'/..., the key origin is written without the m
+        let key_origin = format!("{}{}", fingerprint, &account_derivation_path.to_string()[1..]);
+        let external_descriptor = format!("wpkh([{}]{}/0/*)", key_origin, account_xpub);
+        let internal_descriptor = format!("wpkh([{}]{}/1/*)", key_origin, account_xpub);
+        // a stored database only accepts the descriptors it was created with
+        Wallet::new(&external_descriptor, Some(&internal_descriptor), network, database).map_err(|_e| WalletError::KeyError)
+    }
+
+    /// Get the xpub of the account at `account_index`, deriving it if the `MasterAccount` is unlocked and has not opened the account before.
+    /// 
+    /// # Errors
+    /// If the account has never been opened and the `MasterAccount` is locked return a `WalletError`.
+    fn account_xpub(&mut self, account_index: u32) -> Result<ExtendedPubKey, WalletError> {
+        if let Some(account_xpub) = self.watch_only_keys.account_xpubs.get(&account_index) {
+            return Ok(*account_xpub)
+        }
+        let account_derivation_path = self.own_account_derivation_path(account_index)?;
+        let account_xpub = MasterAccount::derive_account_xpub(self.signing_root_key()?, &account_derivation_path)?;
+        self.watch_only_keys.account_xpubs.insert(account_index, account_xpub);
+        Ok(account_xpub)
+    }
+
+    /// Generate the watch-only wallet of the sub-account at `account_index`.
+    /// 
+    /// # Errors
+    /// If the sub-account's key is held by an external signer, or a cosigner, which has not given it return a `WalletError`.
+    /// If the sub-account's key is not known and the `MasterAccount` is locked, or its database can not be opened
+    /// or does not match its key, return a `WalletError`.
+    fn generate_sub_account_wallet(&mut self, account_index: u32) -> Result<Wallet<AnyDatabase>, WalletError> {
+        if !self.can_open_sub_account(account_index) {
+            return Err(WalletError::ExternalSigner)
+        }
+        let account_xpub = self.account_xpub(account_index)?;
+        let database = self.wallet_store.open_database(Some(account_index))?;
+        MasterAccount::generate_account_wallet(&self.watch_only_keys, account_xpub, self.network, account_index, database)
+    }
+
+    /// Open the wallet of the sub-account at `account_index`, generating it from the seed if it has not been opened before.
+    /// 
+    /// # Errors
+    /// If the sub-account has never been opened and the `MasterAccount` is locked return a `WalletError`.
+    /// If the wallet's database can not be opened or does not match the seed return a `WalletError`.
+    pub fn open_sub_account(&mut self, account_index: u32)-> Result<&Wallet<AnyDatabase>, WalletError> {
+        if !self.sub_account_wallets.contains_key(&account_index) {
+            let wallet = self.generate_sub_account_wallet(account_index)?;
+            self.sub_account_wallets.insert(account_index, wallet);
+        }
+        self.sub_account_wallets.get(&account_index).ok_or(WalletError::KeyError)
+    }
+
+    /// Unlock the `MasterAccount` with the `password` its seed was encrypted with, until the `relock_timeout` passes.
+    /// 
+    /// # Errors
+    /// If the root key is held by an external signer return a `WalletError`.
+    /// If the password is wrong return a `WalletError`.
+    /// If the seed does not generate this `MasterAccount`'s keys return a `WalletError`.
+    pub fn unlock(&mut self, encrypted_seed: &EncryptedSeed, password: &str) -> Result<(), WalletError> {
+        if self.signs_externally() {
+            return Err(WalletError::ExternalSigner)
+        }
+        let seed_secrets = encrypted_seed.decrypt(password)?;
+        let root_key = MasterAccount::generate_root_key_with_bip39_passphrase(
+            Some(seed_secrets.mnemonic_words.expose().clone()),
+            seed_secrets.bip39_passphrase.map(|bip39_passphrase| bip39_passphrase.expose().clone()),
+            self.network,
+        )?;
+        if MasterAccount::generate_watch_only_keys(root_key)?.fingerprint != self.watch_only_keys.fingerprint {
+            return Err(WalletError::KeyError)
+        }
+        self.signing_key = Some(SigningKey { root_key, relock_at: Some(Instant::now() + self.relock_timeout) });
+        info!(relock_timeout_secs = self.relock_timeout.as_secs(), "unlocked the wallet");
+        Ok(())
+    }
+
+    /// Lock the `MasterAccount`, dropping its root key until it is unlocked again.
+    pub fn lock(&mut self) {
+        if self.signing_key.take().is_some() {
+            info!("locked the wallet");
+        }
+    }
+
+    /// Set how long the `MasterAccount` stays unlocked after `unlock`, taking effect the next time it is unlocked.
+    pub fn set_relock_timeout(&mut self, relock_timeout: Duration) {
+        self.relock_timeout = relock_timeout;
+    }
+
+    /// Return true if the `MasterAccount` is locked, locking it if its relock timeout has passed, else return false.
+    pub fn is_locked(&mut self) -> bool {
+        let relock_timeout_passed = match &self.signing_key {
+            Some(SigningKey { relock_at: Some(relock_at), .. }) => Instant::now() >= *relock_at,
+            Some(SigningKey { relock_at: None, .. }) => false,
+            None => return true,
+        };
+        if relock_timeout_passed {
+            self.lock();
+        }
+        relock_timeout_passed
+    }
 
-        Ok(wallet)
+    /// Get the root key, to sign with or derive the key of a new sub-account.
+    /// 
+    /// # Errors
+    /// If the root key is held by an external signer, or the `MasterAccount` is locked, return a `WalletError`.
+    fn signing_root_key(&mut self) -> Result<ExtendedPrivKey, WalletError> {
+        if self.signs_externally() {
+            return Err(WalletError::ExternalSigner)
+        }
+        if self.is_locked() {
+            return Err(WalletError::WalletLocked)
+        }
+        self.signing_key.as_ref().map(|signing_key| signing_key.root_key).ok_or(WalletError::WalletLocked)
+    }
+
+    /// Look for a sub-account at `account_index` which has been used, opening it if it has.
+    /// 
+    /// The sub-account's wallet is synced, and kept open only if any transaction paid to or spent from it.
+    /// A sub-account whose key an external signer, or a cosigner, has not given can not have been used.
+    /// 
+    /// # Errors
+    /// If the wallet is not connected to an electrum server, or the sub-account can not be opened or synced, return a `WalletError`.
+    pub fn discover_sub_account(&mut self, account_index: u32)-> Result<bool, WalletError> {
+        if !self.can_open_sub_account(account_index) {
+            return Ok(false)
+        }
+        let was_open = self.sub_account_wallets.contains_key(&account_index);
+        let was_known = self.watch_only_keys.account_xpubs.contains_key(&account_index);
+        if self.blockchain.is_none() {
+            return Err(WalletError::SyncElectrumError)
+        }
+        self.open_sub_account(account_index)?;
+        let blockchain = self.blockchain.as_ref().ok_or(WalletError::SyncElectrumError)?;
+        let wallet = self.sub_account_wallets.get(&account_index).ok_or(WalletError::KeyError)?;
+        wallet.sync(blockchain, SyncOptions::default())?;
+        let is_used = !wallet.list_transactions(false)?.is_empty();
+        if !is_used && !was_open {
+            self.sub_account_wallets.remove(&account_index);
+            if !was_known {
+                self.watch_only_keys.account_xpubs.remove(&account_index);
+            }
+        }
+        Ok(is_used)
+    }
+
+    /// List the addresses of the `MasterAccount`'s own wallet, or of the sub-account at `account_index`, which a transaction has paid.
+    /// 
+    /// Addresses from both keychains are listed, in the order they were derived.
+    /// 
+    /// # Errors
+    /// If the sub-account is not open, or its database can not be read, return a `WalletError`.
+    pub fn list_used_addresses(&self, account_index: Option<u32>)-> Result<Vec<AddressInfo>, WalletError> {
+        let wallet = match account_index {
+            Some(account_index) => self.sub_account_wallets.get(&account_index).ok_or(WalletError::AddressError)?,
+            None => &self.wallet,
+        };
+        let mut used_addresses: Vec<AddressInfo> = vec![];
+        for transaction_details in wallet.list_transactions(true)? {
+            let transaction = match transaction_details.transaction {
+                Some(transaction) => transaction,
+                None => continue,
+            };
+            for output in transaction.output {
+                let derivation_path = wallet.database().get_path_from_script_pubkey(&output.script_pubkey)?;
+                if let Some((keychain, index)) = derivation_path {
+                    let address = Address::from_script(&output.script_pubkey, self.network).ok_or(WalletError::AddressError)?;
+                    if !used_addresses.iter().any(|used_address| used_address.address == address) {
+                        used_addresses.push(AddressInfo { index, address, keychain });
+                    }
+                }
+            }
+        }
+        used_addresses.sort_by_key(|used_address| (used_address.keychain == KeychainKind::Internal, used_address.index));
+        Ok(used_addresses)
+    }
+
+    /// Every wallet of the `MasterAccount`, its own wallet first followed by the open sub-accounts.
+    fn wallets(&self)-> impl Iterator<Item = &Wallet<AnyDatabase>> {
+        iter::once(&self.wallet).chain(self.sub_account_wallets.values())
+    }
+
+    /// Randomly generate a new set of 12 english mnemonic words.
+    /// 
+    /// # Panics
+    /// If the system is unable to generate randomness for the mnemonic.
+    pub fn generate_mnemonic_words() -> String {
+        let mnemonic: GeneratedKey<_, miniscript::Segwitv0> = Mnemonic::generate((WordCount::Words12, Language::English)).expect("mnemonic unable to be generated");
+        // Convert mnemonic to string
+        mnemonic.to_string()
     }
 
     /// This function connects the wallet to an electrum server.
@@ -105,13 +588,34 @@ impl MasterAccount {
     /// # Errors
     /// If we can not connect to the electrum server return a `WalletError`.
     pub fn sync_wallet_with_electrum_server(&mut self, electrum_url: Option<&str>) -> Result<(), WalletError>{
-        let default_electrum_server = env::var("electrum_server")?;
-        let electrum_client_url = electrum_url.unwrap_or(&default_electrum_server);
-        
-        let client = Client::new(electrum_client_url)?; 
-        let blockchain = ElectrumBlockchain::from(client);
+        self.sync_wallet_with_electrum_server_and_stop_gap(electrum_url, DEFAULT_STOP_GAP)
+    }
+
+    /// Connect the wallet to an electrum server, like `sync_wallet_with_electrum_server`, looking for transactions
+    /// until `stop_gap` unused addresses in a row are found.
+    /// 
+    /// A larger stop gap finds coins sent to addresses far past the last used one, for example when restoring a wallet
+    /// which handed out many addresses that were never paid.
+    /// 
+    /// # Errors
+    /// If we can not connect to the electrum server return a `WalletError`.
+    pub fn sync_wallet_with_electrum_server_and_stop_gap(&mut self, electrum_url: Option<&str>, stop_gap: usize) -> Result<(), WalletError>{
+        let electrum_client_url = match electrum_url {
+            Some(electrum_url) => String::from(electrum_url),
+            None => env::var("electrum_server")?,
+        };
+        // the same settings as `Client::new`, which does not let the stop gap be set
+        let blockchain = ElectrumBlockchain::from_config(&ElectrumBlockchainConfig {
+            url: electrum_client_url,
+            socks5: None,
+            retry: 1,
+            timeout: None,
+            stop_gap,
+        })?;
         
-        self.wallet.sync(&blockchain, SyncOptions::default())?;
+        for wallet in self.wallets() {
+            wallet.sync(&blockchain, SyncOptions::default())?;
+        }
         self.set_blockchain(blockchain);
         Ok(())
     }
@@ -123,44 +627,196 @@ impl MasterAccount {
 
     /// Spend bitcoin from our bitcoin wallet.
     /// 
-    /// This will sign a bitcoin transaction and broadcast it to the bitcoin network.
+    /// This will build and sign a bitcoin transaction and broadcast it to the bitcoin network.
     /// After the transaction is broadcast we will update the `pending_transactions` with the new transaction,
     /// and get the new bitcoin amount.
+    /// If the root key is held by an external signer the transaction is not signed or broadcast, it is returned as an unsigned PSBT instead.
     /// 
     /// # Errors
     /// If there is an error syncing to our wallet return a `WalletError`.
     /// If the passed in address is invalid return a `WalletError`.
     /// If there is an issue signing the transaction return a  `WalletError`.
     /// If there is an issue broadcasting the bitcoin transaction return a  `WalletError`.
-    pub fn spend_bitcoin(&mut self, amount: u64, address: &str, sat_per_vb: f32 ) -> Result<TransactionDetails, WalletError>{
+    pub fn spend_bitcoin(&mut self, amount: u64, address: &str, sat_per_vb: f32 ) -> Result<SpendOutcome, WalletError>{
+        let (psbt, tx_details) = self.build_spend_transaction(amount, address, FeeRate::from_sat_per_vb(sat_per_vb), vec![], vec![], None)?;
+        if self.signs_externally() {
+            debug!(txid = %tx_details.txid, "returning the spend unsigned for the external signer");
+            return Ok(SpendOutcome::AwaitingSignature(psbt, tx_details))
+        }
+        Ok(SpendOutcome::Broadcast(self.sign_and_broadcast_transaction(psbt, tx_details)?))
+    }
+
+    /// Build an unsigned transaction spending `amount` to `address`.
+    /// 
+    /// The `must_spend` outputs are always used as inputs, with more added by coin selection if they do not cover the spend.
+    /// `must_spend` outputs may belong to a sub-account, in which case the sub-account's wallet signs for them.
+    /// The `unspendable` outputs will not be used as inputs, for example because a transaction
+    /// waiting to be approved already spends them, or they belong to a different `Account`.
+    /// Change is sent to `change_script` if one is passed, otherwise to the wallet's internal keychain.
+    /// 
+    /// # Errors
+    /// If there is an error syncing to our wallet return a `WalletError`.
+    /// If the passed in address is invalid return a `WalletError`.
+    /// If the wallet does not have the funds for the transaction return a `WalletError`.
+    pub fn build_spend_transaction(&mut self, amount: u64, address: &str, fee_rate: FeeRate, must_spend: Vec<OutPoint>, unspendable: Vec<OutPoint>, change_script: Option<Script>) -> Result<(PartiallySignedTransaction, TransactionDetails), WalletError>{
+        self.build_batch_spend_transaction(&[(address.to_string(), amount)], fee_rate, must_spend, unspendable, change_script)
+    }
+
+    /// Spend bitcoin to several `recipients`, each an address and the amount it is paid, in one transaction, at the `sat_per_vb` fee rate.
+    /// 
+    /// If the `MasterAccount`'s keys are held by an external signer the transaction is not signed, it is returned unsigned
+    /// for the external signer to sign, otherwise it is signed and broadcast.
+    /// 
+    /// # Errors
+    /// If an address is invalid return a `WalletError`.
+    /// If the wallet does not have the funds for the transaction return a `WalletError`.
+    /// If there is an issue signing or broadcasting the transaction return a `WalletError`.
+    pub fn spend_bitcoin_batch(&mut self, recipients: &[(String, u64)], sat_per_vb: f32) -> Result<SpendOutcome, WalletError>{
+        let (psbt, tx_details) = self.build_batch_spend_transaction(recipients, FeeRate::from_sat_per_vb(sat_per_vb), vec![], vec![], None)?;
+        if self.signs_externally() {
+            debug!(txid = %tx_details.txid, "returning the batched spend unsigned for the external signer");
+            return Ok(SpendOutcome::AwaitingSignature(psbt, tx_details))
+        }
+        Ok(SpendOutcome::Broadcast(self.sign_and_broadcast_transaction(psbt, tx_details)?))
+    }
+
+    /// Build an unsigned transaction paying each of the `recipients`, an address and the amount it is paid, with one output each.
+    /// 
+    /// The inputs and change are chosen as `build_spend_transaction` chooses them.
+    /// 
+    /// # Errors
+    /// If there is an error syncing to our wallet return a `WalletError`.
+    /// If one of the addresses is invalid return a `WalletError`.
+    /// If the wallet does not have the funds for the transaction return a `WalletError`.
+    pub fn build_batch_spend_transaction(&mut self, recipients: &[(String, u64)], fee_rate: FeeRate, must_spend: Vec<OutPoint>, unspendable: Vec<OutPoint>, change_script: Option<Script>) -> Result<(PartiallySignedTransaction, TransactionDetails), WalletError>{
         // make sure our wallet is up to date before we make a spend.
         self.sync_wallet()?;
 
-        let receiving_address = Address::from_str(address)?;
+        let mut outputs = vec![];
+        for (address, amount) in recipients {
+            outputs.push((self.validate_address(address)?.script_pubkey(), *amount));
+        }
+        let (own_outpoints, sub_account_utxos) = self.split_sub_account_utxos(must_spend)?;
         let mut tx_builder = self.wallet.build_tx();
+        tx_builder
+            .set_recipients(outputs)
+            .add_utxos(&own_outpoints)?
+            .unspendable(unspendable)
+            .enable_rbf().fee_rate(fee_rate);
+        for (outpoint, psbt_input, satisfaction_weight) in sub_account_utxos {
+            tx_builder.add_foreign_utxo(outpoint, psbt_input, satisfaction_weight)?;
+        }
+        if let Some(change_script) = change_script {
+            tx_builder.drain_to(change_script);
+        }
+
+        let (psbt, tx_details) = tx_builder.finish()?;
+
+        debug!(txid = %tx_details.txid, recipients = recipients.len(), sent = tx_details.sent, received = tx_details.received, fee = ?tx_details.fee, "built spend transaction");
+        Ok((psbt, tx_details))
+    }
+
+    /// Build an unsigned transaction spending `amount` to `address` from the coins of the sub-account at `account_index`.
+    /// 
+    /// The `unspendable` outputs will not be used as inputs.
+    /// Change is sent to `change_script` if one is passed, otherwise to the sub-account's internal keychain.
+    /// 
+    /// # Errors
+    /// If there is an error syncing to our wallet return a `WalletError`.
+    /// If the passed in address is invalid return a `WalletError`.
+    /// If the sub-account does not have the funds for the transaction return a `WalletError`.
+    pub fn build_sub_account_spend_transaction(&mut self, account_index: u32, amount: u64, address: &str, fee_rate: FeeRate, unspendable: Vec<OutPoint>, change_script: Option<Script>) -> Result<(PartiallySignedTransaction, TransactionDetails), WalletError>{
+        self.open_sub_account(account_index)?;
+        self.sync_wallet()?;
+
+        let receiving_address = self.validate_address(address)?;
+        let wallet = self.sub_account_wallets.get(&account_index).ok_or(WalletError::KeyError)?;
+        let mut tx_builder = wallet.build_tx();
         tx_builder
             .add_recipient(receiving_address.script_pubkey(), amount)
-            .enable_rbf().fee_rate(FeeRate::from_sat_per_vb(sat_per_vb));
+            .unspendable(unspendable)
+            .enable_rbf().fee_rate(fee_rate);
+        if let Some(change_script) = change_script {
+            tx_builder.drain_to(change_script);
+        }
 
-        let (mut psbt, tx_details) = tx_builder.finish()?;
+        let (psbt, tx_details) = tx_builder.finish()?;
 
-        println!("tx_details is {:?}", tx_details);
-    
-        self.wallet.sign(&mut psbt, SignOptions::default())?;
+        debug!(account_index, txid = %tx_details.txid, sent = tx_details.sent, received = tx_details.received, fee = ?tx_details.fee, "built sub-account spend transaction");
+        Ok((psbt, tx_details))
+    }
+
+    /// Split `outpoints` into those of the `MasterAccount`'s own wallet and those of its sub-accounts.
+    /// 
+    /// The sub-account utxos are returned with the psbt input and satisfaction weight the tx builder needs to spend them as foreign utxos.
+    /// An outpoint which no wallet knows is returned with the wallet's own outpoints, so the tx builder rejects it.
+    fn split_sub_account_utxos(&self, outpoints: Vec<OutPoint>) -> Result<(Vec<OutPoint>, Vec<ForeignUtxo>), WalletError>{
+        let mut own_outpoints = vec![];
+        let mut sub_account_utxos = vec![];
+        'outpoints: for outpoint in outpoints {
+            if self.wallet.get_utxo(outpoint)?.is_none() {
+                for wallet in self.sub_account_wallets.values() {
+                    if let Some(utxo) = wallet.get_utxo(outpoint)? {
+                        let satisfaction_weight = wallet.get_descriptor_for_keychain(utxo.keychain).max_satisfaction_weight().map_err(|_e| WalletError::KeyError)?;
+                        let psbt_input = wallet.get_psbt_input(utxo, None, false)?;
+                        sub_account_utxos.push((outpoint, psbt_input, satisfaction_weight));
+                        continue 'outpoints;
+                    }
+                }
+            }
+            own_outpoints.push(outpoint);
+        }
+        Ok((own_outpoints, sub_account_utxos))
+    }
+
+    /// Sign a transaction built by `build_spend_transaction` and broadcast it to the bitcoin network.
+    /// 
+    /// After the transaction is broadcast we will update the `pending_transactions` with the new transaction,
+    /// and get the new bitcoin amount.
+    /// 
+    /// # Errors
+    /// If the `MasterAccount` is locked return a `WalletError`.
+    /// If there is an issue signing the transaction return a  `WalletError`.
+    /// If there is an issue broadcasting the bitcoin transaction return a  `WalletError`.
+    pub fn sign_and_broadcast_transaction(&mut self, mut psbt: PartiallySignedTransaction, tx_details: TransactionDetails) -> Result<TransactionDetails, WalletError>{
+        self.sign_transaction(&mut psbt)?;
+        self.broadcast_transaction(psbt, tx_details)
+    }
+
+    /// Broadcast the transaction of a finalized `psbt` to the bitcoin network, for example one signed on another device.
+    /// 
+    /// After the transaction is broadcast we will update the `pending_transactions` with the new transaction,
+    /// and get the new bitcoin amount.
+    /// 
+    /// # Errors
+    /// If any input of the `psbt` has not been finalized return a `WalletError`.
+    /// If there is an issue broadcasting the bitcoin transaction, for example because the electrum server rejected it, return a `WalletError`,
+    /// and the `pending_transactions` are left unchanged.
+    pub fn broadcast_transaction(&mut self, psbt: PartiallySignedTransaction, tx_details: TransactionDetails) -> Result<TransactionDetails, WalletError>{
+        if !is_finalized(&psbt) {
+            return Err(WalletError::UnsignedTransaction)
+        }
+        let amount = tx_details.sent.saturating_sub(tx_details.received + tx_details.fee.unwrap_or(0));
         
         // now broadcast it 
         let raw_transaction = psbt.extract_tx();
         let txid = raw_transaction.txid();
-        println!("the txid {}", txid);
         let my_blockchain = self.blockchain.as_ref();
         
         let electrum_blockchain_option = my_blockchain;
         
 
-        let _electrum_blockchain = match electrum_blockchain_option {
+        let electrum_blockchain = match electrum_blockchain_option {
             Some(electrum_blockchain) => electrum_blockchain.broadcast(&raw_transaction),
             None => return Err(WalletError::BroadcastTransactionError),
         };
+        match electrum_blockchain {
+            Ok(()) => info!(%txid, amount, fee = ?tx_details.fee, "broadcast transaction"),
+            Err(error) => {
+                warn!(%txid, %error, "the electrum server did not accept the transaction");
+                return Err(WalletError::BroadcastTransactionError)
+            },
+        }
 
         self.sync_wallet()?;
 
@@ -174,18 +830,372 @@ impl MasterAccount {
              confirmation_time: tx_details.confirmation_time.clone()};
 
         // now we have a pending transaction, so add it to the list of pending_transactions
-        println!("I am pushing tx_details  {:?} to pending_transactions", copied_transaction);
         self.pending_transactions.push(tx_details);
 
-        println!("bitcoin amount {} other amount {}", self.bitcoin_amount, amount);
         // TODO should this be get_bitcoin_total? should we update here?
         // yes we need to update after spending
         self.bitcoin_amount = self.get_bitcoin_total()?; // - amount;
-        println!("you have spent {} bitcoin, you now have {} remaining", amount, self.bitcoin_amount);
+        debug!(%txid, amount, bitcoin_amount = self.bitcoin_amount, "updated the bitcoin amount after spending");
 
         Ok(copied_transaction)
     }
 
+    /// Replace a pending transaction with one paying a higher `sat_per_vb` fee rate, signing and broadcasting the replacement.
+    /// 
+    /// The replacement is swapped for the original in the `pending_transactions`.
+    /// 
+    /// # Errors
+    /// If the transaction is not one of the `pending_transactions` return a `WalletError`.
+    /// If the replacement can not be built, for example because the fee rate is not higher than the original's, return a `WalletError`.
+    /// If there is an issue signing or broadcasting the replacement return a `WalletError`.
+    pub fn bump_fee(&mut self, txid: Txid, sat_per_vb: f32) -> Result<TransactionDetails, WalletError>{
+        let (psbt, tx_details) = self.build_fee_bump_transaction(txid, FeeRate::from_sat_per_vb(sat_per_vb), vec![], vec![])?;
+        self.sign_and_broadcast_replacement(txid, psbt, tx_details)
+    }
+
+    /// Build an unsigned transaction replacing the pending transaction `txid` with the same payments at a higher `fee_rate`.
+    /// 
+    /// The replacement is built by the wallet whose coins the original spent, keeping every input of the original.
+    /// The extra fee comes out of the original's change, either the wallet's own change output or an output to one of the
+    /// `shrinkable` scripts, and if the change does not cover it coin selection adds inputs, never one of the `unspendable` outputs.
+    /// A transaction spending the coins of more than one wallet can not be replaced.
+    /// 
+    /// # Errors
+    /// If the transaction is not one of the `pending_transactions` return a `WalletError`.
+    /// If no wallet can build the replacement, for example because the fee rate is not higher than the original's or the wallet
+    /// does not have the funds for the extra fee, return a `WalletError`.
+    pub fn build_fee_bump_transaction(&mut self, txid: Txid, fee_rate: FeeRate, unspendable: Vec<OutPoint>, shrinkable: Vec<Script>) -> Result<(PartiallySignedTransaction, TransactionDetails), WalletError>{
+        if !self.pending_transactions.iter().any(|transaction_details| transaction_details.txid == txid) {
+            return Err(WalletError::TransactionNotPending)
+        }
+        self.sync_wallet()?;
+
+        let mut bump_result = Err(WalletError::TransactionNotPending);
+        // only a wallet whose coins the original spent can replace it
+        for wallet in self.wallets() {
+            if let Some(original) = wallet.get_tx(&txid, true)?.filter(|original| original.sent > 0) {
+                bump_result = MasterAccount::build_wallet_fee_bump(wallet, &original, fee_rate, &unspendable, &shrinkable).map_err(WalletError::from);
+                if bump_result.is_ok() {
+                    break
+                }
+            }
+        }
+
+        let (psbt, tx_details) = bump_result?;
+        debug!(replaced_txid = %txid, txid = %tx_details.txid, fee = ?tx_details.fee, "built fee bump transaction");
+        Ok((psbt, tx_details))
+    }
+
+    /// Build the replacement of the `original` transaction with `wallet`, see `build_fee_bump_transaction`.
+    fn build_wallet_fee_bump(wallet: &Wallet<AnyDatabase>, original: &TransactionDetails, fee_rate: FeeRate, unspendable: &[OutPoint], shrinkable: &[Script]) -> Result<(PartiallySignedTransaction, TransactionDetails), Error>{
+        let original_outputs = original.transaction.as_ref().map(|transaction| transaction.output.clone()).unwrap_or_default();
+        let mut tx_builder = wallet.build_fee_bump(original.txid)?;
+        tx_builder
+            .fee_rate(fee_rate)
+            .unspendable(unspendable.to_vec())
+            .enable_rbf();
+        for script in shrinkable {
+            if original_outputs.iter().any(|output| &output.script_pubkey == script) {
+                tx_builder.allow_shrinking(script.clone())?;
+            }
+        }
+        tx_builder.finish()
+    }
+
+    /// Sign a replacement built by `build_fee_bump_transaction` and broadcast it, swapping it for the transaction `replaced_txid`
+    /// in the `pending_transactions`.
+    /// 
+    /// # Errors
+    /// If the `MasterAccount` is locked return a `WalletError`.
+    /// If there is an issue signing the transaction return a  `WalletError`.
+    /// If there is an issue broadcasting the bitcoin transaction return a  `WalletError`.
+    pub fn sign_and_broadcast_replacement(&mut self, replaced_txid: Txid, psbt: PartiallySignedTransaction, tx_details: TransactionDetails) -> Result<TransactionDetails, WalletError>{
+        let replacement = self.sign_and_broadcast_transaction(psbt, tx_details)?;
+        self.pending_transactions.retain(|transaction_details| transaction_details.txid != replaced_txid);
+        info!(%replaced_txid, txid = %replacement.txid, fee = ?replacement.fee, "replaced pending transaction");
+        Ok(replacement)
+    }
+
+    /// Build an unsigned transaction cancelling the pending transaction `txid`, spending every one of its inputs, and nothing else,
+    /// back to the household at a higher fee.
+    /// 
+    /// Each of the `refunds` is paid its amount and the rest, less the fee, goes to `drain_script`. The fee is paid at `fee_rate`,
+    /// or more if that does not pay for the replacement's own size on top of the fee the original paid, as a replacement must.
+    /// 
+    /// # Errors
+    /// If the transaction is not one of the `pending_transactions` return a `WalletError`.
+    /// If there is an error syncing to our wallet, or the fee the original paid can not be fetched from the electrum server, return a `WalletError`.
+    /// If the original's inputs do not cover the `refunds` and the fee return a `WalletError`.
+    pub fn build_cancel_transaction(&mut self, txid: Txid, fee_rate: FeeRate, refunds: &[(Script, u64)], drain_script: Script) -> Result<(PartiallySignedTransaction, TransactionDetails), WalletError>{
+        if !self.pending_transactions.iter().any(|transaction_details| transaction_details.txid == txid) {
+            return Err(WalletError::TransactionNotPending)
+        }
+        self.sync_wallet()?;
+
+        let original = self.get_transaction(&txid, true)?.ok_or(WalletError::TransactionNotPending)?;
+        let original_transaction = original.transaction.as_ref().ok_or(WalletError::TransactionNotPending)?;
+        let original_fee = match original.fee {
+            Some(fee) => fee,
+            None => self.get_fee_paid(original_transaction)?,
+        };
+        // the original's inputs are spent outputs, which the wallets' databases still hold
+        let outpoints = original_transaction.input.iter().map(|input| input.previous_output).collect();
+        let (own_outpoints, sub_account_utxos) = self.split_sub_account_utxos(outpoints)?;
+
+        // the transaction alone at `fee_rate` gives its size, its fee is then raised if it does not pay the relay fee for that size on top of the original's
+        let (psbt, tx_details) = self.build_wallet_cancel(&own_outpoints, &sub_account_utxos, refunds, drain_script.clone(), fee_rate, None)?;
+        let fee = tx_details.fee.unwrap_or(0);
+        let vsize = (fee as f32 / fee_rate.as_sat_vb()).ceil() as usize;
+        let minimum_fee = original_fee + FeeRate::from_sat_per_vb(MINIMUM_SAT_PER_VB).fee_vb(vsize);
+        let (psbt, tx_details) = if fee < minimum_fee {
+            self.build_wallet_cancel(&own_outpoints, &sub_account_utxos, refunds, drain_script, fee_rate, Some(minimum_fee))?
+        } else {
+            (psbt, tx_details)
+        };
+
+        debug!(replaced_txid = %txid, txid = %tx_details.txid, original_fee, fee = ?tx_details.fee, "built cancel transaction");
+        Ok((psbt, tx_details))
+    }
+
+    /// Build a transaction with the `MasterAccount`'s own wallet spending the `own_outpoints` and `sub_account_utxos`, and nothing else,
+    /// to the `refunds` and `drain_script`, paying `fee_absolute` if it is passed, otherwise `fee_rate`.
+    fn build_wallet_cancel(&self, own_outpoints: &[OutPoint], sub_account_utxos: &[ForeignUtxo], refunds: &[(Script, u64)], drain_script: Script, fee_rate: FeeRate, fee_absolute: Option<u64>) -> Result<(PartiallySignedTransaction, TransactionDetails), WalletError>{
+        let mut tx_builder = self.wallet.build_tx();
+        tx_builder
+            .add_utxos(own_outpoints)?
+            .manually_selected_only()
+            .set_recipients(refunds.to_vec())
+            .drain_to(drain_script)
+            .enable_rbf();
+        for (outpoint, psbt_input, satisfaction_weight) in sub_account_utxos {
+            tx_builder.add_foreign_utxo(*outpoint, psbt_input.clone(), *satisfaction_weight)?;
+        }
+        match fee_absolute {
+            Some(fee_absolute) => tx_builder.fee_absolute(fee_absolute),
+            None => tx_builder.fee_rate(fee_rate),
+        };
+        Ok(tx_builder.finish()?)
+    }
+
+    /// Get the output spent by `outpoint`, if it belongs to one of the wallets, whether or not it has been spent.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn get_previous_output(&self, outpoint: OutPoint) -> Result<Option<TxOut>, WalletError> {
+        for wallet in self.wallets() {
+            if let Some(utxo) = wallet.get_utxo(outpoint)? {
+                return Ok(Some(utxo.txout))
+            }
+        }
+        Ok(None)
+    }
+
+    /// Return true if `script` belongs to one of the wallets, else return false.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn is_mine(&self, script: &Script) -> Result<bool, WalletError> {
+        for wallet in self.wallets() {
+            if wallet.is_mine(script)? {
+                return Ok(true)
+            }
+        }
+        Ok(false)
+    }
+
+    /// Generate a new change address of the `MasterAccount`'s own wallet, from its internal keychain.
+    /// 
+    /// # Errors
+    /// If the address can not be derived return a `WalletError`.
+    pub fn generate_new_change_address(&mut self) -> Result<Address, WalletError> {
+        let my_new_address = self.wallet.get_internal_address(New)?;
+        Ok(my_new_address.address)
+    }
+
+    /// List the utxos received by unconfirmed transactions which spend none of the wallets' coins, payments from outside the
+    /// household which are still waiting to be confirmed.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn list_unconfirmed_incoming_utxos(&self) -> Result<Vec<LocalUtxo>, WalletError> {
+        let mut unconfirmed_incoming_utxos = vec![];
+        for utxo in self.list_unspent()? {
+            let mut is_unconfirmed_incoming = true;
+            for wallet in self.wallets() {
+                if let Some(parent) = wallet.get_tx(&utxo.outpoint.txid, false)? {
+                    is_unconfirmed_incoming &= parent.confirmation_time.is_none() && parent.sent == 0;
+                }
+            }
+            if is_unconfirmed_incoming {
+                unconfirmed_incoming_utxos.push(utxo);
+            }
+        }
+        Ok(unconfirmed_incoming_utxos)
+    }
+
+    /// Build an unsigned transaction spending the unconfirmed `outpoints`, and nothing else, to `address`, paying a fee which
+    /// raises the fee rate of the whole package, the unconfirmed transactions the outpoints come from and the new transaction,
+    /// to `fee_rate`, so miners confirm the unconfirmed transactions with it (child pays for parent).
+    /// 
+    /// The outpoints are spent by the wallet of the sub-account at `account_index`, or by the `MasterAccount`'s own wallet if there is none.
+    /// 
+    /// # Errors
+    /// If there is an error syncing to our wallet, or the fee the unconfirmed transactions paid can not be fetched from the
+    /// electrum server, return a `WalletError`.
+    /// If the passed in address is invalid return a `WalletError`.
+    /// If the outpoints are not unspent outputs of the wallet, or do not cover the fee, return a `WalletError`.
+    pub fn build_cpfp_transaction(&mut self, account_index: Option<u32>, outpoints: &[OutPoint], address: &str, fee_rate: FeeRate) -> Result<(PartiallySignedTransaction, TransactionDetails), WalletError>{
+        self.sync_wallet()?;
+        let receiving_address = self.validate_address(address)?;
+        let wallet = match account_index {
+            Some(account_index) => self.sub_account_wallets.get(&account_index).ok_or(WalletError::KeyError)?,
+            None => &self.wallet,
+        };
+
+        // the fee rate the unconfirmed transactions paid, to be raised to `fee_rate` together with the new transaction
+        let mut parent_txids: Vec<Txid> = outpoints.iter().map(|outpoint| outpoint.txid).collect();
+        parent_txids.sort();
+        parent_txids.dedup();
+        let mut parent_fee = 0;
+        let mut parent_vsize = 0;
+        for parent_txid in parent_txids {
+            let parent = wallet.get_tx(&parent_txid, true)?.ok_or(WalletError::SyncElectrumError)?;
+            let parent_transaction = parent.transaction.as_ref().ok_or(WalletError::SyncElectrumError)?;
+            parent_fee += match parent.fee {
+                Some(fee) => fee,
+                None => self.get_fee_paid(parent_transaction)?,
+            };
+            parent_vsize += parent_transaction.vsize();
+        }
+
+        // the transaction alone at `fee_rate` gives its size, its fee is then raised by what the unconfirmed transactions are short of
+        let (_psbt, tx_details) = MasterAccount::build_wallet_sweep(wallet, outpoints, receiving_address.script_pubkey(), fee_rate, None)?;
+        let parent_fee_shortfall = fee_rate.fee_vb(parent_vsize).saturating_sub(parent_fee);
+        let fee = tx_details.fee.unwrap_or(0) + parent_fee_shortfall;
+        let (psbt, tx_details) = MasterAccount::build_wallet_sweep(wallet, outpoints, receiving_address.script_pubkey(), fee_rate, Some(fee))?;
+
+        debug!(txid = %tx_details.txid, parent_fee, parent_vsize, fee, "built child pays for parent transaction");
+        Ok((psbt, tx_details))
+    }
+
+    /// Build a transaction with `wallet` spending the `outpoints`, and nothing else, to `script`, paying `fee_absolute` if it
+    /// is passed, otherwise `fee_rate`.
+    fn build_wallet_sweep(wallet: &Wallet<AnyDatabase>, outpoints: &[OutPoint], script: Script, fee_rate: FeeRate, fee_absolute: Option<u64>) -> Result<(PartiallySignedTransaction, TransactionDetails), Error>{
+        let mut tx_builder = wallet.build_tx();
+        tx_builder
+            .add_utxos(outpoints)?
+            .manually_selected_only()
+            .drain_to(script)
+            .enable_rbf();
+        match fee_absolute {
+            Some(fee_absolute) => tx_builder.fee_absolute(fee_absolute),
+            None => tx_builder.fee_rate(fee_rate),
+        };
+        tx_builder.finish()
+    }
+
+    /// Get the fee an unconfirmed `transaction` paid, fetching the outputs it spends from the electrum server.
+    /// 
+    /// # Errors
+    /// If the wallet is not connected to an electrum server, or a transaction can not be fetched, return a `WalletError`.
+    fn get_fee_paid(&self, transaction: &Transaction) -> Result<u64, WalletError> {
+        let blockchain = self.blockchain.as_ref().ok_or(WalletError::SyncElectrumError)?;
+        let mut input_amount = 0;
+        for input in &transaction.input {
+            let previous_transaction = blockchain.get_tx(&input.previous_output.txid)?.ok_or(WalletError::SyncElectrumError)?;
+            let previous_output = previous_transaction.output.get(input.previous_output.vout as usize).ok_or(WalletError::SyncElectrumError)?;
+            input_amount += previous_output.value;
+        }
+        let output_amount: u64 = transaction.output.iter().map(|output| output.value).sum();
+        Ok(input_amount.saturating_sub(output_amount))
+    }
+
+    /// Sign and finalize the inputs of `psbt` which any of the `MasterAccount`'s wallets can spend.
+    /// 
+    /// The wallets hold no private keys, each input is signed with the key of the account its bip32 derivation
+    /// points to, derived from the root key, and then finalized by the wallet it belongs to. Inputs of other wallets are left as they are.
+    /// 
+    /// # Errors
+    /// If the `MasterAccount` is locked return a `WalletError`.
+    /// If there is an issue signing or finalizing the transaction return a `WalletError`.
+    pub fn sign_transaction(&mut self, psbt: &mut PartiallySignedTransaction) -> Result<(), WalletError>{
+        let root_key = self.signing_root_key()?;
+        let account_indexes: Vec<u32> = iter::once(0).chain(self.sub_account_wallets.keys().copied()).collect();
+        for account_index in account_indexes {
+            let account_derivation_path = self.own_account_derivation_path(account_index)?;
+            for keychain_index in [0, 1] {
+                let signer = SignerWrapper::new(
+                    DescriptorXKey {
+                        origin: None,
+                        xkey: root_key,
+                        derivation_path: account_derivation_path.child(ChildNumber::from_normal_idx(keychain_index)?),
+                        wildcard: Wildcard::Unhardened,
+                    },
+                    SignerContext::Segwitv0,
+                );
+                signer.sign_transaction(psbt, self.wallet.secp_ctx()).map_err(|_e| WalletError::KeyError)?;
+            }
+        }
+        self.finalize_psbt(psbt)?;
+        Ok(())
+    }
+
+    /// Finalize every input of `psbt` which has the signatures it needs, and return true if every input is finalized.
+    /// 
+    /// Each input is finalized by the wallet it belongs to, an input which is still missing a signature, or belongs to
+    /// none of the `MasterAccount`'s wallets, is left as it is.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn finalize_psbt(&self, psbt: &mut PartiallySignedTransaction) -> Result<bool, WalletError>{
+        for wallet in self.wallets() {
+            wallet.finalize_psbt(psbt, SignOptions::default())?;
+        }
+        Ok(is_finalized(psbt))
+    }
+
+    /// How many more signatures `psbt` needs before it can be broadcast, every input needs one signature, or the threshold of a multisig vault.
+    pub fn signatures_needed(&self, psbt: &PartiallySignedTransaction) -> usize {
+        let threshold = self.multisig_policy().map_or(1, |multisig| multisig.threshold);
+        signatures_needed(psbt, threshold)
+    }
+
+    /// Get the fee rate a `fee_selection` asks for.
+    /// 
+    /// An explicit sat/vB rate is used as is. A confirmation target or priority is estimated by the electrum server,
+    /// and raised to the minimum relay fee rate if the estimate is lower.
+    /// 
+    /// # Errors
+    /// If the wallet is not connected to an electrum server, or the server can not estimate a fee rate, return a `WalletError`.
+    pub fn get_fee_rate(&self, fee_selection: &FeeSelection) -> Result<FeeRate, WalletError> {
+        let confirmation_target = match fee_selection {
+            FeeSelection::SatPerVb(sat_per_vb) => return Ok(FeeRate::from_sat_per_vb(*sat_per_vb)),
+            FeeSelection::ConfirmationTarget(blocks) => *blocks,
+            FeeSelection::Priority(priority) => priority.confirmation_target(),
+        };
+        let blockchain = self.blockchain.as_ref().ok_or(WalletError::SyncElectrumError)?;
+        let estimated_fee_rate = blockchain.estimate_fee(confirmation_target).map_err(|_e| WalletError::FeeEstimationError)?;
+        // electrum servers return a negative estimate when they do not have enough data
+        if estimated_fee_rate.as_sat_vb() <= 0.0 {
+            return Err(WalletError::FeeEstimationError)
+        }
+        Ok(FeeRate::from_sat_per_vb(estimated_fee_rate.as_sat_vb().max(MINIMUM_SAT_PER_VB)))
+    }
+
+    /// Parse an `address` and make sure it belongs to the same bitcoin network as this wallet.
+    /// 
+    /// # Errors
+    /// If the address can not be parsed return a `WalletError`.
+    /// If the address is for a different network, for example a testnet address given to a regtest wallet, return a `WalletError`.
+    pub fn validate_address(&self, address: &str) -> Result<Address, WalletError> {
+        let parsed_address = Address::from_str(address)?;
+        if parsed_address.is_valid_for_network(self.network) {
+            Ok(parsed_address)
+        } else {
+            Err(WalletError::NetworkError)
+        }
+    }
+
     /// Generate a new address and add it to the `all_addresses`.
     pub fn generate_new_address(&mut self) -> Result<Address, WalletError> {        
         let my_new_address = self.wallet.get_address(New)?;
@@ -194,6 +1204,69 @@ impl MasterAccount {
         Ok(copied_address)
     }
 
+    /// Generate a new address of the sub-account at `account_index` and add it to the `all_addresses`.
+    /// 
+    /// # Errors
+    /// If the sub-account's wallet can not be opened, or the address can not be derived, return a `WalletError`.
+    pub fn generate_new_sub_account_address(&mut self, account_index: u32) -> Result<Address, WalletError> {
+        let my_new_address = self.open_sub_account(account_index)?.get_address(New)?;
+        let copied_address = my_new_address.clone();
+        self.all_addresses.push(my_new_address);
+        Ok(copied_address)
+    }
+
+    /// Generate a new change address of the sub-account at `account_index`, from the internal keychain of its wallet.
+    /// 
+    /// # Errors
+    /// If the sub-account's wallet can not be opened, or the address can not be derived, return a `WalletError`.
+    pub fn generate_new_sub_account_change_address(&mut self, account_index: u32) -> Result<Address, WalletError> {
+        let my_new_address = self.open_sub_account(account_index)?.get_internal_address(New)?;
+        Ok(my_new_address.address)
+    }
+
+    /// List the utxos of every wallet, including the sub-accounts.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn list_unspent(&self) -> Result<Vec<LocalUtxo>, WalletError> {
+        let mut utxos = vec![];
+        for wallet in self.wallets() {
+            utxos.extend(wallet.list_unspent()?);
+        }
+        Ok(utxos)
+    }
+
+    /// Get a transaction from the first wallet which knows it, including the raw transaction if `include_raw` is true.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn get_transaction(&self, txid: &Txid, include_raw: bool) -> Result<Option<TransactionDetails>, WalletError> {
+        for wallet in self.wallets() {
+            if let Some(transaction_details) = wallet.get_tx(txid, include_raw)? {
+                return Ok(Some(transaction_details))
+            }
+        }
+        Ok(None)
+    }
+
+    /// List the transactions of every wallet, including the sub-accounts.
+    /// 
+    /// A transaction which involves several wallets is listed once, as the first of those wallets sees it.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn list_transactions(&self, include_raw: bool) -> Result<Vec<TransactionDetails>, WalletError> {
+        let mut transactions: Vec<TransactionDetails> = vec![];
+        for wallet in self.wallets() {
+            for transaction_details in wallet.list_transactions(include_raw)? {
+                if !transactions.iter().any(|listed| listed.txid == transaction_details.txid) {
+                    transactions.push(transaction_details);
+                }
+            }
+        }
+        Ok(transactions)
+    }
+
     /// Calculate the total amount of bitcoin the user has sent but is still pending in the mempool.
     /// 
     /// # Errors
@@ -211,7 +1284,7 @@ impl MasterAccount {
 
     /// Get total bitcoin for this wallet.
     /// 
-    /// This is done by looking at all associated addresses for this wallet xpub.
+    /// This is done by looking at all associated addresses for this wallet xpub and its sub-accounts.
     /// This is strictly derived from the bitcoin blockchain it has nothign to do with 
     /// a MasterAccounts internal accounting, this will return a sum of bitcoin for the entire 
     /// wallet, regardless is it was recieved by the `MasterAccount` or another `Account`.
@@ -220,7 +1293,11 @@ impl MasterAccount {
     /// If there is an issue connecting to the bitcoin network return an `Error`.
     pub fn get_bitcoin_total(&self)-> Result<u64, Error> { 
         self.sync_wallet().unwrap();
-        self.wallet.get_balance()
+        let mut total = 0;
+        for wallet in self.wallets() {
+            total += wallet.get_balance()?;
+        }
+        Ok(total)
      }
 
     /// Get the total bitcoin amount derived fomr the blockchain and then subtract what has been given to other `Account`s.
@@ -229,7 +1306,7 @@ impl MasterAccount {
     /// If there is an issue connecting to the bitcoin network return an `Error`.
     pub fn get_bitcoin_total_minus_transfers_to_children(&self) ->Result<u64, Error> {
         let total_amount_from_utxos =  self.get_bitcoin_total()?;
-        Ok(total_amount_from_utxos -  self.amount_transfered_to_children)
+        Ok(total_amount_from_utxos.saturating_sub(self.amount_transfered_to_children))
     }
 
     /// Increase the `amount_transfered_to_children`.
@@ -255,13 +1332,13 @@ impl MasterAccount {
         // for each pending transaction go check if it is still pending
         let mut transactions_that_are_no_longer_pending: Vec<Txid> = vec![];
         for transaction_detail in &self.pending_transactions{
-            let my_transaction  = self.wallet.get_tx(&transaction_detail.txid, false)?.ok_or(WalletError::SyncElectrumError)?;
+            let my_transaction  = self.get_transaction(&transaction_detail.txid, false)?.ok_or(WalletError::SyncElectrumError)?;
             
             if my_transaction.confirmation_time != None{
                 // remove it from the list if it has been confirmed
                 transactions_that_are_no_longer_pending.push(my_transaction.txid.clone());
             }
-            println!("txId {} this is my transaction {:?}", transaction_detail.txid, my_transaction);
+            debug!(txid = %transaction_detail.txid, confirmation_time = ?my_transaction.confirmation_time, "checked pending transaction");
         }
         // filter out the pending transactions, removing the ones that have been confirmed
         for item in transactions_that_are_no_longer_pending{
@@ -271,8 +1348,19 @@ impl MasterAccount {
         Ok(self.pending_transactions.as_ref())
     }
 
-    /// Sync the `MasterAccount` bitcoin wallet with an electrum server.
+    /// Replace the `pending_transactions` with every unconfirmed transaction which spends from one of the wallets.
+    /// 
+    /// # Errors
+    /// If a wallet's database can not be read return a `WalletError`.
+    pub fn rescan_pending_transactions(&mut self)-> Result<(), WalletError>{
+        self.pending_transactions = self.list_transactions(false)?.into_iter()
+            .filter(|transaction_details| transaction_details.confirmation_time.is_none() && transaction_details.sent > 0)
+            .collect();
+        Ok(())
+    }
 
+    /// Sync the `MasterAccount` bitcoin wallet, and the wallets of its sub-accounts, with an electrum server.
+    /// 
     /// # Errors
     /// If there is an issue syncing with the bitcoin network return a `WalletError`.
     pub fn sync_wallet(&self)-> Result<(),WalletError>{
@@ -280,7 +1368,9 @@ impl MasterAccount {
         match current_blockchain_option {
             None => Err(WalletError::SyncElectrumError),
             Some(current_blockchain) => {
-                self.wallet.sync(current_blockchain, SyncOptions::default())?;
+                for wallet in self.wallets() {
+                    wallet.sync(current_blockchain, SyncOptions::default())?;
+                }
                 Ok(())
             }
         }
@@ -298,6 +1388,11 @@ pub mod test {
     #[allow(unused_imports)]
     use futures::executor::block_on;
     use tokio_test;
+    use crate::logging::Redacted;
+    use crate::seed_store::SeedSecrets;
+    use crate::testing_helpers::get_multisig_cosigner_key;
+    use bdk::bitcoin::{TxIn, TxOut};
+    use bdk::bitcoin::hashes::Hash;
 
     // used to handle async await functions
     macro_rules! aw {
@@ -311,24 +1406,170 @@ pub mod test {
         set_up();
         let mock_mnemonic = get_default_mnenomic_words();
 
-        let mut new_master_account = MasterAccount::new(mock_mnemonic).unwrap();
+        let mut new_master_account = MasterAccount::new(mock_mnemonic, Network::Regtest).unwrap();
         attach_wallet_to_regtest_electrum_server(&mut new_master_account);
 
         assert_eq!(new_master_account.bitcoin_amount, 0)
     }
+    #[test]
+    fn validate_address_accepts_address_from_wallet_network(){
+        set_up();
+        let new_master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+
+        test_result_type_is_not_err(new_master_account.validate_address("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20"));
+    }
+
+    #[test]
+    fn validate_address_rejects_address_from_another_network(){
+        set_up();
+        let new_master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+
+        let testnet_address_result = new_master_account.validate_address("tb1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6whp7ax");
+        assert!(matches!(testnet_address_result, Err(WalletError::NetworkError)));
+    }
+
+    #[test]
+    fn sub_account_addresses_are_derived_from_the_seed_alone(){
+        set_up();
+        let mut first_master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+        let mut restored_master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+
+        let sub_account_address = first_master_account.generate_new_sub_account_address(2).unwrap();
+
+        assert_eq!(restored_master_account.generate_new_sub_account_address(2).unwrap(), sub_account_address);
+        assert_ne!(restored_master_account.generate_new_sub_account_address(3).unwrap(), sub_account_address);
+        assert_ne!(restored_master_account.generate_new_address().unwrap(), sub_account_address);
+        let sub_account_descriptor = first_master_account.sub_account_wallets.get(&2).unwrap().get_descriptor_for_keychain(KeychainKind::External).to_string();
+        assert!(sub_account_descriptor.contains("/84'/1'/2']"));
+    }
+
+    #[test]
+    fn locked_master_account_watches_its_accounts_but_signs_only_while_unlocked(){
+        set_up();
+        let mut unlocked_master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+        unlocked_master_account.open_sub_account(2).unwrap();
+        let mut locked_master_account = MasterAccount::new_watch_only(unlocked_master_account.watch_only_keys().clone(), Network::Regtest, WalletStore::Memory).unwrap();
+
+        assert!(locked_master_account.is_locked());
+        assert_eq!(locked_master_account.generate_new_address().unwrap(), unlocked_master_account.generate_new_address().unwrap());
+        assert_eq!(locked_master_account.generate_new_sub_account_address(2).unwrap(), unlocked_master_account.generate_new_sub_account_address(2).unwrap());
+        assert!(matches!(locked_master_account.open_sub_account(3), Err(WalletError::WalletLocked)));
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(Transaction { version: 2, lock_time: 0, input: vec![], output: vec![] }).unwrap();
+        assert!(matches!(locked_master_account.sign_transaction(&mut psbt), Err(WalletError::WalletLocked)));
+
+        let seed_secrets = SeedSecrets { mnemonic_words: Redacted::new(get_default_mnenomic_words().unwrap()), bip39_passphrase: None };
+        let encrypted_seed = EncryptedSeed::encrypt(&seed_secrets, "correct horse", locked_master_account.watch_only_keys().fingerprint).unwrap();
+        assert!(matches!(locked_master_account.unlock(&encrypted_seed, "battery staple"), Err(WalletError::IncorrectPassword)));
+        locked_master_account.unlock(&encrypted_seed, "correct horse").unwrap();
+        assert!(!locked_master_account.is_locked());
+        test_result_type_is_not_err(locked_master_account.sign_transaction(&mut psbt));
+        test_result_type_is_not_err(locked_master_account.open_sub_account(3));
+
+        locked_master_account.set_relock_timeout(Duration::ZERO);
+        locked_master_account.unlock(&encrypted_seed, "correct horse").unwrap();
+        assert!(locked_master_account.is_locked());
+        assert!(matches!(locked_master_account.sign_transaction(&mut psbt), Err(WalletError::WalletLocked)));
+    }
+
+    #[test]
+    fn seed_from_another_wallet_does_not_unlock(){
+        set_up();
+        let master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+        let mut locked_master_account = MasterAccount::new_watch_only(master_account.watch_only_keys().clone(), Network::Regtest, WalletStore::Memory).unwrap();
+
+        let seed_secrets = SeedSecrets { mnemonic_words: Redacted::new(get_default_mnenomic_words_2().unwrap()), bip39_passphrase: None };
+        let encrypted_seed = EncryptedSeed::encrypt(&seed_secrets, "correct horse", locked_master_account.watch_only_keys().fingerprint).unwrap();
+
+        assert!(matches!(locked_master_account.unlock(&encrypted_seed, "correct horse"), Err(WalletError::KeyError)));
+        assert!(locked_master_account.is_locked());
+    }
+
+    #[test]
+    fn master_account_from_external_signer_descriptors_watches_but_never_signs(){
+        set_up();
+        let mut seed_master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+        seed_master_account.open_sub_account(2).unwrap();
+        let descriptors = vec![
+            seed_master_account.wallet.get_descriptor_for_keychain(KeychainKind::External).to_string(),
+            seed_master_account.sub_account_wallets.get(&2).unwrap().get_descriptor_for_keychain(KeychainKind::Internal).to_string(),
+        ];
+        let watch_only_keys = WatchOnlyKeys::from_descriptors(&descriptors, Network::Regtest).unwrap();
+        let mut external_master_account = MasterAccount::new_watch_only(watch_only_keys, Network::Regtest, WalletStore::Memory).unwrap();
+
+        assert!(external_master_account.signs_externally());
+        assert_eq!(external_master_account.generate_new_address().unwrap(), seed_master_account.generate_new_address().unwrap());
+        assert_eq!(external_master_account.generate_new_sub_account_address(2).unwrap(), seed_master_account.generate_new_sub_account_address(2).unwrap());
+        assert!(external_master_account.can_open_sub_account(2));
+        assert!(!external_master_account.can_open_sub_account(3));
+        assert!(matches!(external_master_account.open_sub_account(3), Err(WalletError::ExternalSigner)));
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(Transaction { version: 2, lock_time: 0, input: vec![], output: vec![] }).unwrap();
+        assert!(matches!(external_master_account.sign_transaction(&mut psbt), Err(WalletError::ExternalSigner)));
+    }
+
+    #[test]
+    fn bip39_passphrase_generates_a_different_wallet(){
+        set_up();
+        let mut master_account = MasterAccount::new(get_default_mnenomic_words(), Network::Regtest).unwrap();
+        let mut passphrase_master_account = MasterAccount::new_with_bip39_passphrase(get_default_mnenomic_words(), Some(String::from("extra words")), Network::Regtest, WalletStore::Memory).unwrap();
+
+        assert_ne!(passphrase_master_account.generate_new_address().unwrap(), master_account.generate_new_address().unwrap());
+        assert!(passphrase_master_account.is_generated_from(&get_default_mnenomic_words().unwrap(), Some("extra words")));
+        assert!(!passphrase_master_account.is_generated_from(&get_default_mnenomic_words().unwrap(), None));
+        assert!(!master_account.is_generated_from(&get_default_mnenomic_words().unwrap(), Some("extra words")));
+    }
+
+    #[test]
+    fn multisig_vault_needs_its_cosigners_signature_and_keys(){
+        set_up();
+        let household_words = get_default_mnenomic_words().unwrap();
+        let cosigner_words = get_default_mnenomic_words_2().unwrap();
+        let household_policy = MultisigPolicy::from_cosigner_keys(2, &[get_multisig_cosigner_key(&cosigner_words, 0), get_multisig_cosigner_key(&cosigner_words, 2)], Network::Regtest).unwrap();
+        let cosigner_policy = MultisigPolicy::from_cosigner_keys(2, &[get_multisig_cosigner_key(&household_words, 0)], Network::Regtest).unwrap();
+        let mut vault = MasterAccount::new_multisig(Some(household_words.clone()), None, household_policy, Network::Regtest, WalletStore::Memory).unwrap();
+        let mut cosigner_vault = MasterAccount::new_multisig(Some(cosigner_words.clone()), None, cosigner_policy, Network::Regtest, WalletStore::Memory).unwrap();
+
+        let vault_address = vault.generate_new_address().unwrap();
+        assert_eq!(vault_address, cosigner_vault.generate_new_address().unwrap());
+        assert!(vault_address.script_pubkey().is_v0_p2wsh());
+        assert!(vault.is_generated_from(&household_words, None));
+        assert!(vault.can_open_sub_account(2));
+        assert!(!vault.can_open_sub_account(3));
+        assert!(matches!(vault.open_sub_account(3), Err(WalletError::ExternalSigner)));
+
+        let own_key_as_cosigner = MultisigPolicy::from_cosigner_keys(1, &[get_multisig_cosigner_key(&household_words, 0)], Network::Regtest).unwrap();
+        assert!(matches!(MasterAccount::new_multisig(Some(household_words), None, own_key_as_cosigner, Network::Regtest, WalletStore::Memory), Err(WalletError::InvalidMultisigPolicy)));
+
+        // spend a made up coin received on the vault's address, signing on both devices
+        let txout = TxOut { value: 100_000, script_pubkey: vault_address.script_pubkey() };
+        let utxo = LocalUtxo { outpoint: OutPoint::new(Txid::from_slice(&[1; 32]).unwrap(), 0), txout: txout.clone(), keychain: KeychainKind::External, is_spent: false };
+        let unsigned_tx = Transaction { version: 2, lock_time: 0, input: vec![TxIn { previous_output: utxo.outpoint, ..Default::default() }], output: vec![TxOut { value: 90_000, script_pubkey: vault_address.script_pubkey() }] };
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).unwrap();
+        psbt.inputs[0] = vault.wallet.get_psbt_input(utxo, None, false).unwrap();
+        psbt.inputs[0].witness_utxo = Some(txout);
+        assert_eq!(vault.signatures_needed(&psbt), 2);
+
+        vault.sign_transaction(&mut psbt).unwrap();
+        assert_eq!(vault.signatures_needed(&psbt), 1);
+        assert!(!is_finalized(&psbt));
+
+        cosigner_vault.sign_transaction(&mut psbt).unwrap();
+        assert!(is_finalized(&psbt));
+        assert_eq!(vault.signatures_needed(&psbt), 0);
+    }
+
     #[test]
     fn spend_bitcoin_returns_success_and_reduces_bitcoin_amount(){
         set_up();
         let mock_mnemonic = get_random_mnenomic_words();
 
-        let mut new_master_account = MasterAccount::new(mock_mnemonic).unwrap();
+        let mut new_master_account = MasterAccount::new(mock_mnemonic, Network::Regtest).unwrap();
         attach_wallet_to_regtest_electrum_server(&mut new_master_account);
         aw!(mine_a_block(&new_master_account.generate_new_address().unwrap().to_string()));
         aw!(mine_a_block(&new_master_account.generate_new_address().unwrap().to_string()));
         sleep_while_block_being_mined();
 
 
-        let response = new_master_account.spend_bitcoin(convert_float_to_satoshis(1.0), "tb1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6whp7ax", 1.0);
+        let response = new_master_account.spend_bitcoin(convert_float_to_satoshis(1.0), "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", 1.0);
         test_result_type_is_not_err(response);
         assert_eq!(new_master_account.bitcoin_amount, 99999790);
     }
@@ -349,7 +1590,7 @@ pub mod test {
         // // use get_default_mnenomic_words_2 so that you hae a fresh wallet not connected to the other wallet derived from get_default_mnenomic_words
         let mock_mnemonic = get_default_mnenomic_words_2();
 
-        let mut new_master_account = MasterAccount::new(mock_mnemonic).unwrap();
+        let mut new_master_account = MasterAccount::new(mock_mnemonic, Network::Regtest).unwrap();
         attach_wallet_to_regtest_electrum_server(&mut new_master_account);
 
         let spent_transaction = new_master_account.spend_bitcoin(convert_float_to_satoshis(1.0), &get_base_address(), 1.0);
@@ -367,7 +1608,7 @@ pub mod test {
         // // use get_default_mnenomic_words_2 so that you hae a fresh wallet not connected to the other wallet derived from get_default_mnenomic_words
         let mock_mnemonic = get_default_mnenomic_words_2();
 
-        let mut new_master_account = MasterAccount::new(mock_mnemonic).unwrap();
+        let mut new_master_account = MasterAccount::new(mock_mnemonic, Network::Regtest).unwrap();
         attach_wallet_to_regtest_electrum_server(&mut new_master_account);
         aw!(mine_a_block(&new_master_account.generate_new_address().unwrap().to_string()));
         aw!(mine_a_block(&new_master_account.generate_new_address().unwrap().to_string()));
@@ -390,7 +1631,7 @@ pub mod test {
 
         let mock_mnemonic = get_random_mnenomic_words();
 
-        let mut new_master_account = MasterAccount::new(mock_mnemonic).unwrap();
+        let mut new_master_account = MasterAccount::new(mock_mnemonic, Network::Regtest).unwrap();
         attach_wallet_to_regtest_electrum_server(&mut new_master_account);
         aw!(mine_a_block(&new_master_account.generate_new_address().unwrap().to_string()));
         sleep_while_block_being_mined();
@@ -408,7 +1649,7 @@ pub mod test {
 
         let mock_mnemonic = get_random_mnenomic_words();
 
-        let mut new_master_account = MasterAccount::new(mock_mnemonic).unwrap();
+        let mut new_master_account = MasterAccount::new(mock_mnemonic, Network::Regtest).unwrap();
         attach_wallet_to_regtest_electrum_server(&mut new_master_account);
         // add one btc
         aw!(mine_a_block(&new_master_account.generate_new_address().unwrap().to_string()));
@@ -420,4 +1661,4 @@ pub mod test {
 
         assert_eq!(total_btc, convert_float_to_satoshis(0.6));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use bdk::bitcoin::Network;
+use bdk::bitcoin::util::bip32::{DerivationPath, ExtendedPubKey, Fingerprint};
+use bdk::miniscript::descriptor::DescriptorPublicKey;
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::WalletError;
+use crate::master_account::MasterAccount;
+use crate::seed_store::parse_key_origin;
+
+/// The cosigners of a multisig vault and how many keys must sign its spends.
+///
+/// The vault's wallets are `wsh(sortedmulti(...))` wallets of the household's own key and every cosigner's key, so no single
+/// key, the household's included, can move its coins. Each key is a BIP48 P2WSH account key, `m/48'/coin'/n'/2'`, where account 0
+/// is the vault's own wallet and account `n` the sub-account of child `n`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultisigPolicy {
+    /// How many of the vault's keys must sign a spend.
+    pub threshold: usize,
+    /// Every key of the vault other than the household's own.
+    pub cosigners: Vec<Cosigner>,
+}
+
+/// A key of a multisig vault held by someone else, for example another parent or a recovery service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cosigner {
+    /// The fingerprint of the cosigner's root key.
+    pub fingerprint: Fingerprint,
+    /// The cosigner's account xpubs by account index.
+    pub account_xpubs: BTreeMap<u32, ExtendedPubKey>,
+}
+
+impl MultisigPolicy {
+    /// Build the policy of a vault whose spends need `threshold` signatures from the household's own key and the cosigners' keys.
+    ///
+    /// Each cosigner key is written with its key origin, `[fingerprint/48'/coin'/n'/2']xpub`, and keys with the same fingerprint
+    /// belong to the same cosigner. Every cosigner must give its key of account 0, the keys of other accounts let the children
+    /// with those account indexes have sub-accounts of their own.
+    ///
+    /// # Errors
+    /// If a key can not be parsed, or is not a BIP48 P2WSH account key with its origin on the `network`, return a `WalletError`.
+    /// If there are no cosigners, a cosigner has no key of account 0, or the `threshold` is not between 1 and the number of keys,
+    /// return a `WalletError`.
+    pub fn from_cosigner_keys(threshold: usize, cosigner_keys: &[String], network: Network) -> Result<MultisigPolicy, WalletError> {
+        let mut cosigners: Vec<Cosigner> = vec![];
+        for cosigner_key in cosigner_keys {
+            let key = DescriptorPublicKey::from_str(cosigner_key.trim()).map_err(|_e| WalletError::InvalidDescriptor)?;
+            let (fingerprint, account_index, account_xpub) = parse_key_origin(key, network, MasterAccount::multisig_account_derivation_path)?;
+            match cosigners.iter_mut().find(|cosigner| cosigner.fingerprint == fingerprint) {
+                Some(cosigner) => {
+                    cosigner.account_xpubs.insert(account_index, account_xpub);
+                },
+                None => cosigners.push(Cosigner { fingerprint, account_xpubs: BTreeMap::from([(account_index, account_xpub)]) }),
+            }
+        }
+
+        let multisig_policy = MultisigPolicy { threshold, cosigners };
+        let every_cosigner_has_account_0 = multisig_policy.has_account(0);
+        if multisig_policy.cosigners.is_empty() || !every_cosigner_has_account_0 || threshold == 0 || threshold > multisig_policy.key_count() {
+            return Err(WalletError::InvalidMultisigPolicy)
+        }
+        Ok(multisig_policy)
+    }
+
+    /// How many keys the vault has, the household's own and every cosigner's.
+    pub fn key_count(&self) -> usize {
+        self.cosigners.len() + 1
+    }
+
+    /// Return true if every cosigner has given its key of the account at `account_index`, else return false.
+    pub fn has_account(&self, account_index: u32) -> bool {
+        self.cosigners.iter().all(|cosigner| cosigner.account_xpubs.contains_key(&account_index))
+    }
+
+    /// Build the descriptor of the receive (0) or change (1) keychain of the vault's account at `account_index`, from the
+    /// household's own `account_xpub`, of the root key with `fingerprint`, and the cosigners' keys of the account.
+    ///
+    /// # Errors
+    /// If a cosigner has not given its key of the account return a `WalletError`.
+    pub fn keychain_descriptor(&self, fingerprint: Fingerprint, account_xpub: ExtendedPubKey, network: Network, account_index: u32, keychain_index: u32) -> Result<String, WalletError> {
+        let account_derivation_path = MasterAccount::multisig_account_derivation_path(network, account_index)?;
+        let mut keys = vec![key_with_origin(fingerprint, &account_derivation_path, account_xpub, keychain_index)];
+        for cosigner in &self.cosigners {
+            let cosigner_xpub = cosigner.account_xpubs.get(&account_index).ok_or(WalletError::ExternalSigner)?;
+            keys.push(key_with_origin(cosigner.fingerprint, &account_derivation_path, *cosigner_xpub, keychain_index));
+        }
+        Ok(format!("wsh(sortedmulti({},{}))", self.threshold, keys.join(",")))
+    }
+}
+
+/// Write a keychain's key as it appears in a descriptor, `[fingerprint/path]xpub/keychain/*`.
+fn key_with_origin(fingerprint: Fingerprint, account_derivation_path: &DerivationPath, account_xpub: ExtendedPubKey, keychain_index: u32) -> String {
+    // the derivation path prints as m/48'/..., the key origin is written without the m
+    format!("[{}{}]{}/{}/*", fingerprint, &account_derivation_path.to_string()[1..], account_xpub, keychain_index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosigner_keys_are_grouped_by_fingerprint_and_checked_against_the_threshold(){
+        let account_xpub = "tpubDCsoQ4BZhaV7iNdAcZRp2Agn33KuPjkBD4KJZdqNvuCg72yqCZaxH7aWRjHXvRMoSfHz2NEsxTz2kU11FkmZ3XaD1cVi2YrU1me49LBQCNy";
+        let sub_account_xpub = "tpubDCsoQ4BZhaV7oLysFdxoPcL9mD2a8BqR41ttjjqAgG8837gUqBVfhJBEKdtxEShoXV5CwwDoEZHBMpMyp5LATtvFLVyUujiK6QKT4Ru6bJW";
+        let cosigner_keys = vec![
+            format!("[f149d0a4/48'/1'/0'/2']{}", account_xpub),
+            format!("[f149d0a4/48'/1'/2'/2']{}", sub_account_xpub),
+            format!("[01020304/48'/1'/0'/2']{}", sub_account_xpub),
+        ];
+
+        let multisig_policy = MultisigPolicy::from_cosigner_keys(2, &cosigner_keys, Network::Regtest).unwrap();
+        assert_eq!(multisig_policy.key_count(), 3);
+        assert_eq!(multisig_policy.cosigners[0].account_xpubs.len(), 2);
+        assert!(multisig_policy.has_account(0));
+        assert!(!multisig_policy.has_account(2));
+
+        assert!(matches!(MultisigPolicy::from_cosigner_keys(4, &cosigner_keys, Network::Regtest), Err(WalletError::InvalidMultisigPolicy)));
+        assert!(matches!(MultisigPolicy::from_cosigner_keys(0, &cosigner_keys, Network::Regtest), Err(WalletError::InvalidMultisigPolicy)));
+        assert!(matches!(MultisigPolicy::from_cosigner_keys(1, &[], Network::Regtest), Err(WalletError::InvalidMultisigPolicy)));
+        let without_account_0 = vec![format!("[f149d0a4/48'/1'/2'/2']{}", sub_account_xpub)];
+        assert!(matches!(MultisigPolicy::from_cosigner_keys(1, &without_account_0, Network::Regtest), Err(WalletError::InvalidMultisigPolicy)));
+        let single_signature_key = vec![format!("[f149d0a4/84'/1'/0']{}", account_xpub)];
+        assert!(matches!(MultisigPolicy::from_cosigner_keys(1, &single_signature_key, Network::Regtest), Err(WalletError::InvalidDescriptor)));
+    }
+}
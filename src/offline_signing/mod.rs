@@ -0,0 +1,102 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bdk::bitcoin::consensus::encode::{deserialize, serialize};
+use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::WalletError;
+
+/// The magic bytes a PSBT in its binary format starts with, as BIP174 defines.
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+/// How a PSBT is written for another device to sign.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PsbtEncoding {
+    /// The base64 text most wallets paste and scan.
+    Base64,
+    /// The raw BIP174 bytes, as hardware wallets read from an sd card.
+    Binary,
+}
+
+impl fmt::Display for PsbtEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PsbtEncoding::Base64 => write!(f, "base64"),
+            PsbtEncoding::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+impl FromStr for PsbtEncoding {
+    type Err = String;
+
+    fn from_str(encoding: &str) -> Result<Self, Self::Err> {
+        match encoding {
+            "base64" => Ok(PsbtEncoding::Base64),
+            "binary" => Ok(PsbtEncoding::Binary),
+            _ => Err(format!("unknown psbt encoding {}, expected base64 or binary", encoding)),
+        }
+    }
+}
+
+/// Encode a PSBT to give to another device to sign.
+pub fn encode_psbt(psbt: &PartiallySignedTransaction, encoding: PsbtEncoding) -> Vec<u8> {
+    match encoding {
+        PsbtEncoding::Base64 => psbt.to_string().into_bytes(),
+        PsbtEncoding::Binary => serialize(psbt),
+    }
+}
+
+/// Decode a PSBT a device has signed, in either its binary format or as base64 text.
+///
+/// # Errors
+/// If the bytes are neither a binary PSBT nor base64 text of one return a `WalletError`.
+pub fn decode_psbt(encoded_psbt: &[u8]) -> Result<PartiallySignedTransaction, WalletError> {
+    if encoded_psbt.starts_with(PSBT_MAGIC) {
+        return deserialize(encoded_psbt).map_err(|_e| WalletError::InvalidPsbt)
+    }
+    let base64_psbt = std::str::from_utf8(encoded_psbt).map_err(|_e| WalletError::InvalidPsbt)?;
+    PartiallySignedTransaction::from_str(base64_psbt.trim()).map_err(|_e| WalletError::InvalidPsbt)
+}
+
+/// Return true if every input of the PSBT has been finalized, so its transaction can be extracted and broadcast, else return false.
+pub fn is_finalized(psbt: &PartiallySignedTransaction) -> bool {
+    psbt.inputs.iter().all(|input| input.final_script_sig.is_some() || input.final_script_witness.is_some())
+}
+
+/// How many more signatures the PSBT needs before it can be broadcast, when each input needs `threshold` signatures.
+///
+/// This is the most any input which has not been finalized still needs, so 0 once every input is finalized.
+pub fn signatures_needed(psbt: &PartiallySignedTransaction, threshold: usize) -> usize {
+    psbt.inputs.iter()
+        .filter(|input| input.final_script_sig.is_none() && input.final_script_witness.is_none())
+        .map(|input| threshold.saturating_sub(input.partial_sigs.len()))
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::{OutPoint, Transaction, TxIn, Txid};
+    use bdk::bitcoin::hashes::Hash;
+
+    #[test]
+    fn psbt_decodes_from_either_encoding(){
+        let previous_output = OutPoint::new(Txid::from_slice(&[1; 32]).unwrap(), 0);
+        let unsigned_tx = Transaction { version: 2, lock_time: 0, input: vec![TxIn { previous_output, ..Default::default() }], output: vec![] };
+        let psbt = PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).unwrap();
+
+        let base64_psbt = encode_psbt(&psbt, PsbtEncoding::Base64);
+        let binary_psbt = encode_psbt(&psbt, PsbtEncoding::Binary);
+        assert!(binary_psbt.starts_with(PSBT_MAGIC));
+        assert_eq!(decode_psbt(&base64_psbt).unwrap(), psbt);
+        assert_eq!(decode_psbt(&binary_psbt).unwrap(), psbt);
+        assert_eq!(decode_psbt(format!("{}\n", psbt).as_bytes()).unwrap(), psbt);
+        assert!(matches!(decode_psbt(b"not a psbt"), Err(WalletError::InvalidPsbt)));
+        assert!(!is_finalized(&psbt));
+        assert_eq!(signatures_needed(&psbt, 2), 2);
+    }
+}
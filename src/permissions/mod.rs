@@ -1,7 +1,60 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
 /// The permissions that an `Account` can have.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[derive(PartialEq)]
+#[derive(Serialize, Deserialize)]
 pub enum BitcoinPermissions {
+    /// Spend to any address.
     Send,
+    /// Be issued new addresses to receive bitcoin on.
     Receive,
-}
\ No newline at end of file
+    /// Spend, but only to the `Account`'s whitelisted addresses.
+    /// This restricts an `Account` even if it also has the `Send` permission.
+    SendToWhitelistOnly,
+    /// See the `Account`'s pending transactions and transaction history.
+    ViewTransactionHistory,
+    /// Ask the head of the house for a transfer from the `MasterAccount`.
+    RequestTransfers,
+    /// Add children and grant or revoke the permissions of other children.
+    ManageChildren,
+}
+
+impl BitcoinPermissions {
+    /// The lowercase name of the permission, as accepted by `from_str`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BitcoinPermissions::Send => "send",
+            BitcoinPermissions::Receive => "receive",
+            BitcoinPermissions::SendToWhitelistOnly => "send-to-whitelist-only",
+            BitcoinPermissions::ViewTransactionHistory => "view-transaction-history",
+            BitcoinPermissions::RequestTransfers => "request-transfers",
+            BitcoinPermissions::ManageChildren => "manage-children",
+        }
+    }
+}
+
+impl fmt::Display for BitcoinPermissions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for BitcoinPermissions {
+    type Err = String;
+
+    /// Parse a permission from its lowercase name, for example "send" or "view-transaction-history".
+    fn from_str(permission: &str) -> Result<Self, Self::Err> {
+        match permission {
+            "send" => Ok(BitcoinPermissions::Send),
+            "receive" => Ok(BitcoinPermissions::Receive),
+            "send-to-whitelist-only" => Ok(BitcoinPermissions::SendToWhitelistOnly),
+            "view-transaction-history" => Ok(BitcoinPermissions::ViewTransactionHistory),
+            "request-transfers" => Ok(BitcoinPermissions::RequestTransfers),
+            "manage-children" => Ok(BitcoinPermissions::ManageChildren),
+            _ => Err(format!("unknown permission {}", permission)),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::permissions::BitcoinPermissions;
+
+/// How many unused addresses in a row a restore looks past before deciding a keychain has no more, the same default bdk uses.
+pub const DEFAULT_STOP_GAP: usize = 20;
+
+/// The permissions a child found by a restore is given when no `ChildMetadata` describes it.
+pub fn discovered_child_permissions() -> Vec<BitcoinPermissions> {
+    vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]
+}
+
+/// What a restore needs to know about a child that the blockchain can not tell it.
+///
+/// Parsed from `<id>:<name>` or `<id>:<name>:<permission>,<permission>`, for example `2:alice:send,receive`.
+/// Without permissions the child gets the `discovered_child_permissions`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChildMetadata {
+    pub account_id: i32,
+    pub account_name: String,
+    pub permissions: Vec<BitcoinPermissions>,
+}
+
+impl FromStr for ChildMetadata {
+    type Err = String;
+
+    fn from_str(child: &str) -> Result<Self, Self::Err> {
+        let mut parts = child.splitn(3, ':');
+        let account_id = parts.next().unwrap_or_default().parse::<i32>().map_err(|_e| format!("invalid child {}, expected <id>:<name>", child))?;
+        let account_name = match parts.next() {
+            Some(account_name) if !account_name.is_empty() => String::from(account_name),
+            _ => return Err(format!("invalid child {}, expected <id>:<name>", child)),
+        };
+        let permissions = match parts.next() {
+            Some(permissions) => permissions.split(',').map(BitcoinPermissions::from_str).collect::<Result<Vec<_>, _>>()?,
+            None => discovered_child_permissions(),
+        };
+        Ok(ChildMetadata { account_id, account_name, permissions })
+    }
+}
+
+/// An `Account` rebuilt by a restore.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RestoredAccount {
+    pub account_id: i32,
+    /// True if the child was not in the `ChildMetadata` and was found by scanning the seed's sub-accounts.
+    pub discovered: bool,
+    pub addresses: usize,
+    pub change_addresses: usize,
+    pub pending_transactions: usize,
+    pub bitcoin_amount: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn child_metadata_parses_with_and_without_permissions(){
+        let child_metadata = "2:alice:send,view-transaction-history".parse::<ChildMetadata>().unwrap();
+        assert_eq!(child_metadata.account_id, 2);
+        assert_eq!(child_metadata.account_name, String::from("alice"));
+        assert_eq!(child_metadata.permissions, vec![BitcoinPermissions::Send, BitcoinPermissions::ViewTransactionHistory]);
+
+        assert_eq!("3:bob".parse::<ChildMetadata>().unwrap().permissions, discovered_child_permissions());
+    }
+
+    #[test]
+    fn child_metadata_without_an_id_and_name_is_rejected(){
+        assert!("alice".parse::<ChildMetadata>().is_err());
+        assert!("2".parse::<ChildMetadata>().is_err());
+        assert!("2:alice:spend".parse::<ChildMetadata>().is_err());
+    }
+}
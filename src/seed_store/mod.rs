@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bdk::bitcoin::Network;
+use bdk::bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint};
+use bdk::miniscript::descriptor::{Descriptor, DescriptorPublicKey, DescriptorXKey};
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::WalletError;
+use crate::encryption::{self, KeyDerivation, Sealed};
+use crate::logging::Redacted;
+use crate::master_account::MasterAccount;
+use crate::multisig::MultisigPolicy;
+
+/// How long a `MasterAccount` unlocked with its password keeps its private keys before it locks itself again,
+/// unless another timeout is set.
+pub const DEFAULT_RELOCK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The public keys a locked `MasterAccount` watches its wallets with.
+///
+/// Each BIP84 account `m/84'/coin'/n'` the `MasterAccount` has opened is kept by its account index, the `MasterAccount`'s own
+/// wallet is account 0. The key of an account which has never been opened can only be derived while the `MasterAccount` is unlocked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchOnlyKeys {
+    /// The fingerprint of the root key the accounts are derived from.
+    pub fingerprint: Fingerprint,
+    pub account_xpubs: BTreeMap<u32, ExtendedPubKey>,
+    /// True if the root key is held by an external signer, such as a hardware wallet, and the `MasterAccount` never has it.
+    #[serde(default)]
+    pub external_signer: bool,
+    /// The cosigners and threshold of a multisig vault, whose accounts are the BIP48 P2WSH accounts `m/48'/coin'/n'/2'`
+    /// instead of the BIP84 accounts, or None for a single signature wallet.
+    #[serde(default)]
+    pub multisig: Option<MultisigPolicy>,
+}
+
+impl WatchOnlyKeys {
+    /// Build the keys of a `MasterAccount` whose root key is held by an external signer from the public descriptors it exports.
+    ///
+    /// Each descriptor is either a `wpkh(...)` descriptor of an account's receive or change keychain, or the account's key on its own,
+    /// and its key must be an xpub with its key origin, `[fingerprint/84'/coin'/n']xpub`, so the account index is known and the
+    /// transactions the `MasterAccount` builds tell the external signer which key signs them. One descriptor must be of account 0,
+    /// the `MasterAccount`'s own wallet, and any others give the sub-accounts of children.
+    ///
+    /// # Errors
+    /// If a descriptor can not be parsed, is not a BIP84 account key with its origin on the `network`, or the descriptors are
+    /// of different root keys, or none is of account 0, return a `WalletError`.
+    pub fn from_descriptors(descriptors: &[String], network: Network) -> Result<WatchOnlyKeys, WalletError> {
+        let mut fingerprint = None;
+        let mut account_xpubs = BTreeMap::new();
+        for descriptor in descriptors {
+            let (key_fingerprint, account_index, account_xpub) = parse_account_key(descriptor, network)?;
+            if *fingerprint.get_or_insert(key_fingerprint) != key_fingerprint {
+                return Err(WalletError::InvalidDescriptor)
+            }
+            account_xpubs.insert(account_index, account_xpub);
+        }
+        if !account_xpubs.contains_key(&0) {
+            return Err(WalletError::InvalidDescriptor)
+        }
+
+        Ok(WatchOnlyKeys {
+            fingerprint: fingerprint.ok_or(WalletError::InvalidDescriptor)?,
+            account_xpubs,
+            external_signer: true,
+            multisig: None,
+        })
+    }
+}
+
+/// Parse the root key fingerprint, account index and xpub of the BIP84 account a public descriptor watches.
+///
+/// # Errors
+/// If the descriptor is not a BIP84 account key with its origin on the `network` return a `WalletError`.
+fn parse_account_key(descriptor: &str, network: Network) -> Result<(Fingerprint, u32, ExtendedPubKey), WalletError> {
+    let descriptor = descriptor.trim();
+    let key = if descriptor.starts_with("wpkh(") {
+        match Descriptor::<DescriptorPublicKey>::from_str(descriptor).map_err(|_e| WalletError::InvalidDescriptor)? {
+            Descriptor::Wpkh(wpkh) => wpkh.into_inner(),
+            _ => return Err(WalletError::InvalidDescriptor),
+        }
+    } else {
+        DescriptorPublicKey::from_str(descriptor).map_err(|_e| WalletError::InvalidDescriptor)?
+    };
+    parse_key_origin(key, network, MasterAccount::account_derivation_path)
+}
+
+/// Parse the root key fingerprint, account index and xpub of an account key with its key origin, `[fingerprint/purpose'/coin'/n'...]xpub`.
+///
+/// The account index is the third step of the origin, which must be the `account_derivation_path` of that account on the `network`.
+///
+/// # Errors
+/// If the key has no origin, its origin is not the account's derivation path, or it is not on the `network`, return a `WalletError`.
+pub fn parse_key_origin(key: DescriptorPublicKey, network: Network, account_derivation_path: fn(Network, u32) -> Result<DerivationPath, WalletError>) -> Result<(Fingerprint, u32, ExtendedPubKey), WalletError> {
+    let (fingerprint, origin_path, account_xpub, derivation_path) = match key {
+        DescriptorPublicKey::XPub(DescriptorXKey { origin: Some((fingerprint, origin_path)), xkey, derivation_path, .. }) => (fingerprint, origin_path, xkey, derivation_path),
+        _ => return Err(WalletError::InvalidDescriptor),
+    };
+
+    // only the account key is kept, so a descriptor may only go on to the receive or change keychain
+    let is_account_key = derivation_path.as_ref().is_empty()
+        || derivation_path.as_ref() == [ChildNumber::Normal { index: 0 }]
+        || derivation_path.as_ref() == [ChildNumber::Normal { index: 1 }];
+    let account_index = match origin_path.as_ref().get(2) {
+        Some(ChildNumber::Hardened { index }) => *index,
+        _ => return Err(WalletError::InvalidDescriptor),
+    };
+    let is_mainnet_key = account_xpub.network == Network::Bitcoin;
+    if !is_account_key || is_mainnet_key != (network == Network::Bitcoin) || origin_path != account_derivation_path(network, account_index)? {
+        return Err(WalletError::InvalidDescriptor)
+    }
+    Ok((fingerprint, account_index, account_xpub))
+}
+
+/// What the root key of a `MasterAccount` is generated from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeedSecrets {
+    pub mnemonic_words: Redacted<String>,
+    pub bip39_passphrase: Option<Redacted<String>>,
+}
+
+/// `SeedSecrets` encrypted with the password which unlocks the `MasterAccount`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncryptedSeed {
+    /// The fingerprint of the root key the seed generates, so the seed is only used to unlock the wallet it belongs to.
+    pub fingerprint: Fingerprint,
+    pub key_derivation: KeyDerivation,
+    /// Base64 encoded nonce the seed was encrypted with.
+    pub nonce: String,
+    /// Base64 encoded `SeedSecrets`, encrypted with AES-256-GCM and followed by its authentication tag.
+    pub ciphertext: String,
+}
+
+impl EncryptedSeed {
+    /// Encrypt the `seed_secrets` of the root key with `fingerprint` using a key derived from `password`.
+    ///
+    /// # Errors
+    /// If the seed can not be serialized or encrypted return a `WalletError`.
+    pub fn encrypt(seed_secrets: &SeedSecrets, password: &str, fingerprint: Fingerprint) -> Result<EncryptedSeed, WalletError> {
+        let key_derivation = KeyDerivation::generate()?;
+        let associated_data = associated_data(fingerprint, &key_derivation)?;
+        let plaintext = serde_json::to_vec(seed_secrets).map_err(|_e| WalletError::KeyError)?;
+        let sealed = encryption::seal(&key_derivation, password, &associated_data, plaintext)?;
+
+        Ok(EncryptedSeed {
+            fingerprint,
+            key_derivation,
+            nonce: sealed.nonce,
+            ciphertext: sealed.ciphertext,
+        })
+    }
+
+    /// Decrypt the seed with `password`.
+    ///
+    /// # Errors
+    /// If the password is wrong, or the encrypted seed has been altered, return a `WalletError`.
+    pub fn decrypt(&self, password: &str) -> Result<SeedSecrets, WalletError> {
+        if !self.key_derivation.is_supported() {
+            return Err(WalletError::KeyError)
+        }
+        let associated_data = associated_data(self.fingerprint, &self.key_derivation)?;
+        let sealed = Sealed { nonce: self.nonce.clone(), ciphertext: self.ciphertext.clone() };
+        let plaintext = encryption::open(&self.key_derivation, password, &associated_data, &sealed)?;
+        serde_json::from_slice(&plaintext).map_err(|_e| WalletError::KeyError)
+    }
+}
+
+/// The unencrypted fields of an `EncryptedSeed`, which are authenticated along with its ciphertext.
+fn associated_data(fingerprint: Fingerprint, key_derivation: &KeyDerivation) -> Result<Vec<u8>, WalletError> {
+    serde_json::to_vec(&(fingerprint, key_derivation)).map_err(|_e| WalletError::KeyError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_only_keys_are_read_from_descriptors_with_their_key_origin(){
+        let account_xpub = "tpubDCsoQ4BZhaV7iNdAcZRp2Agn33KuPjkBD4KJZdqNvuCg72yqCZaxH7aWRjHXvRMoSfHz2NEsxTz2kU11FkmZ3XaD1cVi2YrU1me49LBQCNy";
+        let sub_account_xpub = "tpubDCsoQ4BZhaV7oLysFdxoPcL9mD2a8BqR41ttjjqAgG8837gUqBVfhJBEKdtxEShoXV5CwwDoEZHBMpMyp5LATtvFLVyUujiK6QKT4Ru6bJW";
+        let descriptors = vec![
+            format!("wpkh([f149d0a4/84'/1'/0']{}/0/*)#x32wq268", account_xpub),
+            format!("[f149d0a4/84'/1'/2']{}", sub_account_xpub),
+        ];
+
+        let watch_only_keys = WatchOnlyKeys::from_descriptors(&descriptors, Network::Regtest).unwrap();
+        assert_eq!(watch_only_keys.fingerprint, Fingerprint::from_str("f149d0a4").unwrap());
+        assert_eq!(watch_only_keys.account_xpubs.get(&0).unwrap().to_string(), account_xpub);
+        assert_eq!(watch_only_keys.account_xpubs.get(&2).unwrap().to_string(), sub_account_xpub);
+        assert!(watch_only_keys.external_signer);
+
+        let without_origin = vec![format!("wpkh({}/0/*)", account_xpub)];
+        assert!(matches!(WatchOnlyKeys::from_descriptors(&without_origin, Network::Regtest), Err(WalletError::InvalidDescriptor)));
+        let other_network = vec![format!("[f149d0a4/84'/0'/0']{}", account_xpub)];
+        assert!(matches!(WatchOnlyKeys::from_descriptors(&other_network, Network::Bitcoin), Err(WalletError::InvalidDescriptor)));
+        let without_account_0 = vec![format!("[f149d0a4/84'/1'/2']{}", sub_account_xpub)];
+        assert!(matches!(WatchOnlyKeys::from_descriptors(&without_account_0, Network::Regtest), Err(WalletError::InvalidDescriptor)));
+        let other_root_key = vec![descriptors[0].clone(), format!("[01020304/84'/1'/2']{}", sub_account_xpub)];
+        assert!(matches!(WatchOnlyKeys::from_descriptors(&other_root_key, Network::Regtest), Err(WalletError::InvalidDescriptor)));
+    }
+
+    #[test]
+    fn seed_decrypts_only_with_its_password(){
+        let seed_secrets = SeedSecrets {
+            mnemonic_words: Redacted::new(String::from("abandon ability able")),
+            bip39_passphrase: Some(Redacted::new(String::from("extra words"))),
+        };
+        let fingerprint = Fingerprint::from(&[1, 2, 3, 4][..]);
+        let encrypted_seed = EncryptedSeed::encrypt(&seed_secrets, "correct horse", fingerprint).unwrap();
+        assert!(!encrypted_seed.ciphertext.contains("abandon"));
+
+        let decrypted_seed = encrypted_seed.decrypt("correct horse").unwrap();
+        assert_eq!(decrypted_seed.mnemonic_words.expose(), "abandon ability able");
+        assert_eq!(decrypted_seed.bip39_passphrase.unwrap().expose(), "extra words");
+        assert!(matches!(encrypted_seed.decrypt("battery staple"), Err(WalletError::IncorrectPassword)));
+
+        let mut other_wallet_seed = encrypted_seed;
+        other_wallet_seed.fingerprint = Fingerprint::from(&[4, 3, 2, 1][..]);
+        assert!(matches!(other_wallet_seed.decrypt("correct horse"), Err(WalletError::IncorrectPassword)));
+    }
+}
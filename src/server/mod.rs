@@ -0,0 +1,979 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post, put};
+use axum::{Json, Router};
+use bdk::bitcoin::{Address, Txid};
+use bdk::TransactionDetails;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::address_book::{AddressBook, AddressBookEntry};
+use crate::allowances::AllowanceScheduler;
+use crate::batch_payouts::{BatchFeePolicy, BatchPayout};
+use crate::children::Children;
+use crate::custom_errors::{AccountError, ApiError, CliError, StorageError, WalletError};
+use crate::fees::FeeSelection;
+use crate::head_of_the_house::HeadOfTheHouse;
+use crate::master_account::MasterAccount;
+use crate::helpers::get_current_timestamp;
+use crate::offline_signing::{decode_psbt, is_finalized};
+use crate::permissions::BitcoinPermissions;
+use crate::spend_proposals::SpendProposal;
+use crate::spending_limits::{SpendTally, SpendingLimits};
+use crate::storage::HouseholdStorage;
+use crate::transfer_requests;
+
+/// Everything a request needs to act on the household.
+///
+/// When `storage` is set the household is saved after every request which changes it.
+pub struct Household {
+    pub head_of_the_house: HeadOfTheHouse,
+    pub children: Children,
+    pub storage: Option<HouseholdStorage>,
+    pub electrum_url: Option<String>,
+}
+
+impl Household {
+    /// Save the household to its storage, if it has any.
+    ///
+    /// # Errors
+    /// If the ledger can not be written return a `StorageError`.
+    pub fn save(&self) -> Result<(), StorageError> {
+        match &self.storage {
+            Some(storage) => self.head_of_the_house.save(storage, &self.children),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How often the server applies allowances which have come due.
+const ALLOWANCE_TICK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The household shared between every request.
+///
+/// Wallet actions block while they talk to the electrum server, so the household is behind a
+/// `std::sync::Mutex` and only ever used from blocking tasks, one request at a time.
+pub type SharedHousehold = Arc<Mutex<Household>>;
+
+/// An `Account` as returned by the api.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountSummary {
+    pub account_id: i32,
+    pub account_name: String,
+    pub permissions: Vec<BitcoinPermissions>,
+    pub addresses: Vec<Address>,
+    pub bitcoin_transfered_from_master: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceResponse {
+    pub account_id: i32,
+    pub balance: u64,
+    /// The part of the balance held by spend proposals waiting for approval.
+    pub held: u64,
+    pub available: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddressResponse {
+    pub account_id: i32,
+    pub address: Address,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpendRequest {
+    pub amount: u64,
+    pub address: String,
+    /// How to choose the fee rate, by default 1 sat/vB.
+    #[serde(default)]
+    pub fee: FeeSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpendResponse {
+    pub account_id: i32,
+    pub status: String,
+}
+
+/// One payout of a batched spend, `amount` paid to `address` by the account `account_id`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchPayoutRequest {
+    pub account_id: i32,
+    pub address: String,
+    pub amount: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSpendRequest {
+    pub payouts: Vec<BatchPayoutRequest>,
+    /// How the fee is shared between the paying accounts, by default in proportion to what each pays out.
+    #[serde(default)]
+    pub fee_policy: BatchFeePolicy,
+    /// How to choose the fee rate, by default 1 sat/vB.
+    #[serde(default)]
+    pub fee: FeeSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchSpendResponse {
+    pub txid: Txid,
+    pub fee: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BumpFeeRequest {
+    /// How to choose the replacement's fee rate, which must be higher than the pending transaction's.
+    pub fee: FeeSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BumpFeeResponse {
+    pub replaced_txid: Txid,
+    pub txid: Txid,
+    pub fee: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelTransactionRequest {
+    /// How to choose the cancelling transaction's fee rate, which must be higher than the pending transaction's.
+    pub fee: FeeSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelTransactionResponse {
+    pub cancelled_txid: Txid,
+    pub txid: Txid,
+    pub fee: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccelerateRequest {
+    /// The fee rate to raise the unconfirmed payments and the spend accelerating them to.
+    pub fee: FeeSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccelerateResponse {
+    pub account_id: i32,
+    pub txid: Txid,
+    pub fee: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferRequest {
+    pub amount: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferResponse {
+    pub account_id: i32,
+    pub bitcoin_transfered_from_master: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnChainTransferRequest {
+    pub amount: u64,
+    /// How to choose the fee rate, by default 1 sat/vB.
+    #[serde(default)]
+    pub fee: FeeSelection,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OnChainTransferResponse {
+    pub account_id: i32,
+    pub txid: Txid,
+    pub fee: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpendingLimitsResponse {
+    pub account_id: i32,
+    pub spending_limits: SpendingLimits,
+    pub spend_tally: SpendTally,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApprovalThresholdRequest {
+    pub approval_threshold: Option<u64>,
+}
+
+/// A `SpendProposal` as returned by the api, without its transaction.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpendProposalSummary {
+    pub proposal_id: u32,
+    pub account_id: i32,
+    pub amount: u64,
+    pub address: String,
+    pub fee: u64,
+    pub created_at: u64,
+    /// How many more signatures the proposal's transaction needs before it can be broadcast.
+    pub signatures_needed: usize,
+}
+
+impl SpendProposalSummary {
+    fn new(proposal: &SpendProposal, master_account: &MasterAccount) -> Self {
+        SpendProposalSummary {
+            proposal_id: proposal.proposal_id,
+            account_id: proposal.user_id,
+            amount: proposal.amount,
+            address: proposal.address.clone(),
+            fee: proposal.fee,
+            created_at: proposal.created_at,
+            signatures_needed: master_account.signatures_needed(&proposal.psbt),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingSpendAmountResponse {
+    pub account_id: i32,
+    pub pending_spend_amount: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddressBookEntryRequest {
+    pub label: String,
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MaxFeeRateRequest {
+    /// The highest fee rate in sat/vB, or null to remove the maximum.
+    pub max_fee_rate: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WhitelistOnlyRequest {
+    pub whitelist_only: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnlockRequest {
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LockResponse {
+    pub locked: bool,
+}
+
+/// The PSBT of a spend proposal, base64 encoded, to sign on another device.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PsbtResponse {
+    pub proposal_id: u32,
+    pub account_id: i32,
+    pub psbt: String,
+    /// True if every input is signed and the proposal can be broadcast.
+    pub finalized: bool,
+    /// How many more signatures are needed, the cosigners' of a multisig vault.
+    pub signatures_needed: usize,
+}
+
+impl PsbtResponse {
+    fn new(proposal: &SpendProposal, master_account: &MasterAccount) -> Self {
+        PsbtResponse {
+            proposal_id: proposal.proposal_id,
+            account_id: proposal.user_id,
+            psbt: proposal.psbt.to_string(),
+            finalized: is_finalized(&proposal.psbt),
+            signatures_needed: master_account.signatures_needed(&proposal.psbt),
+        }
+    }
+}
+
+/// A PSBT signed on another device, base64 encoded.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PsbtRequest {
+    pub psbt: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PermissionsResponse {
+    pub account_id: i32,
+    pub permissions: Vec<BitcoinPermissions>,
+}
+
+/// Build the api router for a household.
+pub fn router(household: SharedHousehold) -> Router {
+    Router::new()
+        .route("/accounts", get(list_accounts))
+        .route("/accounts/:account_id/balance", get(get_balance))
+        .route("/accounts/:account_id/addresses", post(new_address))
+        .route("/accounts/:account_id/spend", post(spend))
+        .route("/accounts/:account_id/psbt", post(build_unsigned_spend))
+        .route("/accounts/:account_id/accelerate", post(accelerate_incoming_payments))
+        .route("/accounts/:account_id/transfer-from-master", post(transfer_from_master))
+        .route("/accounts/:account_id/transfer-from-master-on-chain", post(transfer_from_master_on_chain))
+        .route("/accounts/:account_id/transfer-to-master", post(transfer_to_master))
+        .route("/accounts/:account_id/pending-spend-amount", get(get_pending_spend_amount))
+        .route("/accounts/:account_id/spending-limits", get(get_spending_limits).put(set_spending_limits))
+        .route("/accounts/:account_id/approval-threshold", put(set_approval_threshold))
+        .route("/accounts/:account_id/permissions/:permission", put(grant_permission).delete(revoke_permission))
+        .route("/accounts/:account_id/transactions", get(get_transaction_history))
+        .route("/accounts/:account_id/address-book", get(get_address_book).post(add_address_book_entry))
+        .route("/accounts/:account_id/address-book/:address", delete(remove_address_book_entry))
+        .route("/accounts/:account_id/whitelist-only", put(set_whitelist_only))
+        .route("/accounts/:account_id/max-fee-rate", put(set_max_fee_rate))
+        .route("/accounts/:account_id/transfer-requests", post(request_transfer))
+        .route("/allowances", get(list_allowances))
+        .route("/batch-spend", post(spend_batch))
+        .route("/spend-proposals", get(list_spend_proposals))
+        .route("/spend-proposals/:proposal_id/approve", post(approve_spend_proposal))
+        .route("/spend-proposals/:proposal_id/reject", post(reject_spend_proposal))
+        .route("/spend-proposals/:proposal_id/psbt", get(export_psbt).put(import_psbt))
+        .route("/spend-proposals/:proposal_id/broadcast", post(broadcast_spend_proposal))
+        .route("/transactions/:txid/bump-fee", post(bump_fee))
+        .route("/transactions/:txid/cancel", post(cancel_transaction))
+        .route("/transfer-requests", get(list_transfer_requests))
+        .route("/transfer-requests/:request_id/approve", post(approve_transfer_request))
+        .route("/transfer-requests/:request_id/reject", post(reject_transfer_request))
+        .route("/unlock", post(unlock))
+        .route("/lock", post(lock))
+        .with_state(household)
+}
+
+/// Serve the api for a household on `address` until the process is stopped.
+///
+/// While serving, allowances which have come due are applied every `ALLOWANCE_TICK_INTERVAL`.
+///
+/// # Errors
+/// If the server can not bind to `address` or stops unexpectedly return a `CliError`.
+pub async fn serve(household: Household, address: SocketAddr) -> Result<(), CliError> {
+    let household = Arc::new(Mutex::new(household));
+    tokio::spawn(tick_allowances_periodically(household.clone()));
+    let app = router(household);
+    axum::Server::try_bind(&address)
+        .map_err(|_e| CliError::ServerError)?
+        .serve(app.into_make_service())
+        .await
+        .map_err(|_e| CliError::ServerError)
+}
+
+/// Apply due allowances every `ALLOWANCE_TICK_INTERVAL`.
+///
+/// Each tick also locks the wallet if its relock timeout has passed, so its private keys are dropped even when nothing is signed.
+/// A tick is skipped if the wallet can not be synced, so allowances are not skipped or queued
+/// because of a stale balance. They are applied on a later tick instead.
+async fn tick_allowances_periodically(household: SharedHousehold) {
+    let mut interval = tokio::time::interval(ALLOWANCE_TICK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let _ = with_household(household.clone(), true, |household| {
+            household.head_of_the_house.master_account.is_locked();
+            sync_household(household)?;
+            household.head_of_the_house.tick_allowances(get_current_timestamp());
+            Ok(())
+        }).await;
+    }
+}
+
+/// Run a household action on a blocking task, saving the household afterwards if `save_changes` is set.
+///
+/// The household is saved even if the action failed, since a spend may have been broadcast before a later step errored.
+async fn with_household<T, F>(household: SharedHousehold, save_changes: bool, action: F) -> Result<Json<T>, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Household) -> Result<T, ApiError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let mut household = household.lock().map_err(|_e| ApiError::HouseholdUnavailable)?;
+        let result = action(&mut household);
+        if save_changes {
+            household.save()?;
+        }
+        result
+    })
+    .await
+    .map_err(|_e| ApiError::HouseholdUnavailable)?
+    .map(Json)
+}
+
+/// Return an error if `account_id` does not belong to any `Account`.
+fn ensure_account_exists(household: &Household, account_id: i32) -> Result<(), ApiError> {
+    match household.head_of_the_house.get_account_by_id(account_id) {
+        Some(_account) => Ok(()),
+        None => Err(AccountError::AccountDoesNotExist("Account does not exist").into()),
+    }
+}
+
+fn sync_household(household: &mut Household) -> Result<(), ApiError> {
+    let electrum_url = household.electrum_url.clone();
+    household.head_of_the_house.master_account.sync_wallet_with_electrum_server(electrum_url.as_deref())?;
+    household.head_of_the_house.settle_pending_cancellations()?;
+    household.head_of_the_house.settle_pending_on_chain_transfers()?;
+    Ok(())
+}
+
+async fn list_accounts(State(household): State<SharedHousehold>) -> Result<Json<Vec<AccountSummary>>, ApiError> {
+    with_household(household, false, |household| {
+        let accounts = household.head_of_the_house.accounts.iter().map(|account| AccountSummary {
+            account_id: account.account_id,
+            account_name: household.children.get_child_by_id(account.account_id).map(|child| child.account_name.clone()).unwrap_or_default(),
+            permissions: account.permissions.clone(),
+            addresses: account.addresses.clone(),
+            bitcoin_transfered_from_master: account.bitcoin_transfered_from_master,
+        }).collect();
+        Ok(accounts)
+    }).await
+}
+
+async fn get_balance(State(household): State<SharedHousehold>, Path(account_id): Path<i32>) -> Result<Json<BalanceResponse>, ApiError> {
+    with_household(household, false, move |household| {
+        ensure_account_exists(household, account_id)?;
+        sync_household(household)?;
+        let balance = household.head_of_the_house.get_account_balance_utxo_amount_plus_transfer_balance(account_id)?;
+        let held = household.head_of_the_house.get_account_held_amount(account_id);
+        Ok(BalanceResponse { account_id, balance, held, available: balance.saturating_sub(held) })
+    }).await
+}
+
+async fn new_address(State(household): State<SharedHousehold>, Path(account_id): Path<i32>) -> Result<Json<AddressResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        let child = household.children.get_child_by_id(account_id).ok_or(ApiError::ChildDoesNotExist)?;
+        let address = child.get_new_address(&mut household.head_of_the_house)?;
+        Ok(AddressResponse { account_id, address })
+    }).await
+}
+
+async fn spend(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(spend_request): Json<SpendRequest>) -> Result<Json<SpendResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        let child = household.children.get_child_by_id(account_id).ok_or(ApiError::ChildDoesNotExist)?;
+        let status = child.spend_bitcoin_with_fee(&mut household.head_of_the_house, spend_request.amount, &spend_request.address, spend_request.fee)?;
+        Ok(SpendResponse { account_id, status: status.to_string() })
+    }).await
+}
+
+async fn spend_batch(State(household): State<SharedHousehold>, Json(batch_spend_request): Json<BatchSpendRequest>) -> Result<Json<BatchSpendResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        let payouts: Vec<BatchPayout> = batch_spend_request.payouts.into_iter()
+            .map(|payout| BatchPayout { user_id: payout.account_id, address: payout.address, amount: payout.amount })
+            .collect();
+        let batch_transaction = household.head_of_the_house.spend_bitcoin_batch(&payouts, batch_spend_request.fee_policy, batch_spend_request.fee)?;
+        Ok(BatchSpendResponse { txid: batch_transaction.txid, fee: batch_transaction.fee })
+    }).await
+}
+
+async fn accelerate_incoming_payments(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(accelerate_request): Json<AccelerateRequest>) -> Result<Json<AccelerateResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        ensure_account_exists(household, account_id)?;
+        sync_household(household)?;
+        let cpfp_transaction = household.head_of_the_house.accelerate_incoming_payments(account_id, accelerate_request.fee)?;
+        Ok(AccelerateResponse { account_id, txid: cpfp_transaction.txid, fee: cpfp_transaction.fee })
+    }).await
+}
+
+async fn transfer_from_master(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(transfer_request): Json<TransferRequest>) -> Result<Json<TransferResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        ensure_account_exists(household, account_id)?;
+        sync_household(household)?;
+        household.head_of_the_house.transfer_bitcoin_from_master_to_child(transfer_request.amount, account_id)?;
+        transfer_response(household, account_id)
+    }).await
+}
+
+async fn transfer_from_master_on_chain(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(transfer_request): Json<OnChainTransferRequest>) -> Result<Json<OnChainTransferResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        ensure_account_exists(household, account_id)?;
+        sync_household(household)?;
+        let transfer_transaction = household.head_of_the_house.transfer_bitcoin_from_master_to_child_on_chain(transfer_request.amount, account_id, transfer_request.fee)?;
+        Ok(OnChainTransferResponse { account_id, txid: transfer_transaction.txid, fee: transfer_transaction.fee })
+    }).await
+}
+
+async fn transfer_to_master(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(transfer_request): Json<TransferRequest>) -> Result<Json<TransferResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        ensure_account_exists(household, account_id)?;
+        household.head_of_the_house.transfer_bitcoin_from_child_to_master(transfer_request.amount, account_id)?;
+        transfer_response(household, account_id)
+    }).await
+}
+
+fn transfer_response(household: &Household, account_id: i32) -> Result<TransferResponse, ApiError> {
+    let account = household.head_of_the_house.get_account_by_id(account_id).ok_or(AccountError::AccountDoesNotExist("Account does not exist"))?;
+    Ok(TransferResponse { account_id, bitcoin_transfered_from_master: account.bitcoin_transfered_from_master })
+}
+
+async fn get_pending_spend_amount(State(household): State<SharedHousehold>, Path(account_id): Path<i32>) -> Result<Json<PendingSpendAmountResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        ensure_account_exists(household, account_id)?;
+        sync_household(household)?;
+        let pending_spend_amount = household.head_of_the_house.get_pending_spend_amount(account_id)?;
+        Ok(PendingSpendAmountResponse { account_id, pending_spend_amount })
+    }).await
+}
+
+async fn get_spending_limits(State(household): State<SharedHousehold>, Path(account_id): Path<i32>) -> Result<Json<SpendingLimitsResponse>, ApiError> {
+    with_household(household, false, move |household| spending_limits_response(household, account_id)).await
+}
+
+async fn set_spending_limits(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(spending_limits): Json<SpendingLimits>) -> Result<Json<SpendingLimitsResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        household.head_of_the_house.set_spending_limits(account_id, spending_limits)?;
+        spending_limits_response(household, account_id)
+    }).await
+}
+
+fn spending_limits_response(household: &Household, account_id: i32) -> Result<SpendingLimitsResponse, ApiError> {
+    let account = household.head_of_the_house.get_account_by_id(account_id).ok_or(AccountError::AccountDoesNotExist("Account does not exist"))?;
+    let spend_tally = household.head_of_the_house.get_account_spend_tally(account_id)?;
+    Ok(SpendingLimitsResponse { account_id, spending_limits: account.spending_limits.clone(), spend_tally })
+}
+
+async fn list_allowances(State(household): State<SharedHousehold>) -> Result<Json<AllowanceScheduler>, ApiError> {
+    with_household(household, false, |household| Ok(household.head_of_the_house.allowance_scheduler.clone())).await
+}
+
+async fn set_approval_threshold(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(approval_threshold_request): Json<ApprovalThresholdRequest>) -> Result<Json<ApprovalThresholdRequest>, ApiError> {
+    with_household(household, true, move |household| {
+        household.head_of_the_house.set_approval_threshold(account_id, approval_threshold_request.approval_threshold)?;
+        Ok(approval_threshold_request)
+    }).await
+}
+
+async fn list_spend_proposals(State(household): State<SharedHousehold>) -> Result<Json<Vec<SpendProposalSummary>>, ApiError> {
+    with_household(household, false, |household| {
+        let head_of_the_house = &household.head_of_the_house;
+        Ok(head_of_the_house.get_spend_proposals().iter().map(|proposal| SpendProposalSummary::new(proposal, &head_of_the_house.master_account)).collect())
+    }).await
+}
+
+async fn approve_spend_proposal(State(household): State<SharedHousehold>, Path(proposal_id): Path<u32>) -> Result<Json<SpendResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        let account_id = household.head_of_the_house.get_spend_proposals().iter()
+            .find(|proposal| proposal.proposal_id == proposal_id)
+            .map(|proposal| proposal.user_id)
+            .ok_or(AccountError::SpendProposalDoesNotExist)?;
+        let status = household.head_of_the_house.approve_spend_proposal(proposal_id)?;
+        Ok(SpendResponse { account_id, status: status.to_string() })
+    }).await
+}
+
+async fn reject_spend_proposal(State(household): State<SharedHousehold>, Path(proposal_id): Path<u32>) -> Result<Json<SpendProposalSummary>, ApiError> {
+    with_household(household, true, move |household| {
+        let proposal = household.head_of_the_house.reject_spend_proposal(proposal_id)?;
+        Ok(SpendProposalSummary::new(&proposal, &household.head_of_the_house.master_account))
+    }).await
+}
+
+async fn build_unsigned_spend(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(spend_request): Json<SpendRequest>) -> Result<Json<PsbtResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        household.children.get_child_by_id(account_id).ok_or(ApiError::ChildDoesNotExist)?;
+        let proposal_id = household.head_of_the_house.build_unsigned_spend(account_id, spend_request.amount, &spend_request.address, spend_request.fee)?;
+        let head_of_the_house = &household.head_of_the_house;
+        Ok(PsbtResponse::new(head_of_the_house.get_spend_proposal(proposal_id)?, &head_of_the_house.master_account))
+    }).await
+}
+
+async fn export_psbt(State(household): State<SharedHousehold>, Path(proposal_id): Path<u32>) -> Result<Json<PsbtResponse>, ApiError> {
+    with_household(household, false, move |household| {
+        let head_of_the_house = &household.head_of_the_house;
+        Ok(PsbtResponse::new(head_of_the_house.get_spend_proposal(proposal_id)?, &head_of_the_house.master_account))
+    }).await
+}
+
+async fn import_psbt(State(household): State<SharedHousehold>, Path(proposal_id): Path<u32>, Json(psbt_request): Json<PsbtRequest>) -> Result<Json<PsbtResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        let signed_psbt = decode_psbt(psbt_request.psbt.as_bytes())?;
+        household.head_of_the_house.import_signed_psbt(proposal_id, signed_psbt)?;
+        let head_of_the_house = &household.head_of_the_house;
+        Ok(PsbtResponse::new(head_of_the_house.get_spend_proposal(proposal_id)?, &head_of_the_house.master_account))
+    }).await
+}
+
+async fn broadcast_spend_proposal(State(household): State<SharedHousehold>, Path(proposal_id): Path<u32>) -> Result<Json<SpendResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        let account_id = household.head_of_the_house.get_spend_proposal(proposal_id)?.user_id;
+        let status = household.head_of_the_house.broadcast_spend_proposal(proposal_id)?;
+        Ok(SpendResponse { account_id, status: status.to_string() })
+    }).await
+}
+
+async fn bump_fee(State(household): State<SharedHousehold>, Path(txid): Path<Txid>, Json(bump_fee_request): Json<BumpFeeRequest>) -> Result<Json<BumpFeeResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        let replacement = household.head_of_the_house.bump_fee(txid, bump_fee_request.fee)?;
+        Ok(BumpFeeResponse { replaced_txid: txid, txid: replacement.txid, fee: replacement.fee })
+    }).await
+}
+
+async fn cancel_transaction(State(household): State<SharedHousehold>, Path(txid): Path<Txid>, Json(cancel_request): Json<CancelTransactionRequest>) -> Result<Json<CancelTransactionResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        let replacement = household.head_of_the_house.cancel_transaction(txid, cancel_request.fee)?;
+        Ok(CancelTransactionResponse { cancelled_txid: txid, txid: replacement.txid, fee: replacement.fee })
+    }).await
+}
+
+async fn grant_permission(State(household): State<SharedHousehold>, Path((account_id, permission)): Path<(i32, String)>) -> Result<Json<PermissionsResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        let permission = parse_permission(&permission)?;
+        household.head_of_the_house.grant_permission(account_id, permission)?;
+        permissions_response(household, account_id)
+    }).await
+}
+
+async fn revoke_permission(State(household): State<SharedHousehold>, Path((account_id, permission)): Path<(i32, String)>) -> Result<Json<PermissionsResponse>, ApiError> {
+    with_household(household, true, move |household| {
+        let permission = parse_permission(&permission)?;
+        household.head_of_the_house.revoke_permission(account_id, permission)?;
+        permissions_response(household, account_id)
+    }).await
+}
+
+fn parse_permission(permission: &str) -> Result<BitcoinPermissions, ApiError> {
+    permission.parse().map_err(|_e| AccountError::Default("Unknown permission").into())
+}
+
+fn permissions_response(household: &Household, account_id: i32) -> Result<PermissionsResponse, ApiError> {
+    let account = household.head_of_the_house.get_account_by_id(account_id).ok_or(AccountError::AccountDoesNotExist("Account does not exist"))?;
+    Ok(PermissionsResponse { account_id, permissions: account.permissions.clone() })
+}
+
+async fn get_transaction_history(State(household): State<SharedHousehold>, Path(account_id): Path<i32>) -> Result<Json<Vec<TransactionDetails>>, ApiError> {
+    with_household(household, false, move |household| {
+        let child = household.children.get_child_by_id(account_id).ok_or(ApiError::ChildDoesNotExist)?.clone();
+        sync_household(household)?;
+        Ok(child.get_transaction_history(&mut household.head_of_the_house)?)
+    }).await
+}
+
+async fn request_transfer(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(transfer_request): Json<TransferRequest>) -> Result<Json<transfer_requests::TransferRequest>, ApiError> {
+    with_household(household, true, move |household| {
+        let child = household.children.get_child_by_id(account_id).ok_or(ApiError::ChildDoesNotExist)?;
+        let request_id = child.request_transfer(&mut household.head_of_the_house, transfer_request.amount)?;
+        household.head_of_the_house.get_transfer_requests().iter()
+            .find(|request| request.request_id == request_id)
+            .cloned()
+            .ok_or_else(|| AccountError::TransferRequestDoesNotExist.into())
+    }).await
+}
+
+async fn list_transfer_requests(State(household): State<SharedHousehold>) -> Result<Json<Vec<transfer_requests::TransferRequest>>, ApiError> {
+    with_household(household, false, |household| Ok(household.head_of_the_house.get_transfer_requests().clone())).await
+}
+
+async fn approve_transfer_request(State(household): State<SharedHousehold>, Path(request_id): Path<u32>) -> Result<Json<transfer_requests::TransferRequest>, ApiError> {
+    with_household(household, true, move |household| {
+        sync_household(household)?;
+        Ok(household.head_of_the_house.approve_transfer_request(request_id)?)
+    }).await
+}
+
+async fn reject_transfer_request(State(household): State<SharedHousehold>, Path(request_id): Path<u32>) -> Result<Json<transfer_requests::TransferRequest>, ApiError> {
+    with_household(household, true, move |household| Ok(household.head_of_the_house.reject_transfer_request(request_id)?)).await
+}
+
+async fn get_address_book(State(household): State<SharedHousehold>, Path(account_id): Path<i32>) -> Result<Json<AddressBook>, ApiError> {
+    with_household(household, false, move |household| Ok(household.head_of_the_house.get_address_book(account_id)?.clone())).await
+}
+
+async fn add_address_book_entry(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(entry_request): Json<AddressBookEntryRequest>) -> Result<Json<AddressBookEntry>, ApiError> {
+    with_household(household, true, move |household| {
+        Ok(household.head_of_the_house.add_address_book_entry(account_id, entry_request.label, &entry_request.address)?)
+    }).await
+}
+
+async fn remove_address_book_entry(State(household): State<SharedHousehold>, Path((account_id, address)): Path<(i32, String)>) -> Result<Json<AddressBookEntry>, ApiError> {
+    with_household(household, true, move |household| Ok(household.head_of_the_house.remove_address_book_entry(account_id, &address)?)).await
+}
+
+async fn set_whitelist_only(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(whitelist_only_request): Json<WhitelistOnlyRequest>) -> Result<Json<WhitelistOnlyRequest>, ApiError> {
+    with_household(household, true, move |household| {
+        household.head_of_the_house.set_whitelist_only(account_id, whitelist_only_request.whitelist_only)?;
+        Ok(whitelist_only_request)
+    }).await
+}
+
+async fn set_max_fee_rate(State(household): State<SharedHousehold>, Path(account_id): Path<i32>, Json(max_fee_rate_request): Json<MaxFeeRateRequest>) -> Result<Json<MaxFeeRateRequest>, ApiError> {
+    with_household(household, true, move |household| {
+        household.head_of_the_house.set_max_fee_rate(account_id, max_fee_rate_request.max_fee_rate)?;
+        Ok(max_fee_rate_request)
+    }).await
+}
+
+async fn unlock(State(household): State<SharedHousehold>, Json(unlock_request): Json<UnlockRequest>) -> Result<Json<LockResponse>, ApiError> {
+    with_household(household, false, move |household| {
+        let storage = household.storage.as_ref().ok_or(WalletError::NoEncryptedSeed)?;
+        household.head_of_the_house.unlock(storage, &unlock_request.password)?;
+        Ok(LockResponse { locked: false })
+    }).await
+}
+
+async fn lock(State(household): State<SharedHousehold>) -> Result<Json<LockResponse>, ApiError> {
+    with_household(household, false, |household| {
+        household.head_of_the_house.master_account.lock();
+        Ok(LockResponse { locked: true })
+    }).await
+}
+
+impl ApiError {
+    /// The http status code the error is returned with.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::ChildDoesNotExist => StatusCode::NOT_FOUND,
+            ApiError::Account(AccountError::AccountDoesNotExist(_)) => StatusCode::NOT_FOUND,
+            ApiError::Account(AccountError::InsufficientAccount) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Account(AccountError::InvalidAddress) => StatusCode::BAD_REQUEST,
+            ApiError::Account(AccountError::SpendingLimitExceeded(_, _)) => StatusCode::FORBIDDEN,
+            ApiError::Account(AccountError::SpendProposalDoesNotExist) => StatusCode::NOT_FOUND,
+            ApiError::Account(AccountError::TransferRequestDoesNotExist) => StatusCode::NOT_FOUND,
+            ApiError::Account(AccountError::MissingPermission(_)) => StatusCode::FORBIDDEN,
+            ApiError::Account(AccountError::AddressNotWhitelisted) => StatusCode::FORBIDDEN,
+            ApiError::Account(AccountError::AddressBookEntryAlreadyExists) => StatusCode::CONFLICT,
+            ApiError::Account(AccountError::AddressBookEntryDoesNotExist) => StatusCode::NOT_FOUND,
+            ApiError::Account(AccountError::FeeRateAboveMaximum(_)) => StatusCode::FORBIDDEN,
+            ApiError::Account(AccountError::FeeRateTooLow) => StatusCode::BAD_REQUEST,
+            ApiError::Account(AccountError::WalletLocked) => StatusCode::LOCKED,
+            ApiError::Account(AccountError::ExternalSigner) => StatusCode::CONFLICT,
+            ApiError::Account(AccountError::PsbtMismatch) => StatusCode::BAD_REQUEST,
+            ApiError::Account(AccountError::UnsignedTransaction) => StatusCode::CONFLICT,
+            ApiError::Account(AccountError::TransactionNotPending) => StatusCode::CONFLICT,
+            ApiError::Account(AccountError::NoUnconfirmedPayments) => StatusCode::CONFLICT,
+            ApiError::Account(AccountError::EmptyBatch) => StatusCode::BAD_REQUEST,
+            ApiError::Account(AccountError::ApprovalRequired) => StatusCode::FORBIDDEN,
+            ApiError::Account(AccountError::Default(_)) => StatusCode::BAD_REQUEST,
+            ApiError::Wallet(WalletError::AddressError) => StatusCode::BAD_REQUEST,
+            ApiError::Wallet(WalletError::NetworkError) => StatusCode::BAD_REQUEST,
+            ApiError::Wallet(WalletError::SyncElectrumError) => StatusCode::BAD_GATEWAY,
+            ApiError::Wallet(WalletError::BroadcastTransactionError) => StatusCode::BAD_GATEWAY,
+            ApiError::Wallet(WalletError::KeyError) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Wallet(WalletError::MissingPermission(_)) => StatusCode::FORBIDDEN,
+            ApiError::Wallet(WalletError::FeeEstimationError) => StatusCode::BAD_GATEWAY,
+            ApiError::Wallet(WalletError::DatabaseError) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Wallet(WalletError::WalletLocked) => StatusCode::LOCKED,
+            ApiError::Wallet(WalletError::IncorrectPassword) => StatusCode::UNAUTHORIZED,
+            ApiError::Wallet(WalletError::NoEncryptedSeed) => StatusCode::CONFLICT,
+            ApiError::Wallet(WalletError::InvalidDescriptor) => StatusCode::BAD_REQUEST,
+            ApiError::Wallet(WalletError::ExternalSigner) => StatusCode::CONFLICT,
+            ApiError::Wallet(WalletError::InvalidPsbt) => StatusCode::BAD_REQUEST,
+            ApiError::Wallet(WalletError::UnsignedTransaction) => StatusCode::CONFLICT,
+            ApiError::Wallet(WalletError::InvalidMultisigPolicy) => StatusCode::BAD_REQUEST,
+            ApiError::Wallet(WalletError::TransactionNotPending) => StatusCode::CONFLICT,
+            ApiError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::HouseholdUnavailable => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// A stable name for the error, so clients do not need to match on the message.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::HouseholdUnavailable => "HouseholdUnavailable",
+            ApiError::ChildDoesNotExist => "ChildDoesNotExist",
+            ApiError::Account(AccountError::AccountDoesNotExist(_)) => "AccountDoesNotExist",
+            ApiError::Account(AccountError::InsufficientAccount) => "InsufficientAccount",
+            ApiError::Account(AccountError::InvalidAddress) => "InvalidAddress",
+            ApiError::Account(AccountError::SpendingLimitExceeded(_, _)) => "SpendingLimitExceeded",
+            ApiError::Account(AccountError::SpendProposalDoesNotExist) => "SpendProposalDoesNotExist",
+            ApiError::Account(AccountError::TransferRequestDoesNotExist) => "TransferRequestDoesNotExist",
+            ApiError::Account(AccountError::MissingPermission(_)) => "MissingPermission",
+            ApiError::Account(AccountError::AddressNotWhitelisted) => "AddressNotWhitelisted",
+            ApiError::Account(AccountError::AddressBookEntryAlreadyExists) => "AddressBookEntryAlreadyExists",
+            ApiError::Account(AccountError::AddressBookEntryDoesNotExist) => "AddressBookEntryDoesNotExist",
+            ApiError::Account(AccountError::FeeRateAboveMaximum(_)) => "FeeRateAboveMaximum",
+            ApiError::Account(AccountError::FeeRateTooLow) => "FeeRateTooLow",
+            ApiError::Account(AccountError::WalletLocked) => "WalletLocked",
+            ApiError::Account(AccountError::ExternalSigner) => "ExternalSigner",
+            ApiError::Account(AccountError::PsbtMismatch) => "PsbtMismatch",
+            ApiError::Account(AccountError::UnsignedTransaction) => "UnsignedTransaction",
+            ApiError::Account(AccountError::TransactionNotPending) => "TransactionNotPending",
+            ApiError::Account(AccountError::NoUnconfirmedPayments) => "NoUnconfirmedPayments",
+            ApiError::Account(AccountError::EmptyBatch) => "EmptyBatch",
+            ApiError::Account(AccountError::ApprovalRequired) => "ApprovalRequired",
+            ApiError::Account(AccountError::Default(_)) => "AccountError",
+            ApiError::Wallet(WalletError::SyncElectrumError) => "SyncElectrumError",
+            ApiError::Wallet(WalletError::AddressError) => "AddressError",
+            ApiError::Wallet(WalletError::BroadcastTransactionError) => "BroadcastTransactionError",
+            ApiError::Wallet(WalletError::KeyError) => "KeyError",
+            ApiError::Wallet(WalletError::NetworkError) => "NetworkError",
+            ApiError::Wallet(WalletError::MissingPermission(_)) => "MissingPermission",
+            ApiError::Wallet(WalletError::FeeEstimationError) => "FeeEstimationError",
+            ApiError::Wallet(WalletError::DatabaseError) => "DatabaseError",
+            ApiError::Wallet(WalletError::WalletLocked) => "WalletLocked",
+            ApiError::Wallet(WalletError::IncorrectPassword) => "IncorrectPassword",
+            ApiError::Wallet(WalletError::NoEncryptedSeed) => "NoEncryptedSeed",
+            ApiError::Wallet(WalletError::InvalidDescriptor) => "InvalidDescriptor",
+            ApiError::Wallet(WalletError::ExternalSigner) => "ExternalSigner",
+            ApiError::Wallet(WalletError::InvalidPsbt) => "InvalidPsbt",
+            ApiError::Wallet(WalletError::UnsignedTransaction) => "UnsignedTransaction",
+            ApiError::Wallet(WalletError::InvalidMultisigPolicy) => "InvalidMultisigPolicy",
+            ApiError::Wallet(WalletError::TransactionNotPending) => "TransactionNotPending",
+            ApiError::Storage(_) => "StorageError",
+        }
+    }
+}
+
+/// Errors are returned as `{"error": {"kind": ..., "message": ...}}` with a matching status code.
+///
+/// A spending limit error also includes the `period` whose limit was exceeded and the account's `spend_tally`,
+/// and a permission error includes the missing `permission`.
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = json!({
+            "error": {
+                "kind": self.kind(),
+                "message": self.to_string(),
+            }
+        });
+        if let ApiError::Account(AccountError::SpendingLimitExceeded(period, tally)) = &self {
+            body["error"]["period"] = json!(period);
+            body["error"]["spend_tally"] = json!(tally);
+        }
+        if let ApiError::Account(AccountError::MissingPermission(permission)) | ApiError::Wallet(WalletError::MissingPermission(permission)) = &self {
+            body["error"]["permission"] = json!(permission.name());
+        }
+        (self.status_code(), Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::Network;
+    use crate::testing_helpers::{get_default_mnenomic_words, set_up};
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    fn test_household() -> SharedHousehold {
+        let mut children = Children::new();
+        let mut head_of_the_house = HeadOfTheHouse::new(&mut children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+        head_of_the_house.create_new_user(&mut children, 2, String::from("alice"), vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+        Arc::new(Mutex::new(Household { head_of_the_house, children, storage: None, electrum_url: None }))
+    }
+
+    #[test]
+    fn list_accounts_returns_every_account_with_its_child_name(){
+        set_up();
+        let household = test_household();
+
+        let Json(accounts) = aw!(list_accounts(State(household))).unwrap();
+
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[1].account_id, 2);
+        assert_eq!(accounts[1].account_name, String::from("alice"));
+    }
+
+    #[test]
+    fn new_address_is_assigned_to_the_account(){
+        set_up();
+        let household = test_household();
+
+        let Json(address_response) = aw!(new_address(State(household.clone()), Path(2))).unwrap();
+
+        let household = household.lock().unwrap();
+        let account = household.head_of_the_house.get_account_by_id(2).unwrap();
+        assert_eq!(account.addresses, vec![address_response.address]);
+    }
+
+    #[test]
+    fn unknown_child_returns_not_found(){
+        set_up();
+        let household = test_household();
+
+        let error = aw!(new_address(State(household), Path(7))).unwrap_err();
+
+        assert!(matches!(error, ApiError::ChildDoesNotExist));
+        assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn unlock_without_an_encrypted_seed_is_a_conflict(){
+        set_up();
+        let household = test_household();
+
+        let error = aw!(unlock(State(household.clone()), Json(UnlockRequest { password: String::from("hunter2") }))).unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+        assert_eq!(error.kind(), "NoEncryptedSeed");
+
+        let Json(lock_response) = aw!(lock(State(household.clone()))).unwrap();
+        assert!(lock_response.locked);
+        assert!(household.lock().unwrap().head_of_the_house.master_account.is_locked());
+    }
+
+    #[test]
+    fn transfer_to_master_without_transfered_funds_is_unprocessable(){
+        set_up();
+        let household = test_household();
+
+        let error = aw!(transfer_to_master(State(household), Path(2), Json(TransferRequest { amount: 1000 }))).unwrap_err();
+
+        assert!(matches!(error, ApiError::Account(AccountError::InsufficientAccount)));
+        assert_eq!(error.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.kind(), "InsufficientAccount");
+    }
+
+    #[test]
+    fn set_approval_threshold_updates_the_account(){
+        set_up();
+        let household = test_household();
+
+        let _ = aw!(set_approval_threshold(State(household.clone()), Path(2), Json(ApprovalThresholdRequest { approval_threshold: Some(10000) }))).unwrap();
+
+        let household = household.lock().unwrap();
+        assert_eq!(household.head_of_the_house.get_account_by_id(2).unwrap().approval_threshold, Some(10000));
+    }
+
+    #[test]
+    fn rejecting_unknown_spend_proposal_returns_not_found(){
+        set_up();
+        let household = test_household();
+
+        let error = aw!(reject_spend_proposal(State(household), Path(1))).unwrap_err();
+
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(error.kind(), "SpendProposalDoesNotExist");
+    }
+
+    #[test]
+    fn granted_permission_lets_a_child_request_transfers(){
+        set_up();
+        let household = test_household();
+
+        let error = aw!(request_transfer(State(household.clone()), Path(2), Json(TransferRequest { amount: 5000 }))).unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::FORBIDDEN);
+        assert_eq!(error.kind(), "MissingPermission");
+
+        let Json(permissions_response) = aw!(grant_permission(State(household.clone()), Path((2, String::from("request-transfers"))))).unwrap();
+        assert!(permissions_response.permissions.contains(&BitcoinPermissions::RequestTransfers));
+        let Json(transfer_request) = aw!(request_transfer(State(household.clone()), Path(2), Json(TransferRequest { amount: 5000 }))).unwrap();
+        let Json(rejected_request) = aw!(reject_transfer_request(State(household), Path(transfer_request.request_id))).unwrap();
+        assert_eq!(rejected_request.amount, 5000);
+    }
+
+    #[test]
+    fn unknown_permission_is_a_bad_request(){
+        set_up();
+        let household = test_household();
+
+        let error = aw!(revoke_permission(State(household), Path((2, String::from("fly"))))).unwrap_err();
+
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn address_book_entries_are_added_and_conflicts_rejected(){
+        set_up();
+        let household = test_household();
+        let entry_request = || AddressBookEntryRequest { label: String::from("grandma"), address: String::from("bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20") };
+
+        let _ = aw!(add_address_book_entry(State(household.clone()), Path(2), Json(entry_request()))).unwrap();
+        let error = aw!(add_address_book_entry(State(household.clone()), Path(2), Json(entry_request()))).unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+
+        let Json(address_book) = aw!(get_address_book(State(household), Path(2))).unwrap();
+        assert_eq!(address_book.entries.len(), 1);
+    }
+}
@@ -0,0 +1,159 @@
+use bdk::bitcoin::util::psbt::PartiallySignedTransaction;
+use bdk::bitcoin::{Address, OutPoint};
+use bdk::TransactionDetails;
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::AccountError;
+
+/// A spend by an `Account` which is waiting for the head of the house to approve it, or for its PSBT to be signed on another device.
+///
+/// The transaction is built when the spend is requested but is only signed and broadcast once approved.
+/// The `psbt` collects the signatures of PSBTs signed elsewhere and imported into the proposal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpendProposal {
+    pub proposal_id: u32,
+    pub user_id: i32,
+    pub amount: u64,
+    pub address: String,
+    pub fee: u64,
+    /// Unix timestamp, in seconds, of when the spend was requested.
+    pub created_at: u64,
+    pub psbt: PartiallySignedTransaction,
+    pub transaction_details: TransactionDetails,
+    /// The `Account`'s change address paid by the transaction, added to the `Account` once the transaction is broadcast.
+    #[serde(default)]
+    pub change_address: Option<Address>,
+}
+
+impl SpendProposal {
+    /// The total the proposal holds from the `Account`'s balance, the amount plus the fee.
+    pub fn held_amount(&self) -> u64 {
+        self.amount + self.fee
+    }
+}
+
+/// The spend proposals waiting for approval.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpendProposals {
+    pub proposals: Vec<SpendProposal>,
+    next_proposal_id: u32,
+}
+
+impl SpendProposals {
+    /// Store a proposal to spend `amount` to `address` with the built `psbt`, and return its id.
+    pub fn add(&mut self, user_id: i32, amount: u64, address: &str, created_at: u64, psbt: PartiallySignedTransaction, transaction_details: TransactionDetails) -> u32 {
+        self.next_proposal_id += 1;
+        self.proposals.push(SpendProposal {
+            proposal_id: self.next_proposal_id,
+            user_id,
+            amount,
+            address: address.to_string(),
+            fee: transaction_details.fee.unwrap_or(0),
+            created_at,
+            psbt,
+            transaction_details,
+            change_address: None,
+        });
+        self.next_proposal_id
+    }
+
+    /// Remove a proposal, returning it.
+    ///
+    /// # Errors
+    /// If no proposal has the `proposal_id` return an `AccountError`.
+    pub fn remove(&mut self, proposal_id: u32) -> Result<SpendProposal, AccountError> {
+        let index = self.proposals.iter().position(|proposal| proposal.proposal_id == proposal_id).ok_or(AccountError::SpendProposalDoesNotExist)?;
+        Ok(self.proposals.remove(index))
+    }
+
+    /// Get a proposal.
+    ///
+    /// # Errors
+    /// If no proposal has the `proposal_id` return an `AccountError`.
+    pub fn get(&self, proposal_id: u32) -> Result<&SpendProposal, AccountError> {
+        self.proposals.iter().find(|proposal| proposal.proposal_id == proposal_id).ok_or(AccountError::SpendProposalDoesNotExist)
+    }
+
+    /// Get a proposal to change, for example to add the signatures of an imported PSBT.
+    ///
+    /// # Errors
+    /// If no proposal has the `proposal_id` return an `AccountError`.
+    pub fn get_mut(&mut self, proposal_id: u32) -> Result<&mut SpendProposal, AccountError> {
+        self.proposals.iter_mut().find(|proposal| proposal.proposal_id == proposal_id).ok_or(AccountError::SpendProposalDoesNotExist)
+    }
+
+    /// Put a removed proposal back, for example if approving it failed.
+    pub fn restore(&mut self, proposal: SpendProposal) {
+        self.proposals.push(proposal);
+        self.proposals.sort_by_key(|proposal| proposal.proposal_id);
+    }
+
+    /// Get an `Account`'s proposals.
+    pub fn get_user_proposals(&self, user_id: i32) -> Vec<&SpendProposal> {
+        self.proposals.iter().filter(|proposal| proposal.user_id == user_id).collect()
+    }
+
+    /// The total held from an `Account`'s balance by its proposals.
+    pub fn get_user_held_amount(&self, user_id: i32) -> u64 {
+        self.get_user_proposals(user_id).iter().map(|proposal| proposal.held_amount()).sum()
+    }
+
+    /// The outputs spent by every proposal, which must not be spent by any other transaction while the proposals wait.
+    pub fn get_reserved_outpoints(&self) -> Vec<OutPoint> {
+        self.proposals.iter()
+            .flat_map(|proposal| proposal.psbt.unsigned_tx.input.iter().map(|input| input.previous_output))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bdk::bitcoin::{Transaction, TxIn, Txid};
+    use bdk::bitcoin::hashes::Hash;
+
+    fn mock_psbt_and_details(input_txid_byte: u8, fee: u64) -> (PartiallySignedTransaction, TransactionDetails) {
+        let previous_output = OutPoint::new(Txid::from_slice(&[input_txid_byte; 32]).unwrap(), 0);
+        let unsigned_tx = Transaction { version: 2, lock_time: 0, input: vec![TxIn { previous_output, ..Default::default() }], output: vec![] };
+        let transaction_details = TransactionDetails {
+            transaction: None,
+            txid: unsigned_tx.txid(),
+            received: 0,
+            sent: 0,
+            fee: Some(fee),
+            confirmation_time: None,
+        };
+        (PartiallySignedTransaction::from_unsigned_tx(unsigned_tx).unwrap(), transaction_details)
+    }
+
+    #[test]
+    fn held_amount_totals_a_users_proposals_and_fees(){
+        let mut spend_proposals = SpendProposals::default();
+        let (psbt, details) = mock_psbt_and_details(1, 141);
+        spend_proposals.add(2, 10000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", 0, psbt, details);
+        let (psbt, details) = mock_psbt_and_details(2, 200);
+        spend_proposals.add(2, 5000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", 0, psbt, details);
+        let (psbt, details) = mock_psbt_and_details(3, 141);
+        spend_proposals.add(3, 7000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", 0, psbt, details);
+
+        assert_eq!(spend_proposals.get_user_held_amount(2), 15341);
+        assert_eq!(spend_proposals.get_user_held_amount(3), 7141);
+        assert_eq!(spend_proposals.get_reserved_outpoints().len(), 3);
+    }
+
+    #[test]
+    fn removed_proposal_can_be_restored(){
+        let mut spend_proposals = SpendProposals::default();
+        let (psbt, details) = mock_psbt_and_details(1, 141);
+        let first_proposal_id = spend_proposals.add(2, 10000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", 0, psbt, details);
+        let (psbt, details) = mock_psbt_and_details(2, 141);
+        spend_proposals.add(2, 5000, "bcrt1qapswup3gzwzmwqp9sk7s5zvm3v9n00x6v7cn20", 0, psbt, details);
+
+        let removed_proposal = spend_proposals.remove(first_proposal_id).unwrap();
+        assert_eq!(removed_proposal.amount, 10000);
+        assert!(matches!(spend_proposals.remove(first_proposal_id), Err(AccountError::SpendProposalDoesNotExist)));
+
+        spend_proposals.restore(removed_proposal);
+        assert_eq!(spend_proposals.proposals[0].proposal_id, first_proposal_id);
+    }
+}
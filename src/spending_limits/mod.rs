@@ -0,0 +1,145 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The number of seconds in each rolling spending period.
+const SECONDS_IN_A_DAY: u64 = 24 * 60 * 60;
+const SECONDS_IN_A_WEEK: u64 = 7 * SECONDS_IN_A_DAY;
+const SECONDS_IN_A_MONTH: u64 = 30 * SECONDS_IN_A_DAY;
+
+/// The limits on how many satoshis an `Account` may spend.
+///
+/// Each limit is optional, a limit of `None` is not enforced. The daily, weekly and monthly limits
+/// are enforced over rolling periods of the last 24 hours, 7 days and 30 days.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpendingLimits {
+    pub max_per_transaction: Option<u64>,
+    pub daily: Option<u64>,
+    pub weekly: Option<u64>,
+    pub monthly: Option<u64>,
+}
+
+/// A spend made by an `Account`, kept to enforce its `SpendingLimits`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpendRecord {
+    pub amount: u64,
+    /// Unix timestamp, in seconds, of when the spend was made.
+    pub timestamp: u64,
+}
+
+/// The spending period a `SpendingLimits` limit applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SpendingPeriod {
+    Transaction,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl fmt::Display for SpendingPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpendingPeriod::Transaction => write!(f, "per transaction"),
+            SpendingPeriod::Daily => write!(f, "daily"),
+            SpendingPeriod::Weekly => write!(f, "weekly"),
+            SpendingPeriod::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+/// The total satoshis an `Account` has spent in each rolling spending period.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpendTally {
+    pub daily: u64,
+    pub weekly: u64,
+    pub monthly: u64,
+}
+
+impl SpendTally {
+    /// Total the `spend_records` made in the rolling periods ending at `now`.
+    pub fn from_spend_records(spend_records: &[SpendRecord], now: u64) -> SpendTally {
+        let mut tally = SpendTally::default();
+        for spend_record in spend_records {
+            let age = now.saturating_sub(spend_record.timestamp);
+            if age < SECONDS_IN_A_DAY {
+                tally.daily += spend_record.amount;
+            }
+            if age < SECONDS_IN_A_WEEK {
+                tally.weekly += spend_record.amount;
+            }
+            if age < SECONDS_IN_A_MONTH {
+                tally.monthly += spend_record.amount;
+            }
+        }
+        tally
+    }
+}
+
+impl SpendingLimits {
+    /// Find the first limit that spending `amount` on top of the `tally` would exceed.
+    ///
+    /// Returns `None` if the spend is within every limit.
+    pub fn exceeded_period(&self, amount: u64, tally: &SpendTally) -> Option<SpendingPeriod> {
+        let limits_and_spent = [
+            (SpendingPeriod::Transaction, self.max_per_transaction, 0),
+            (SpendingPeriod::Daily, self.daily, tally.daily),
+            (SpendingPeriod::Weekly, self.weekly, tally.weekly),
+            (SpendingPeriod::Monthly, self.monthly, tally.monthly),
+        ];
+
+        for (period, limit, already_spent) in limits_and_spent {
+            if let Some(limit) = limit {
+                if already_spent + amount > limit {
+                    return Some(period)
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether spend records older than the longest spending period can be discarded.
+    pub fn is_spend_record_expired(spend_record: &SpendRecord, now: u64) -> bool {
+        now.saturating_sub(spend_record.timestamp) >= SECONDS_IN_A_MONTH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn spend_records() -> Vec<SpendRecord> {
+        vec![
+            SpendRecord { amount: 1000, timestamp: NOW - 60 },
+            SpendRecord { amount: 2000, timestamp: NOW - 3 * SECONDS_IN_A_DAY },
+            SpendRecord { amount: 4000, timestamp: NOW - 20 * SECONDS_IN_A_DAY },
+            SpendRecord { amount: 8000, timestamp: NOW - 40 * SECONDS_IN_A_DAY },
+        ]
+    }
+
+    #[test]
+    fn tally_totals_spends_in_each_rolling_period(){
+        let tally = SpendTally::from_spend_records(&spend_records(), NOW);
+
+        assert_eq!(tally, SpendTally { daily: 1000, weekly: 3000, monthly: 7000 });
+    }
+
+    #[test]
+    fn no_limits_are_never_exceeded(){
+        let tally = SpendTally::from_spend_records(&spend_records(), NOW);
+
+        assert_eq!(SpendingLimits::default().exceeded_period(u64::MAX - tally.monthly, &tally), None);
+    }
+
+    #[test]
+    fn exceeded_period_reports_the_first_limit_exceeded(){
+        let tally = SpendTally::from_spend_records(&spend_records(), NOW);
+        let limits = SpendingLimits { max_per_transaction: Some(5000), daily: None, weekly: Some(6000), monthly: Some(8000) };
+
+        assert_eq!(limits.exceeded_period(500, &tally), None);
+        assert_eq!(limits.exceeded_period(1500, &tally), Some(SpendingPeriod::Monthly));
+        assert_eq!(limits.exceeded_period(3500, &tally), Some(SpendingPeriod::Weekly));
+        assert_eq!(limits.exceeded_period(5001, &tally), Some(SpendingPeriod::Transaction));
+    }
+}
@@ -0,0 +1,251 @@
+use std::path::Path;
+use std::{thread, time};
+
+use bdk::bitcoin::{Address, Network};
+use bdk::sled;
+use bdk::wallet::AddressInfo;
+use bdk::{KeychainKind, TransactionDetails};
+use serde::{Deserialize, Serialize};
+
+use crate::account::Account;
+use crate::allowances::AllowanceScheduler;
+use crate::spend_proposals::SpendProposals;
+use crate::transfer_requests::TransferRequests;
+use crate::child::Child;
+use crate::custom_errors::StorageError;
+use crate::master_account::WalletStore;
+use crate::seed_store::{EncryptedSeed, WatchOnlyKeys};
+
+/// The sled tree holding the `HouseholdLedger`.
+const HOUSEHOLD_TREE_NAME: &str = "household";
+const LEDGER_KEY: &str = "ledger";
+const ENCRYPTED_SEED_KEY: &str = "encrypted_seed";
+/// How many times to try opening the database before giving up.
+const OPEN_ATTEMPTS: u32 = 10;
+
+/// An address generated by the `MasterAccount` wallet, stored alongside the keychain and index it was derived from.
+///
+/// bdk's `AddressInfo` can not be serialized, so this struct is used to persist it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredAddress {
+    pub index: u32,
+    pub address: Address,
+    pub keychain: KeychainKind,
+}
+
+impl From<&AddressInfo> for StoredAddress {
+    fn from(address_info: &AddressInfo) -> Self {
+        StoredAddress {
+            index: address_info.index,
+            address: address_info.address.clone(),
+            keychain: address_info.keychain,
+        }
+    }
+}
+
+impl From<StoredAddress> for AddressInfo {
+    fn from(stored_address: StoredAddress) -> Self {
+        AddressInfo {
+            index: stored_address.index,
+            address: stored_address.address,
+            keychain: stored_address.keychain,
+        }
+    }
+}
+
+/// All of the household state that is not kept in the bdk wallet database.
+///
+/// This includes every `Account` with its permissions, addresses and pending transactions,
+/// every `Child`, the `MasterAccount`'s internal accounting of what it has transfered to children,
+/// the scheduled allowances, the spend proposals and transfer requests waiting for approval, the bitcoin network the household runs on
+/// and the `WatchOnlyKeys` the household's wallets can be loaded with while they are locked.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HouseholdLedger {
+    pub accounts: Vec<Account>,
+    pub children: Vec<Child>,
+    pub all_addresses: Vec<StoredAddress>,
+    pub account_addresses: Vec<StoredAddress>,
+    pub pending_transactions: Vec<TransactionDetails>,
+    pub amount_transfered_to_children: u64,
+    pub network: Network,
+    #[serde(default)]
+    pub allowance_scheduler: AllowanceScheduler,
+    #[serde(default)]
+    pub spend_proposals: SpendProposals,
+    #[serde(default)]
+    pub transfer_requests: TransferRequests,
+    #[serde(default)]
+    pub watch_only_keys: Option<WatchOnlyKeys>,
+}
+
+/// A persistent store for a household, backed by a sled database.
+///
+/// The store holds one tree which is used as the `MasterAccount`'s bdk wallet database, one tree for each
+/// child sub-account's bdk wallet database, and one tree which holds the `HouseholdLedger` and, if the seed has been
+/// encrypted with a password, the `EncryptedSeed`.
+pub struct HouseholdStorage {
+    db: sled::Db,
+}
+
+impl HouseholdStorage {
+    /// Open, or create if it does not exist, the household database at `path`.
+    ///
+    /// sled keeps its file lock until its background flush thread exits, so a database which was just
+    /// closed in this process may briefly refuse to open. Opening is retried a few times to allow for that.
+    ///
+    /// # Errors
+    /// If the database can not be opened return a `StorageError`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<HouseholdStorage, StorageError> {
+        let mut attempts = 1;
+        loop {
+            match sled::open(path.as_ref()) {
+                Ok(db) => return Ok(HouseholdStorage { db }),
+                Err(_error) if attempts < OPEN_ATTEMPTS => {
+                    attempts += 1;
+                    thread::sleep(time::Duration::from_millis(50));
+                },
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// Open a household database which is deleted once it is dropped.
+    ///
+    /// # Errors
+    /// If the database can not be created return a `StorageError`.
+    pub fn open_temporary() -> Result<HouseholdStorage, StorageError> {
+        let db = sled::Config::new().temporary(true).open()?;
+        Ok(HouseholdStorage { db })
+    }
+
+    /// Get the store the `MasterAccount`'s wallets should be kept in.
+    pub fn wallet_store(&self) -> WalletStore {
+        WalletStore::Sled(self.db.clone())
+    }
+
+    /// Write the `HouseholdLedger` to disk, replacing any previously saved ledger.
+    ///
+    /// # Errors
+    /// If the ledger can not be serialized or written return a `StorageError`.
+    pub fn save_ledger(&self, ledger: &HouseholdLedger) -> Result<(), StorageError> {
+        let household_tree = self.db.open_tree(HOUSEHOLD_TREE_NAME)?;
+        let serialized_ledger = serde_json::to_vec(ledger)?;
+        household_tree.insert(LEDGER_KEY, serialized_ledger)?;
+        household_tree.flush()?;
+        Ok(())
+    }
+
+    /// Read the saved `HouseholdLedger`, returning `None` if no household has been saved yet.
+    ///
+    /// # Errors
+    /// If the ledger can not be read or deserialized return a `StorageError`.
+    pub fn load_ledger(&self) -> Result<Option<HouseholdLedger>, StorageError> {
+        let household_tree = self.db.open_tree(HOUSEHOLD_TREE_NAME)?;
+        match household_tree.get(LEDGER_KEY)? {
+            Some(serialized_ledger) => Ok(Some(serde_json::from_slice(&serialized_ledger)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Write the `EncryptedSeed` to disk, replacing any previously saved seed.
+    ///
+    /// # Errors
+    /// If the seed can not be serialized or written return a `StorageError`.
+    pub fn save_encrypted_seed(&self, encrypted_seed: &EncryptedSeed) -> Result<(), StorageError> {
+        let household_tree = self.db.open_tree(HOUSEHOLD_TREE_NAME)?;
+        household_tree.insert(ENCRYPTED_SEED_KEY, serde_json::to_vec(encrypted_seed)?)?;
+        household_tree.flush()?;
+        Ok(())
+    }
+
+    /// Read the saved `EncryptedSeed`, returning `None` if the seed has not been encrypted with a password.
+    ///
+    /// # Errors
+    /// If the seed can not be read or deserialized return a `StorageError`.
+    pub fn load_encrypted_seed(&self) -> Result<Option<EncryptedSeed>, StorageError> {
+        let household_tree = self.db.open_tree(HOUSEHOLD_TREE_NAME)?;
+        match household_tree.get(ENCRYPTED_SEED_KEY)? {
+            Some(serialized_seed) => Ok(Some(serde_json::from_slice(&serialized_seed)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::children::Children;
+    use crate::head_of_the_house::HeadOfTheHouse;
+    use crate::permissions::BitcoinPermissions;
+    use crate::testing_helpers::{get_default_mnenomic_words, get_default_mnenomic_words_2, set_up};
+
+    #[test]
+    fn load_ledger_returns_none_for_a_new_household(){
+        set_up();
+        let storage = HouseholdStorage::open_temporary().unwrap();
+
+        assert!(storage.load_ledger().unwrap().is_none());
+    }
+
+    #[test]
+    fn saved_household_is_reloaded_with_accounts_children_and_transfers(){
+        set_up();
+        let storage = HouseholdStorage::open_temporary().unwrap();
+        let mut children = Children::new();
+        let mut head_of_house = HeadOfTheHouse::load(&storage, &mut children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+        head_of_house.create_new_user(&mut children, 2, String::from("alice"), vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+        let child_address = head_of_house.get_new_address(2).unwrap();
+        head_of_house.get_mut_account_by_id(2).unwrap().receive_transfered_bitcoin(5000);
+        head_of_house.master_account.transfer_bitcoin(5000);
+        head_of_house.save(&storage, &children).unwrap();
+        drop(head_of_house);
+
+        let mut reloaded_children = Children::new();
+        let mut reloaded_head_of_house = HeadOfTheHouse::load(&storage, &mut reloaded_children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+
+        assert_eq!(reloaded_children.children.len(), 2);
+        assert_eq!(reloaded_children.get_child_by_id(2).unwrap().account_name, String::from("alice"));
+        assert_eq!(reloaded_head_of_house.accounts.len(), 2);
+        assert_eq!(reloaded_head_of_house.master_account.amount_transfered_to_children, 5000);
+        assert_eq!(reloaded_head_of_house.master_account.all_addresses.len(), 1);
+
+        let reloaded_account = reloaded_head_of_house.get_account_by_id(2).unwrap();
+        assert_eq!(reloaded_account.addresses, vec![child_address.clone()]);
+        assert_eq!(reloaded_account.bitcoin_transfered_from_master, 5000);
+        assert_eq!(reloaded_account.permissions, vec![BitcoinPermissions::Send, BitcoinPermissions::Receive]);
+
+        // the wallet database remembers which addresses were already handed out
+        let next_address = reloaded_head_of_house.get_new_address(2).unwrap();
+        assert_ne!(next_address, child_address);
+    }
+
+    #[test]
+    fn loading_a_household_with_different_mnemonic_words_fails(){
+        set_up();
+        let storage = HouseholdStorage::open_temporary().unwrap();
+        let mut children = Children::new();
+        let head_of_house = HeadOfTheHouse::load(&storage, &mut children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+        head_of_house.save(&storage, &children).unwrap();
+        drop(head_of_house);
+
+        let mut reloaded_children = Children::new();
+        let reloaded_head_of_house = HeadOfTheHouse::load(&storage, &mut reloaded_children, get_default_mnenomic_words_2(), Network::Regtest);
+
+        assert!(matches!(reloaded_head_of_house, Err(StorageError::WalletError)));
+    }
+
+    #[test]
+    fn loading_a_household_on_a_different_network_fails(){
+        set_up();
+        let storage = HouseholdStorage::open_temporary().unwrap();
+        let mut children = Children::new();
+        let head_of_house = HeadOfTheHouse::load(&storage, &mut children, get_default_mnenomic_words(), Network::Regtest).unwrap();
+        head_of_house.save(&storage, &children).unwrap();
+        drop(head_of_house);
+
+        let mut reloaded_children = Children::new();
+        let reloaded_head_of_house = HeadOfTheHouse::load(&storage, &mut reloaded_children, get_default_mnenomic_words(), Network::Testnet);
+
+        assert!(matches!(reloaded_head_of_house, Err(StorageError::NetworkMismatch)));
+    }
+}
@@ -4,10 +4,12 @@ use std::{thread, time, env};
 use bdk::bitcoin::psbt::PartiallySignedTransaction;
 use bdk::keys::{ GeneratableKey, GeneratedKey, bip39::{Mnemonic, WordCount, Language}};
 use bdk::{miniscript, TransactionDetails};
-use bdk::bitcoin::Address;
+use bdk::bitcoin::{Address, Network};
+use bdk::bitcoin::secp256k1::Secp256k1;
+use bdk::bitcoin::util::bip32::ExtendedPubKey;
 use std::str::FromStr;
 use bdk::Wallet;
-use bdk::database::MemoryDatabase;
+use bdk::database::AnyDatabase;
 use crate::helpers::convert_float_to_satoshis;
 use bdk::FeeRate;
 
@@ -40,6 +42,18 @@ pub fn get_random_mnenomic_words()-> Option<String>{
     Some(mnemonic_words)
 }
 
+/// Get the regtest multisig vault key, `[fingerprint/48'/1'/n'/2']xpub`, a cosigner with `mnemonic_words` gives for the account at `account_index`.
+/// 
+/// # Panics
+/// If the mnemonic words are invalid.
+pub fn get_multisig_cosigner_key(mnemonic_words: &str, account_index: u32)-> String{
+    let secp = Secp256k1::new();
+    let root_key = MasterAccount::generate_root_key(Some(String::from(mnemonic_words)), Network::Regtest).expect("error generating the cosigner's root key");
+    let account_derivation_path = MasterAccount::multisig_account_derivation_path(Network::Regtest, account_index).expect("error building the cosigner's derivation path");
+    let account_xpub = ExtendedPubKey::from_priv(&secp, &root_key.derive_priv(&secp, &account_derivation_path).expect("error deriving the cosigner's key"));
+    format!("[{}{}]{}", root_key.fingerprint(&secp), &account_derivation_path.to_string()[1..], account_xpub)
+}
+
 /// Automatically mine a block which adds bitcoin to a `receiving_address`.
 /// 
 /// This will happen on the local esplora regtest blockchain that is running on localhost 3000.
@@ -76,7 +90,7 @@ pub fn test_result_type_is_not_err<T, E>(result:Result<T,E>){
 
 /// A global set up function for all tests which will set up all the neccesary environment variables.
 pub fn set_up(){
-    set_env_variables();
+    set_env_variables().unwrap();
 }
 
 /// Get the `test_address` environment variables
@@ -99,7 +113,7 @@ pub fn get_regtest_rpc()-> String {
 /// 
 /// # Panics
 /// If the environment variable `test_address` is an invalid address.
-pub fn build_mock_transaction(wallet:&Wallet<MemoryDatabase>, mock_amount:u64)->(PartiallySignedTransaction, TransactionDetails){
+pub fn build_mock_transaction(wallet:&Wallet<AnyDatabase>, mock_amount:u64)->(PartiallySignedTransaction, TransactionDetails){
     let test_address = get_base_address();
     let receiving_address = Address::from_str(&test_address).expect("Error in build_mock_transaction receiving_address");
 
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::custom_errors::AccountError;
+
+/// A child's request for a transfer from the `MasterAccount`, waiting for the head of the house.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferRequest {
+    pub request_id: u32,
+    pub child_id: i32,
+    pub amount: u64,
+    /// Unix timestamp, in seconds, of when the transfer was requested.
+    pub created_at: u64,
+}
+
+/// The transfer requests waiting for the head of the house.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferRequests {
+    pub requests: Vec<TransferRequest>,
+    next_request_id: u32,
+}
+
+impl TransferRequests {
+    /// Store a request for `amount` to be transfered to a child, and return its id.
+    pub fn add(&mut self, child_id: i32, amount: u64, created_at: u64) -> u32 {
+        self.next_request_id += 1;
+        self.requests.push(TransferRequest {
+            request_id: self.next_request_id,
+            child_id,
+            amount,
+            created_at,
+        });
+        self.next_request_id
+    }
+
+    /// Remove a request, returning it.
+    ///
+    /// # Errors
+    /// If no request has the `request_id` return an `AccountError`.
+    pub fn remove(&mut self, request_id: u32) -> Result<TransferRequest, AccountError> {
+        let index = self.requests.iter().position(|request| request.request_id == request_id).ok_or(AccountError::TransferRequestDoesNotExist)?;
+        Ok(self.requests.remove(index))
+    }
+
+    /// Put a removed request back, for example if approving it failed.
+    pub fn restore(&mut self, request: TransferRequest) {
+        self.requests.push(request);
+        self.requests.sort_by_key(|request| request.request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_removed_by_id(){
+        let mut transfer_requests = TransferRequests::default();
+        let first_request_id = transfer_requests.add(2, 10000, 0);
+        let second_request_id = transfer_requests.add(3, 5000, 0);
+
+        let removed_request = transfer_requests.remove(first_request_id).unwrap();
+
+        assert_eq!(removed_request.child_id, 2);
+        assert_eq!(transfer_requests.requests[0].request_id, second_request_id);
+        assert!(matches!(transfer_requests.remove(first_request_id), Err(AccountError::TransferRequestDoesNotExist)));
+    }
+}